@@ -12,6 +12,7 @@ use jni::JNIEnv;
 use libsm::sm2;
 use libsm::sm2::signature::Signature;
 use libsm::sm3::hash;
+use libsm::sm3::kdf;
 use mlsag::member::Member;
 use num_bigint::BigUint;
 
@@ -53,6 +54,124 @@ fn sm3(env: JNIEnv, input: jbyteArray) -> Result<jbyteArray, ChainErr> {
     Ok(output)
 }
 
+#[no_mangle]
+pub extern "system" fn Java_org_tdf_natives_Crypto_hmacSm3(
+    env: JNIEnv,
+    _class: JClass,
+    _key: jbyteArray,
+    _data: jbyteArray,
+) -> jbyteArray {
+    match hmac_sm3(env, _key, _data) {
+        Ok(o) => o,
+        Err(e) => {
+            env.throw_new(RT_EX, e.0);
+            null_mut()
+        }
+    }
+}
+
+fn hmac_sm3(env: JNIEnv, _key: jbyteArray, _data: jbyteArray) -> Result<jbyteArray, ChainErr> {
+    let key = env.convert_byte_array(_key)?;
+    let data = env.convert_byte_array(_data)?;
+    let mac = libsm::sm3::hmac::hmac_sm3(&key, &data);
+    Ok(env.byte_array_from_slice(&mac)?)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_tdf_natives_Crypto_hkdfSm3(
+    env: JNIEnv,
+    _class: JClass,
+    _salt: jbyteArray,
+    _ikm: jbyteArray,
+    _info: jbyteArray,
+    _len: jint,
+) -> jbyteArray {
+    match hkdf_sm3(env, _salt, _ikm, _info, _len) {
+        Ok(o) => o,
+        Err(e) => {
+            env.throw_new(RT_EX, e.0);
+            null_mut()
+        }
+    }
+}
+
+fn hkdf_sm3(
+    env: JNIEnv,
+    _salt: jbyteArray,
+    _ikm: jbyteArray,
+    _info: jbyteArray,
+    _len: jint,
+) -> Result<jbyteArray, ChainErr> {
+    if _len < 0 {
+        return Err(ChainErr("invalid output length"));
+    }
+    let salt = env.convert_byte_array(_salt)?;
+    let ikm = env.convert_byte_array(_ikm)?;
+    let info = env.convert_byte_array(_info)?;
+    let mut okm = vec![0u8; _len as usize];
+    kdf::hkdf(&salt, &ikm, &info, &mut okm)?;
+    Ok(env.byte_array_from_slice(&okm)?)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_tdf_natives_Crypto_pbkdf2HmacSm3(
+    env: JNIEnv,
+    _class: JClass,
+    _password: jbyteArray,
+    _salt: jbyteArray,
+    _iterations: jint,
+    _len: jint,
+) -> jbyteArray {
+    match pbkdf2_hmac_sm3(env, _password, _salt, _iterations, _len) {
+        Ok(o) => o,
+        Err(e) => {
+            env.throw_new(RT_EX, e.0);
+            null_mut()
+        }
+    }
+}
+
+fn pbkdf2_hmac_sm3(
+    env: JNIEnv,
+    _password: jbyteArray,
+    _salt: jbyteArray,
+    _iterations: jint,
+    _len: jint,
+) -> Result<jbyteArray, ChainErr> {
+    if _len < 0 {
+        return Err(ChainErr("invalid output length"));
+    }
+    let password = env.convert_byte_array(_password)?;
+    let salt = env.convert_byte_array(_salt)?;
+    let mut key = vec![0u8; _len as usize];
+    kdf::pbkdf2_hmac_sm3_checked(&password, &salt, _iterations as u32, &mut key)?;
+    Ok(env.byte_array_from_slice(&key)?)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_tdf_natives_Crypto_sm2Kdf(
+    env: JNIEnv,
+    _class: JClass,
+    _z: jbyteArray,
+    _len: jint,
+) -> jbyteArray {
+    match sm2_kdf(env, _z, _len) {
+        Ok(o) => o,
+        Err(e) => {
+            env.throw_new(RT_EX, e.0);
+            null_mut()
+        }
+    }
+}
+
+fn sm2_kdf(env: JNIEnv, _z: jbyteArray, _len: jint) -> Result<jbyteArray, ChainErr> {
+    if _len < 0 {
+        return Err(ChainErr("invalid output length"));
+    }
+    let z = env.convert_byte_array(_z)?;
+    Ok(env.byte_array_from_slice(&kdf::kdf(&z, _len as usize))?)
+}
+
 #[no_mangle]
 pub extern "system" fn Java_org_tdf_natives_Crypto_sm2PkFromSk(
     env: JNIEnv,
@@ -123,6 +242,12 @@ impl From<sm2::error::Sm2Error> for ChainErr {
     }
 }
 
+impl From<libsm::sm3::Sm3Error> for ChainErr {
+    fn from(e: libsm::sm3::Sm3Error) -> Self {
+        ChainErr(e.into())
+    }
+}
+
 fn sm2_verify(
     env: JNIEnv,
     _seed: jlong,
@@ -130,7 +255,40 @@ fn sm2_verify(
     _pub_key: jbyteArray,
     _sig: jbyteArray,
 ) -> Result<jboolean, ChainErr> {
-    libsm::seed(_seed as u64);
+    // deprecated: the seed is ignored and only kept so the Java signature
+    // doesn't change
+    sm2_verify_id(env, "1234567812345678", _message, _pub_key, _sig)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_tdf_natives_Crypto_sm2VerifyWithId(
+    env: JNIEnv,
+    _class: JClass,
+    _id: JString,
+    _message: jbyteArray,
+    _pub_key: jbyteArray,
+    _sig: jbyteArray,
+) -> jboolean {
+    let r = env
+        .get_string(_id)
+        .map_err(ChainErr::from)
+        .and_then(|id| sm2_verify_id(env, &String::from(id), _message, _pub_key, _sig));
+    match r {
+        Ok(o) => o,
+        Err(e) => {
+            env.throw_new(RT_EX, e.0);
+            0
+        }
+    }
+}
+
+fn sm2_verify_id(
+    env: JNIEnv,
+    id: &str,
+    _message: jbyteArray,
+    _pub_key: jbyteArray,
+    _sig: jbyteArray,
+) -> Result<jboolean, ChainErr> {
     let sig = env.convert_byte_array(_sig)?;
 
     if sig.len() != 64 {
@@ -138,13 +296,13 @@ fn sm2_verify(
     }
 
     let sig_ctx = sm2::signature::SigCtx::new();
-    let s: Signature = Signature::new(&sig[..32], &sig[32..]);
+    let s = Signature::from_bytes(&sig)?;
     let ecc_ctx = sm2::ecc::EccCtx::new();
     let decoded = env.convert_byte_array(_pub_key)?;
     let message = env.convert_byte_array(_message)?;
     let pk = ecc_ctx.bytes_to_point(&decoded)?;
 
-    if sig_ctx.verify(&message, &pk, &s) {
+    if sig_ctx.verify_with_id(id, &message, &pk, &s) {
         Ok(1)
     } else {
         Ok(0)
@@ -178,12 +336,43 @@ fn sm2_sign(
     _priv: jbyteArray,
     _msg: jbyteArray,
 ) -> Result<jbyteArray, ChainErr> {
-    libsm::seed(_seed as u64);
+    // deprecated: signatures are deterministic, the seed is ignored and only
+    // kept so the Java signature doesn't change
+    sm2_sign_id(env, "1234567812345678", _priv, _msg)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_tdf_natives_Crypto_sm2SignWithId(
+    env: JNIEnv,
+    _class: JClass,
+    _id: JString,
+    _priv: jbyteArray,
+    _msg: jbyteArray,
+) -> jbyteArray {
+    let r = env
+        .get_string(_id)
+        .map_err(ChainErr::from)
+        .and_then(|id| sm2_sign_id(env, &String::from(id), _priv, _msg));
+    match r {
+        Ok(o) => o,
+        Err(e) => {
+            env.throw_new(RT_EX, e.0);
+            null_mut()
+        }
+    }
+}
+
+fn sm2_sign_id(
+    env: JNIEnv,
+    id: &str,
+    _priv: jbyteArray,
+    _msg: jbyteArray,
+) -> Result<jbyteArray, ChainErr> {
     let private_key = env.convert_byte_array(_priv)?;
     let message = env.convert_byte_array(_msg)?;
     let c = sm2::signature::SigCtx::new();
     let sk = BigUint::from_bytes_be(&private_key);
-    let sig = c.sign(&message, &sk, &c.pk_from_sk_checked(&sk)?);
+    let sig = c.sign_with_id(id, &message, &sk, &c.pk_from_sk_checked(&sk)?);
     let mut r = [0; 32];
     let mut s = [0; 32];
     let _r = sig.get_r().to_bytes_be();
@@ -196,6 +385,87 @@ fn sm2_sign(
     Ok(env.byte_array_from_slice(&ret)?)
 }
 
+#[no_mangle]
+pub extern "system" fn Java_org_tdf_natives_Crypto_sm2SignRecoverable(
+    env: JNIEnv,
+    // this is the class that owns our
+    // static method. Not going to be
+    // used, but still needs to have
+    // an argument slot
+    _class: JClass,
+    _priv: jbyteArray,
+    _digest: jbyteArray,
+) -> jbyteArray {
+    match sm2_sign_recoverable(env, _priv, _digest) {
+        Ok(o) => o,
+        Err(e) => {
+            env.throw_new(RT_EX, e.0);
+            null_mut()
+        }
+    }
+}
+
+// returns r || s || recovery id
+fn sm2_sign_recoverable(
+    env: JNIEnv,
+    _priv: jbyteArray,
+    _digest: jbyteArray,
+) -> Result<jbyteArray, ChainErr> {
+    let private_key = env.convert_byte_array(_priv)?;
+    let digest = env.convert_byte_array(_digest)?;
+    let c = sm2::signature::SigCtx::new();
+    let sk = BigUint::from_bytes_be(&private_key);
+    let (sig, recid) = c.sign_recoverable_checked(&digest, &sk)?;
+    let mut ret = [0; 65];
+    let _r = sig.get_r().to_bytes_be();
+    ret[32 - _r.len()..32].copy_from_slice(&_r);
+    let _s = sig.get_s().to_bytes_be();
+    ret[64 - _s.len()..64].copy_from_slice(&_s);
+    ret[64] = recid;
+    Ok(env.byte_array_from_slice(&ret)?)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_tdf_natives_Crypto_sm2RecoverPublicKey(
+    env: JNIEnv,
+    // this is the class that owns our
+    // static method. Not going to be
+    // used, but still needs to have
+    // an argument slot
+    _class: JClass,
+    _digest: jbyteArray,
+    _sig: jbyteArray,
+    _compress: jboolean,
+) -> jbyteArray {
+    match sm2_recover_public_key(env, _digest, _sig, _compress) {
+        Ok(o) => o,
+        Err(e) => {
+            env.throw_new(RT_EX, e.0);
+            null_mut()
+        }
+    }
+}
+
+fn sm2_recover_public_key(
+    env: JNIEnv,
+    _digest: jbyteArray,
+    _sig: jbyteArray,
+    _compress: jboolean,
+) -> Result<jbyteArray, ChainErr> {
+    let sig = env.convert_byte_array(_sig)?;
+
+    if sig.len() != 65 {
+        return Err(ChainErr("invalid signature size"));
+    }
+
+    let digest = env.convert_byte_array(_digest)?;
+    let sig_ctx = sm2::signature::SigCtx::new();
+    let ecc_ctx = sm2::ecc::EccCtx::new();
+    let s = Signature::from_bytes(&sig[..64])?;
+    let pk = sig_ctx.recover_public_key(&digest, &s, sig[64])?;
+    Ok(env.byte_array_from_slice(&ecc_ctx.point_to_bytes(&pk, _compress != 0))?)
+}
+
 #[no_mangle]
 pub extern "system" fn Java_org_tdf_natives_Crypto_mlsagGetSk(
     env: JNIEnv,
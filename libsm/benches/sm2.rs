@@ -0,0 +1,95 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[macro_use]
+extern crate criterion;
+extern crate libsm;
+extern crate num_bigint;
+
+use criterion::{black_box, Criterion};
+use libsm::sm2::ecc::EccCtx;
+use libsm::sm2::signature::SigCtx;
+use libsm::sm2::PreparedPublicKey;
+use num_bigint::BigUint;
+
+fn scalar(hex: &[u8]) -> BigUint {
+    BigUint::parse_bytes(hex, 16).unwrap()
+}
+
+fn bench_mul(c: &mut Criterion) {
+    let curve = EccCtx::new();
+    let a = scalar(b"3945208F7B2144B13F36E38AC6D39F95889393692860B51A42FB81EF4DF7C5B8");
+    let b = scalar(b"59276E27D506861A16680F3AD9C02DCCEF3CC1FA3CDBE4CE6D54B80DEAC1BC21");
+    let p = curve.g_mul(&a);
+
+    c.bench_function("sm2 g_mul", |bench| bench.iter(|| curve.g_mul(black_box(&a))));
+    c.bench_function("sm2 mul", |bench| bench.iter(|| curve.mul(black_box(&b), &p)));
+    c.bench_function("sm2 g_mul + mul", |bench| {
+        bench.iter(|| curve.add(&curve.g_mul(black_box(&a)), &curve.mul(black_box(&b), &p)))
+    });
+    c.bench_function("sm2 double_scalar_mul", |bench| {
+        bench.iter(|| curve.double_scalar_mul(black_box(&a), black_box(&b), &p))
+    });
+}
+
+fn bench_signature(c: &mut Criterion) {
+    let ctx = SigCtx::new();
+    let sk = scalar(b"3945208F7B2144B13F36E38AC6D39F95889393692860B51A42FB81EF4DF7C5B8");
+    let pk = ctx.pk_from_sk(&sk);
+    let digest = ctx.hash("1234567812345678", &pk, b"message digest");
+    let sig = ctx.sign_raw(&digest, &sk);
+
+    c.bench_function("sm2 sign_raw", |bench| bench.iter(|| ctx.sign_raw(black_box(&digest), &sk)));
+    c.bench_function("sm2 verify_raw", |bench| {
+        bench.iter(|| ctx.verify_raw(black_box(&digest), &pk, &sig))
+    });
+
+    let prepared = PreparedPublicKey::new(&pk).unwrap();
+    c.bench_function("sm2 verify_raw_prepared", |bench| {
+        bench.iter(|| ctx.verify_raw_prepared(black_box(&digest), &prepared, &sig))
+    });
+    let bytes = ctx.serialize_pubkey(&pk, true);
+    c.bench_function("sm2 verify from compressed key", |bench| {
+        bench.iter(|| ctx.verify(b"message digest", &ctx.load_pubkey(black_box(&bytes)).unwrap(), &sig))
+    });
+    c.bench_function("sm2 verify_prepared", |bench| {
+        bench.iter(|| ctx.verify_prepared(black_box(b"message digest"), &prepared, &sig))
+    });
+}
+
+fn bench_batch(c: &mut Criterion) {
+    let ctx = SigCtx::new();
+    let curve = EccCtx::new();
+    let mut keys = Vec::new();
+    let mut msgs = Vec::new();
+    let mut sigs = Vec::new();
+    for i in 1..=64u32 {
+        let sk = scalar(b"3945208F7B2144B13F36E38AC6D39F95889393692860B51A42FB81EF4DF7C5B8") * i;
+        let sk = sk % curve.get_n();
+        let pk = ctx.pk_from_sk(&sk);
+        let msg = i.to_be_bytes().to_vec();
+        sigs.push(ctx.sign(&msg, &sk, &pk));
+        keys.push(pk);
+        msgs.push(msg);
+    }
+    let items: Vec<(&[u8], _, _)> = (0..64).map(|i| (&msgs[i][..], &keys[i], &sigs[i])).collect();
+
+    c.bench_function("sm2 verify x64", |bench| {
+        bench.iter(|| items.iter().all(|(msg, pk, sig)| ctx.verify(msg, pk, sig)))
+    });
+    c.bench_function("sm2 verify_batch x64", |bench| bench.iter(|| ctx.verify_batch(black_box(&items))));
+}
+
+criterion_group!(benches, bench_mul, bench_signature, bench_batch);
+criterion_main!(benches);
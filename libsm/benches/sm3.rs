@@ -0,0 +1,60 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[macro_use]
+extern crate criterion;
+extern crate libsm;
+
+use criterion::{black_box, Criterion, Throughput};
+use libsm::sm3::{hash_many, Sm3Hash, Sm3Hasher};
+
+fn bench_hash(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sm3");
+    for &len in [64usize, 1024, 1 << 20].iter() {
+        let data = vec![0x5au8; len];
+        group.throughput(Throughput::Bytes(len as u64));
+        group.bench_function(format!("hash {}B", len), |bench| {
+            bench.iter(|| {
+                let mut hasher = Sm3Hasher::new();
+                hasher.update(black_box(&data));
+                hasher.finalize()
+            })
+        });
+    }
+    group.finish();
+}
+
+// 65-byte inputs, a prefix byte and two child hashes, as hashed for Merkle nodes
+fn bench_many(c: &mut Criterion) {
+    let nodes: Vec<Vec<u8>> = (0..64u8).map(|i| vec![i; 65]).collect();
+    let msgs: Vec<&[u8]> = nodes.iter().map(|n| &n[..]).collect();
+
+    let mut group = c.benchmark_group("sm3 64 x 65B");
+    group.throughput(Throughput::Bytes(64 * 65));
+    group.bench_function("one by one", |bench| {
+        bench.iter(|| {
+            black_box(&msgs)
+                .iter()
+                .map(|m| Sm3Hash::new(m).get_hash())
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("hash_many", |bench| {
+        bench.iter(|| hash_many(black_box(&msgs)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_hash, bench_many);
+criterion_main!(benches);
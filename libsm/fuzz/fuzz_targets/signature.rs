@@ -0,0 +1,36 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use libsm::sm2::signature::Signature;
+
+fuzz_target!(|data: &[u8]| {
+    // canonical DER has a single encoding
+    if let Ok(sig) = Signature::der_decode(data) {
+        assert_eq!(sig.der_encode(), data);
+    }
+    if let Ok(sig) = Signature::der_decode_raw(data) {
+        assert_eq!(&sig.der_encode()[2..], data);
+    }
+    // whatever the lax parser accepts encodes canonically
+    if let Ok(sig) = Signature::der_decode_lax(data) {
+        let der = sig.der_encode();
+        assert!(Signature::der_decode(&der).is_ok());
+    }
+    if let Ok(sig) = Signature::from_bytes(data) {
+        assert_eq!(&sig.to_bytes()[..], data);
+    }
+});
@@ -0,0 +1,89 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! ASN.1 shared by the SM2 key files and X.509: the object identifiers of
+//! SM2 keys, AlgorithmIdentifier and SubjectPublicKeyInfo.
+
+use alloc::vec::*;
+use yasna;
+use yasna::models::ObjectIdentifier;
+use yasna::{ASN1Error, ASN1ErrorKind, ASN1Result, BERReader, DERWriter};
+
+use sm2::ecc::EccCtx;
+use sm2::signature::Pubkey;
+
+// 1.2.840.10045.2.1
+pub(crate) const OID_EC_PUBLIC_KEY: &[u64] = &[1, 2, 840, 10045, 2, 1];
+// 1.2.156.10197.1.301
+pub(crate) const OID_SM2: &[u64] = &[1, 2, 156, 10197, 1, 301];
+
+pub(crate) fn oid(components: &[u64]) -> ObjectIdentifier {
+    ObjectIdentifier::from_slice(components)
+}
+
+pub(crate) fn invalid() -> ASN1Error {
+    ASN1Error::new(ASN1ErrorKind::Invalid)
+}
+
+/// AlgorithmIdentifier, the parameters are kept as DER.
+#[derive(PartialEq, Eq)]
+pub(crate) struct Algorithm {
+    pub(crate) oid: ObjectIdentifier,
+    pub(crate) params: Option<Vec<u8>>,
+}
+
+impl Algorithm {
+    pub(crate) fn read(reader: BERReader) -> ASN1Result<Algorithm> {
+        reader.read_sequence(|reader| {
+            let oid = reader.next().read_oid()?;
+            let params = reader.read_optional(|reader| reader.read_der())?;
+            Ok(Algorithm { oid, params })
+        })
+    }
+
+    /// id-ecPublicKey with the SM2 curve as parameters.
+    pub(crate) fn is_sm2_public_key(&self) -> bool {
+        let sm2 = yasna::construct_der(|writer| writer.write_oid(&oid(OID_SM2)));
+        self.oid == oid(OID_EC_PUBLIC_KEY) && self.params.as_ref() == Some(&sm2)
+    }
+}
+
+// AlgorithmIdentifier { id-ecPublicKey, sm2 }
+pub(crate) fn write_sm2_algorithm(writer: DERWriter) {
+    writer.write_sequence(|writer| {
+        writer.next().write_oid(&oid(OID_EC_PUBLIC_KEY));
+        writer.next().write_oid(&oid(OID_SM2));
+    })
+}
+
+// SubjectPublicKeyInfo with the uncompressed point
+pub(crate) fn write_spki(writer: DERWriter, pk: &Pubkey) {
+    let point = EccCtx::new().point_to_bytes(pk, false);
+    writer.write_sequence(|writer| {
+        write_sm2_algorithm(writer.next());
+        writer.next().write_bitvec_bytes(&point, point.len() * 8);
+    })
+}
+
+/// Algorithm and point of a SubjectPublicKeyInfo, the point is left encoded.
+pub(crate) fn read_spki(reader: BERReader) -> ASN1Result<(Algorithm, Vec<u8>)> {
+    reader.read_sequence(|reader| {
+        let algorithm = Algorithm::read(reader.next())?;
+        let (point, bits) = reader.next().read_bitvec_bytes()?;
+        if bits != point.len() * 8 {
+            return Err(invalid());
+        }
+        Ok((algorithm, point))
+    })
+}
@@ -0,0 +1,119 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The error of every module, so that callers using several of them can
+//! propagate a single type with `?`.
+
+use sm2::error::Sm2Error;
+use sm3::error::Sm3Error;
+use sm4::error::Sm4Error;
+use x509::error::X509Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    Sm2(Sm2Error),
+    Sm3(Sm3Error),
+    Sm4(Sm4Error),
+    X509(X509Error),
+}
+
+impl From<Sm2Error> for Error {
+    fn from(e: Sm2Error) -> Self {
+        Error::Sm2(e)
+    }
+}
+
+impl From<Sm3Error> for Error {
+    fn from(e: Sm3Error) -> Self {
+        Error::Sm3(e)
+    }
+}
+
+impl From<Sm4Error> for Error {
+    fn from(e: Sm4Error) -> Self {
+        Error::Sm4(e)
+    }
+}
+
+impl From<X509Error> for Error {
+    fn from(e: X509Error) -> Self {
+        Error::X509(e)
+    }
+}
+
+impl From<Error> for &str {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Sm2(e) => e.into(),
+            Error::Sm3(e) => e.into(),
+            Error::Sm4(e) => e.into(),
+            Error::X509(e) => e.into(),
+        }
+    }
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Error::Sm2(ref e) => write!(f, "sm2: {}", e),
+            Error::Sm3(ref e) => write!(f, "sm3: {}", e),
+            Error::Sm4(ref e) => write!(f, "sm4: {}", e),
+            Error::X509(ref e) => write!(f, "x509: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Error::Sm2(ref e) => Some(e),
+            Error::Sm3(ref e) => Some(e),
+            Error::Sm4(ref e) => Some(e),
+            Error::X509(ref e) => Some(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decrypt(key: &[u8], data: &[u8]) -> Result<Vec<u8>, Error> {
+        use sm4::cipher_mode::{CipherMode, Sm4CipherMode};
+        let cipher = Sm4CipherMode::new_checked(key, CipherMode::Cbc)?;
+        Ok(cipher.decrypt_checked(data, &[0; 16])?)
+    }
+
+    #[test]
+    fn test_error() {
+        assert_eq!(
+            decrypt(&[0; 15], &[0; 16]),
+            Err(Error::Sm4(Sm4Error::InvalidKeyLength))
+        );
+        assert_eq!(
+            decrypt(&[0; 16], &[0; 15]),
+            Err(Error::Sm4(Sm4Error::InvalidCiphertext))
+        );
+
+        assert_eq!(format!("{:?}", Sm2Error::InvalidPublic), "InvalidPublic");
+        assert_eq!(format!("{}", Sm2Error::InvalidPublic), "invalid public key");
+        assert_eq!(
+            format!("{}", Error::from(X509Error::Expired)),
+            "x509: certificate expired"
+        );
+        let s: &str = Error::Sm2(Sm2Error::InvalidDer).into();
+        assert_eq!(s, "invalid der");
+    }
+}
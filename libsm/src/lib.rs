@@ -12,28 +12,34 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 #![cfg_attr(feature = "internal_benches", allow(unstable_features), feature(test))]
-#![cfg_attr(target_arch = "wasm32", no_std)]
-
+#![cfg_attr(all(target_arch = "wasm32", not(feature = "std")), no_std)]
 
+mod asn1;
+pub mod error;
 pub mod sm2;
 pub mod sm3;
 pub mod sm4;
+pub mod x509;
+
+pub use error::Error;
 
 #[macro_use]
 extern crate alloc;
+extern crate rand_core;
+#[cfg(test)]
 extern crate rand;
 extern crate num_bigint;
 extern crate num_integer;
 extern crate num_traits;
 extern crate yasna;
+extern crate subtle;
+extern crate base64;
+extern crate digest;
+extern crate aead;
 
 #[macro_use]
 extern crate lazy_static;
 
-pub fn seed(s: u64) {
-    sm2::seed(s)
-}
-
 use alloc::string::*;
 use alloc::vec::*;
 
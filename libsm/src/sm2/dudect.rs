@@ -0,0 +1,154 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Timing leakage tests in the style of dudect: the inputs are split into a
+//! fixed class and a random class, measured in random order, and Welch's
+//! t-test tells whether the two timing distributions differ.
+//!
+//! They are slow and only meaningful with optimizations, run them with
+//! `cargo test --release -p libsm dudect -- --ignored --test-threads=1`.
+
+use std::time::Instant;
+use std::vec::Vec;
+
+use num_bigint::BigUint;
+use num_traits::*;
+use rand_core::{OsRng, RngCore};
+
+use super::ecc::EccCtx;
+use super::field::{FieldCtx, FieldElem};
+
+// dudect reports a leak above 4.5 and a definite leak above 10, the latter
+// leaves room for the noise of a shared machine
+const THRESHOLD: f64 = 10.0;
+
+// measurements above this percentile are dropped, they are mostly interrupts
+const CROP: f64 = 0.9;
+
+/// |t| of Welch's t-test between the timings of `run` on the two classes of
+/// `n` inputs made by `gen`.
+fn leakage<I, G, F>(n: usize, mut gen: G, mut run: F) -> f64
+where
+    G: FnMut(bool) -> I,
+    F: FnMut(&I),
+{
+    let mut rng = OsRng;
+    let inputs: Vec<(bool, I)> = (0..n)
+        .map(|_| {
+            let class = rng.next_u32() & 1 == 1;
+            (class, gen(class))
+        })
+        .collect();
+
+    let timings: Vec<(bool, f64)> = inputs
+        .iter()
+        .map(|(class, input)| {
+            let start = Instant::now();
+            run(input);
+            (*class, start.elapsed().as_nanos() as f64)
+        })
+        .collect();
+
+    let mut sorted: Vec<f64> = timings.iter().map(|t| t.1).collect();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let crop = sorted[((n as f64) * CROP) as usize];
+
+    // Welford's online mean and variance of each class
+    let mut count = [0f64; 2];
+    let mut mean = [0f64; 2];
+    let mut m2 = [0f64; 2];
+    for &(class, t) in timings.iter().filter(|t| t.1 <= crop) {
+        let c = class as usize;
+        count[c] += 1.0;
+        let delta = t - mean[c];
+        mean[c] += delta / count[c];
+        m2[c] += delta * (t - mean[c]);
+    }
+
+    let var0 = m2[0] / (count[0] - 1.0);
+    let var1 = m2[1] / (count[1] - 1.0);
+    ((mean[0] - mean[1]) / (var0 / count[0] + var1 / count[1]).sqrt()).abs()
+}
+
+fn random_scalar(curve: &EccCtx) -> BigUint {
+    curve.random_uint_with(&mut OsRng)
+}
+
+fn random_elem() -> FieldElem {
+    let mut buf = [0u8; 32];
+    OsRng.fill_bytes(&mut buf);
+    // clear the top bit so the element is below p
+    buf[0] &= 0x7f;
+    FieldElem::from_bytes(&buf)
+}
+
+#[test]
+#[ignore]
+fn dudect_detects_leak() {
+    // the binary extended Euclidean algorithm on BigUint is variable time,
+    // the harness must notice
+    let curve = EccCtx::new();
+    let t = leakage(
+        20000,
+        |class| if class { BigUint::one() } else { random_scalar(&curve) },
+        |x| {
+            let _ = curve.inv_n(x);
+        },
+    );
+    assert!(t > THRESHOLD, "t = {}", t);
+}
+
+#[test]
+#[ignore]
+fn dudect_field_inv() {
+    let ctx = FieldCtx::new();
+    let t = leakage(
+        20000,
+        |class| if class { FieldElem::from_num(1) } else { random_elem() },
+        |x| {
+            let _ = ctx.inv(x);
+        },
+    );
+    assert!(t < THRESHOLD, "t = {}", t);
+}
+
+#[test]
+#[ignore]
+fn dudect_mul() {
+    let curve = EccCtx::new();
+    let p = curve.g_mul(&random_scalar(&curve));
+    let t = leakage(
+        2000,
+        |class| if class { BigUint::one() } else { random_scalar(&curve) },
+        |k| {
+            let _ = curve.mul(k, &p);
+        },
+    );
+    assert!(t < THRESHOLD, "t = {}", t);
+}
+
+#[test]
+#[ignore]
+fn dudect_g_mul() {
+    let curve = EccCtx::new();
+    let _ = curve.g_mul(&BigUint::one());
+    let t = leakage(
+        5000,
+        |class| if class { BigUint::one() } else { random_scalar(&curve) },
+        |k| {
+            let _ = curve.g_mul(k);
+        },
+    );
+    assert!(t < THRESHOLD, "t = {}", t);
+}
@@ -34,51 +34,35 @@ pub struct Point {
     pub z: FieldElem,
 }
 
-fn pre_vec_gen(n: u32) -> [u32; 8] {
-    let mut pre_vec: [u32; 8] = [0; 8];
-    let mut i = 0;
-    while i < 8 {
-        pre_vec[7 - i] = (n >> i) & 0x01;
-        i += 1;
-    }
-    pre_vec
-}
-fn pre_vec_gen2(n: u32) -> [u32; 8] {
-    let mut pre_vec: [u32; 8] = [0; 8];
-    let mut i = 0;
-    while i < 8 {
-        pre_vec[7 - i] = ((n >> i) & 0x01) << 16;
-        i += 1;
-    }
-    pre_vec
-}
+// width of the wNAF of the scalars of G and of an arbitrary point, prepared
+// public keys use the width of G
+pub(crate) const WNAF_G: usize = 7;
+const WNAF_P: usize = 5;
 
 lazy_static! {
-    static ref TABLE_1: Vec<Point> = {
-        let mut table: Vec<Point> = Vec::new();
+    // odd multiples G, 3G, ..., (2^(WNAF_G - 1) - 1)G, in affine coordinates
+    static ref TABLE_G_ODD: Vec<Point> = {
         let ctx = EccCtx::new();
-        for i in 0..256 {
-            let p1 = ctx.mul_raw(&pre_vec_gen(i as u32), &ctx.generator());
-            table.push(p1);
-        }
-        table
+        ctx.odd_multiples_affine(&ctx.generator(), WNAF_G)
     };
-    static ref TABLE_2: Vec<Point> = {
-        let mut table: Vec<Point> = Vec::new();
+
+    // fixed 4-bit windows of G, TABLE_G[16 * i + j] = j * 16^i * G
+    static ref TABLE_G: Vec<Point> = {
+        let mut table: Vec<Point> = Vec::with_capacity(64 * 16);
         let ctx = EccCtx::new();
-        for i in 0..256 {
-            let p1 = ctx.mul_raw(&pre_vec_gen2(i as u32), &ctx.generator());
-            table.push(p1);
+        let mut base = ctx.generator();
+        for _ in 0..64 {
+            let mut p = ctx.zero();
+            for _ in 0..16 {
+                table.push(p.clone());
+                p = ctx.add(&p, &base);
+            }
+            for _ in 0..4 {
+                base = ctx.double(&base);
+            }
         }
         table
     };
-
-}
-
-pub static mut SEED: u64 = 0;
-
-pub(crate) fn seed(s: u64) {
-    unsafe { SEED = s; }
 }
 
 impl EccCtx {
@@ -111,7 +95,7 @@ impl EccCtx {
                 16,
             )
             .unwrap(),
-            inv2: fctx.inv(&FieldElem::from_num(2)),
+            inv2: fctx.inv(&FieldElem::from_num(2)).unwrap(),
         }
     }
 
@@ -130,12 +114,13 @@ impl EccCtx {
         &self.n
     }
 
-    pub fn inv_n(&self, x: &BigUint) -> BigUint {
-        if *x == BigUint::zero() {
-            panic!("zero has no inversion.");
+    /// x^-1 mod n, None if x is a multiple of n.
+    pub fn inv_n(&self, x: &BigUint) -> Option<BigUint> {
+        let mut ru = x % self.get_n();
+        if ru == BigUint::zero() {
+            return None;
         }
 
-        let mut ru = x.clone();
         let mut rv = self.get_n().clone();
         let mut ra = BigUint::one();
         let mut rc = BigUint::zero();
@@ -179,7 +164,7 @@ impl EccCtx {
                 }
             }
         }
-        rc
+        Some(rc)
     }
 
     pub fn new_point(&self, x: &FieldElem, y: &FieldElem) -> Result<Point, Sm2Error> {
@@ -287,12 +272,49 @@ impl EccCtx {
             panic!("cannot convert the infinite point to affine");
         }
 
-        let zinv = ctx.inv(&p.z);
+        // z is nonzero for every other point
+        let zinv = ctx.inv(&p.z).unwrap();
         let x = ctx.mul(&p.x, &ctx.mul(&zinv, &zinv));
         let y = ctx.mul(&p.y, &ctx.mul(&zinv, &ctx.mul(&zinv, &zinv)));
         (x, y)
     }
 
+    /// Affine coordinates of all points with a single field inversion
+    /// (Montgomery's trick), None for the zero point.
+    pub fn batch_to_affine(&self, points: &[Point]) -> Vec<Option<(FieldElem, FieldElem)>> {
+        let ctx = &self.fctx;
+        let one = FieldElem::from_num(1);
+
+        // prods[i] is the product of the nonzero z up to i
+        let mut prods: Vec<FieldElem> = Vec::with_capacity(points.len());
+        let mut acc = one;
+        for p in points {
+            if !p.is_zero() {
+                acc = ctx.mul(&acc, &p.z);
+            }
+            prods.push(acc);
+        }
+
+        // acc is a product of nonzero z, or one
+        let mut inv = ctx.inv(&acc).unwrap();
+        let mut ret = vec![None; points.len()];
+        for i in (0..points.len()).rev() {
+            let p = &points[i];
+            if p.is_zero() {
+                continue;
+            }
+            let prev = if i == 0 { one } else { prods[i - 1] };
+            let zinv = ctx.mul(&inv, &prev);
+            inv = ctx.mul(&inv, &p.z);
+
+            let zinv2 = ctx.square(&zinv);
+            let x = ctx.mul(&p.x, &zinv2);
+            let y = ctx.mul(&p.y, &ctx.mul(&zinv2, &zinv));
+            ret[i] = Some((x, y));
+        }
+        ret
+    }
+
     pub fn neg(&self, p: &Point) -> Point {
         let neg_y = self.fctx.neg(&p.y);
         match self.new_jacobian(&p.x, &neg_y, &p.z) {
@@ -309,10 +331,6 @@ impl EccCtx {
             return p1.clone();
         }
 
-        if p1 == p2 {
-            return self.double(&p1);
-        }
-
         let ctx = &self.fctx;
 
         let z1z1 = ctx.square(&p1.z);
@@ -323,9 +341,18 @@ impl EccCtx {
         let s2 = ctx.mul(&p2.y,&ctx.mul(&p1.z,&z1z1));
 
         let h = ctx.sub(&u2,&u1);
+        let r = ctx.sub(&s2,&s1);
+
+        // same x, the points are equal or opposite whatever their z
+        if h.is_zero() {
+            if r.is_zero() {
+                return self.double(p1);
+            }
+            return self.zero();
+        }
+
         let hh = ctx.square(&h);
         let hhh = ctx.mul(&h,&hh);
-        let r = ctx.sub(&s2,&s1);
         let v = ctx.mul(&u1,&hh);
 
         let x3 = ctx.sub(&ctx.sub(&ctx.square(&r),&hhh),&ctx.mul(&FieldElem::from_num(2), &v));
@@ -343,37 +370,69 @@ impl EccCtx {
         }
     }
 
-    //dbl-1998-cmo-2 9m+6s
-    // XX = X12
-    // YY = Y12
-    // ZZ = Z12
-    // S = 4*X1*YY
-    // M = 3*XX+a*ZZ2
-    // T = M2-2*S
-    // X3 = T
-    // Y3 = M*(S-T)-8*YY2
-    // Z3 = 2*Y1*Z1
-    pub fn double(&self, p: &Point) -> Point {
-        if p.is_zero(){
-            return p.clone();
-        }
+    // add-1998-cmo-2 without branches, the zero point of either side is selected
+    // afterwards, p1 and p2 must not be the same nonzero point
+    fn add_ct(&self, p1: &Point, p2: &Point) -> Point {
+        let ctx = &self.fctx;
 
+        let z1z1 = ctx.square(&p1.z);
+        let z2z2 = ctx.square(&p2.z);
+        let u1 = ctx.mul(&p1.x, &z2z2);
+        let u2 = ctx.mul(&p2.x, &z1z1);
+        let s1 = ctx.mul(&p1.y, &ctx.mul(&p2.z, &z2z2));
+        let s2 = ctx.mul(&p2.y, &ctx.mul(&p1.z, &z1z1));
+
+        let h = ctx.sub(&u2, &u1);
+        let hh = ctx.square(&h);
+        let hhh = ctx.mul(&h, &hh);
+        let r = ctx.sub(&s2, &s1);
+        let v = ctx.mul(&u1, &hh);
+
+        let x3 = ctx.sub(&ctx.sub(&ctx.square(&r), &hhh), &ctx.add(&v, &v));
+        let y3 = ctx.sub(&ctx.mul(&r, &ctx.sub(&v, &x3)), &ctx.mul(&s1, &hhh));
+        let z3 = ctx.mul(&p1.z, &ctx.mul(&p2.z, &h));
+
+        let sum = Point {
+            x: x3,
+            y: y3,
+            z: z3,
+        };
+        let sum = Point::conditional_select(&sum, p1, p2.z.ct_eq(&FieldElem::zero()));
+        Point::conditional_select(&sum, p2, p1.z.ct_eq(&FieldElem::zero()))
+    }
+
+    //dbl-2001-b 3m+5s, for a = -3 as in SM2
+    // delta = Z1^2
+    // gamma = Y1^2
+    // beta = X1*gamma
+    // alpha = 3*(X1-delta)*(X1+delta)
+    // X3 = alpha^2-8*beta
+    // Z3 = (Y1+Z1)^2-gamma-delta
+    // Y3 = alpha*(4*beta-X3)-8*gamma^2
+    // Z3 = 0 when Z1 = 0, the zero point needs no special case
+    pub fn double(&self, p: &Point) -> Point {
         let ctx = &self.fctx;
 
-        let xx = ctx.square(&p.x);
-        let yy = ctx.square(&p.y);
-        let zz = ctx.square(&p.z);
-        let yy8 = &ctx.mul(&FieldElem::from_num(8), &ctx.square(&yy));
+        let delta = ctx.square(&p.z);
+        let gamma = ctx.square(&p.y);
+        let beta = ctx.mul(&p.x, &gamma);
 
-        let s = ctx.mul(&FieldElem::from_num(4), &ctx.mul(&p.x, &yy));
-        let m = ctx.add(&ctx.mul(&FieldElem::from_num(3), &xx),
-                        &ctx.mul(&self.a, &ctx.square(&zz)));
+        let t = ctx.mul(&ctx.sub(&p.x, &delta), &ctx.add(&p.x, &delta));
+        let alpha = ctx.add(&ctx.add(&t, &t), &t);
 
-        let x3 = ctx.sub(&ctx.square(&m), &ctx.mul(&FieldElem::from_num(2), &s));
+        let beta2 = ctx.add(&beta, &beta);
+        let beta4 = ctx.add(&beta2, &beta2);
+        let beta8 = ctx.add(&beta4, &beta4);
+        let x3 = ctx.sub(&ctx.square(&alpha), &beta8);
 
-        let y3 = ctx.sub(&ctx.mul(&m, &ctx.sub(&s, &x3)), &yy8);
+        let yz = ctx.add(&p.y, &p.z);
+        let z3 = ctx.sub(&ctx.sub(&ctx.square(&yz), &gamma), &delta);
 
-        let z3 = ctx.mul(&FieldElem::from_num(2), &ctx.mul(&p.y, &p.z));
+        let gamma2 = ctx.square(&gamma);
+        let gamma2 = ctx.add(&gamma2, &gamma2);
+        let gamma4 = ctx.add(&gamma2, &gamma2);
+        let gamma8 = ctx.add(&gamma4, &gamma4);
+        let y3 = ctx.sub(&ctx.mul(&alpha, &ctx.sub(&beta4, &x3)), &gamma8);
 
         Point {
             x: x3,
@@ -390,62 +449,125 @@ impl EccCtx {
         self.mul_raw(&k.value, p)
     }
 
+    // Montgomery ladder, the same sequence of operations for every m
     pub fn mul_raw(&self, m: &[u32], p: &Point) -> Point {
-        let mut q = self.zero();
+        let mut r0 = self.zero();
+        let mut r1 = p.clone();
 
         let mut i = 0;
         while i < 256 {
             let index = i as usize / 32;
             let bit = 31 - i as usize % 32;
+            let choice = Choice::from(((m[index] >> bit) & 0x01) as u8);
 
-            // let sum = self.add(&q0, &q1);
-            q = self.double(&q);
+            // r1 = r0 + p holds through the loop, so the addition never sees equal points
+            Point::conditional_swap(&mut r0, &mut r1, choice);
+            r1 = self.add_ct(&r0, &r1);
+            r0 = self.double(&r0);
+            Point::conditional_swap(&mut r0, &mut r1, choice);
+
+            i += 1;
+        }
+        r0
+    }
+
+    // Fixed window over the precomputed multiples of G, every window reads the
+    // whole row of its table.
+    pub fn g_mul(&self, m: &BigUint) -> Point {
+        let m = m % self.get_n();
+        let k = FieldElem::from_biguint(&m);
+        let mut q = self.zero();
 
-            if (m[index] >> bit) & 0x01 != 0 {
-                q = self.add(&q, &p);
+        for i in 0..64 {
+            let digit = (k.value[7 - i / 8] >> (4 * (i % 8))) & 0x0f;
+            let row = &TABLE_G[16 * i..16 * i + 16];
 
-                // q = self.double(&q0);
+            let mut t = row[0].clone();
+            for (j, p) in row.iter().enumerate().skip(1) {
+                t = Point::conditional_select(&t, p, (j as u32).ct_eq(&digit));
             }
 
-            i += 1;
+            // q < 16^i * G <= t unless t is the zero, they are never the same point
+            q = self.add_ct(&q, &t);
         }
+
         q
     }
-    #[inline(always)]
-    fn ith_bit(n: u32, i: i32) -> u32 {
-        (n >> i) & 0x01
-    }
 
-    #[inline(always)]
-    fn compose_k(v: &[u32], i: i32) -> u32 {
-        EccCtx::ith_bit(v[7], i)
-            + (EccCtx::ith_bit(v[6], i) << 1)
-            + (EccCtx::ith_bit(v[5], i) << 2)
-            + (EccCtx::ith_bit(v[4], i) << 3)
-            + (EccCtx::ith_bit(v[3], i) << 4)
-            + (EccCtx::ith_bit(v[2], i) << 5)
-            + (EccCtx::ith_bit(v[1], i) << 6)
-            + (EccCtx::ith_bit(v[0], i) << 7)
+    /// `a * G + b * p` with interleaved wNAF, in variable time, only for public
+    /// scalars and points as in signature verification.
+    pub fn double_scalar_mul(&self, a: &BigUint, b: &BigUint, p: &Point) -> Point {
+        self.double_scalar_mul_table(a, b, &self.odd_multiples(p, WNAF_P), WNAF_P)
     }
 
-    pub fn g_mul(&self, m: &BigUint) -> Point {
-        let m = m % self.get_n();
-        let k = FieldElem::from_biguint(&m);
-        let mut q = self.zero();
+    // a * G + b * P, where table_p holds the odd multiples of P for a wNAF of width w
+    pub(crate) fn double_scalar_mul_table(
+        &self,
+        a: &BigUint,
+        b: &BigUint,
+        table_p: &[Point],
+        w: usize,
+    ) -> Point {
+        let naf_a = wnaf(&FieldElem::from_biguint(&(a % self.get_n())), WNAF_G);
+        let naf_b = wnaf(&FieldElem::from_biguint(&(b % self.get_n())), w);
+
+        let top = naf_a
+            .iter()
+            .zip(naf_b.iter())
+            .rposition(|(x, y)| *x != 0 || *y != 0);
+        let top = match top {
+            Some(i) => i,
+            None => return self.zero(),
+        };
 
-        let mut i = 15;
-        while i >= 0 {
+        let mut q = self.zero();
+        for i in (0..=top).rev() {
             q = self.double(&q);
-            let k1 = EccCtx::compose_k(&k.value, i);
-            let k2 = EccCtx::compose_k(&k.value, i + 16);
-            let p1 = &TABLE_1[k1 as usize];
-            let p2 = &TABLE_2[k2 as usize];
-            q = self.add(&self.add(&q, p1), p2);
+            q = self.add_digit(&q, &TABLE_G_ODD, naf_a[i]);
+            q = self.add_digit(&q, table_p, naf_b[i]);
+        }
+        q
+    }
 
-            i -= 1;
+    // odd_multiples in affine coordinates, p must not be zero
+    pub(crate) fn odd_multiples_affine(&self, p: &Point, w: usize) -> Vec<Point> {
+        self.batch_to_affine(&self.odd_multiples(p, w))
+            .into_iter()
+            .map(|xy| {
+                let (x, y) = xy.expect("odd multiples of a nonzero point are nonzero");
+                Point { x, y, z: FieldElem::from_num(1) }
+            })
+            .collect()
+    }
+
+    // p, 3p, ..., (2^(w - 1) - 1)p
+    fn odd_multiples(&self, p: &Point, w: usize) -> Vec<Point> {
+        let double_p = self.double(p);
+        let mut table = Vec::with_capacity(1 << (w - 2));
+        table.push(p.clone());
+        for i in 1..(1 << (w - 2)) {
+            let next = self.add(&table[i - 1], &double_p);
+            table.push(next);
         }
+        table
+    }
 
-        q
+    // q + digit * p, where table holds the odd multiples of p
+    #[inline]
+    fn add_digit(&self, q: &Point, table: &[Point], digit: i8) -> Point {
+        if digit > 0 {
+            self.add(q, &table[digit as usize / 2])
+        } else if digit < 0 {
+            let p = &table[(-digit) as usize / 2];
+            let neg_p = Point {
+                x: p.x,
+                y: self.fctx.neg(&p.y),
+                z: p.z,
+            };
+            self.add(q, &neg_p)
+        } else {
+            q.clone()
+        }
     }
 
     pub fn eq(&self, p1: &Point, p2: &Point) -> bool {
@@ -463,9 +585,14 @@ impl EccCtx {
         p1x.eq(&p2x) && p1y.eq(&p2y)
     }
 
+    /// Random number in [1, n - 2] drawn from `OsRng`.
+    #[cfg(feature = "getrandom")]
     pub fn random_uint(&self) -> BigUint {
+        self.random_uint_with(&mut OsRng)
+    }
 
-        let mut rng = unsafe { rand::rngs::SmallRng::seed_from_u64(SEED) } ;
+    /// Random number in [1, n - 2] drawn from `rng`.
+    pub fn random_uint_with<R: RngCore + CryptoRng>(&self, rng: &mut R) -> BigUint {
         let mut buf: [u8; 32] = [0; 32];
 
         let mut ret;
@@ -542,6 +669,52 @@ impl EccCtx {
     }
 }
 
+// width-w non-adjacent form of k, least significant digit first. Every nonzero
+// digit is odd and below 2^(w - 1) in absolute value, and is followed by at
+// least w - 1 zeros. A final carry may add one digit above bit 255.
+fn wnaf(k: &FieldElem, w: usize) -> Vec<i8> {
+    // little-endian 64-bit limbs, one spare limb to read windows past the top
+    let mut limbs = [0u64; 5];
+    for (i, limb) in limbs.iter_mut().take(4).enumerate() {
+        *limb = (k.value[7 - 2 * i] as u64) | ((k.value[6 - 2 * i] as u64) << 32);
+    }
+
+    let width = 1u64 << w;
+    let mask = width - 1;
+    let mut naf = vec![0i8; 256 + w];
+    let mut carry = 0u64;
+    let mut pos = 0;
+    while pos < 256 {
+        let idx = pos / 64;
+        let bit = pos % 64;
+        let buf = if bit + w <= 64 {
+            limbs[idx] >> bit
+        } else {
+            (limbs[idx] >> bit) | (limbs[idx + 1] << (64 - bit))
+        };
+        let window = carry + (buf & mask);
+
+        if window & 1 == 0 {
+            // a zero digit, a pending carry meets a set bit and stays pending
+            pos += 1;
+            continue;
+        }
+
+        if window < width / 2 {
+            carry = 0;
+            naf[pos] = window as i8;
+        } else {
+            carry = 1;
+            naf[pos] = (window as i64 - width as i64) as i8;
+        }
+        pos += w;
+    }
+    if carry == 1 {
+        naf[pos] = 1;
+    }
+    naf
+}
+
 impl Default for EccCtx {
     fn default() -> Self {
         Self::new()
@@ -552,10 +725,29 @@ impl Point {
     pub fn is_zero(&self) -> bool {
         self.z.eq(&FieldElem::zero())
     }
+
+    /// `b` if `choice` is set, else `a`, in constant time.
+    pub fn conditional_select(a: &Point, b: &Point, choice: Choice) -> Point {
+        Point {
+            x: FieldElem::conditional_select(&a.x, &b.x, choice),
+            y: FieldElem::conditional_select(&a.y, &b.y, choice),
+            z: FieldElem::conditional_select(&a.z, &b.z, choice),
+        }
+    }
+
+    /// Swaps `a` and `b` if `choice` is set, in constant time.
+    pub fn conditional_swap(a: &mut Point, b: &mut Point, choice: Choice) {
+        FieldElem::conditional_swap(&mut a.x, &mut b.x, choice);
+        FieldElem::conditional_swap(&mut a.y, &mut b.y, choice);
+        FieldElem::conditional_swap(&mut a.z, &mut b.z, choice);
+    }
 }
 
-use rand::{RngCore, SeedableRng};
+use rand_core::{CryptoRng, RngCore};
+#[cfg(feature = "getrandom")]
+use rand_core::OsRng;
 use sm2::error::Sm2Error;
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
 
 impl core::fmt::Display for Point {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
@@ -623,19 +815,101 @@ mod tests {
         assert!(curve.eq(&nn_g, &new_g));
     }
 
+    #[test]
+    fn test_wnaf() {
+        use num_bigint::BigInt;
+
+        let curve = EccCtx::new();
+        let mut ks = vec![BigUint::zero(), BigUint::one(), curve.get_n() - BigUint::one()];
+        for _ in 0..20 {
+            ks.push(curve.random_uint_with(&mut rand_core::OsRng));
+        }
+
+        for k in ks.iter() {
+            for w in [WNAF_P, WNAF_G].iter() {
+                let naf = wnaf(&FieldElem::from_biguint(k), *w);
+                let mut sum = BigInt::zero();
+                let mut last: Option<usize> = None;
+                for (i, d) in naf.iter().enumerate().rev() {
+                    sum = (sum << 1) + BigInt::from(*d);
+                    if *d != 0 {
+                        assert!(d % 2 != 0 && (d.abs() as i32) < 1 << (w - 1));
+                        if let Some(j) = last {
+                            assert!(j - i >= *w);
+                        }
+                        last = Some(i);
+                    }
+                }
+                assert_eq!(sum, BigInt::from(k.clone()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_double_scalar_mul() {
+        let curve = EccCtx::new();
+        let n = curve.get_n().clone();
+        let p = curve.g_mul(&curve.random_uint_with(&mut rand_core::OsRng));
+
+        let check = |a: &BigUint, b: &BigUint, p: &Point| {
+            let expected = curve.add(&curve.g_mul(a), &curve.mul(b, p));
+            assert!(curve.eq(&expected, &curve.double_scalar_mul(a, b, p)));
+        };
+
+        for _ in 0..10 {
+            let a = curve.random_uint_with(&mut rand_core::OsRng);
+            let b = curve.random_uint_with(&mut rand_core::OsRng);
+            check(&a, &b, &p);
+        }
+
+        let one = BigUint::one();
+        check(&BigUint::zero(), &BigUint::zero(), &p);
+        check(&one, &BigUint::zero(), &p);
+        check(&BigUint::zero(), &(&n - &one), &p);
+        check(&(&n - &one), &(&n - &one), &p);
+        // a * G = -b * G, so the sum is the zero point
+        let g = curve.generator();
+        let a = BigUint::from_u32(12345).unwrap();
+        assert!(curve.double_scalar_mul(&a, &(&n - &a), &g).is_zero());
+        check(&a, &a, &g);
+    }
+
+    #[test]
+    fn test_batch_to_affine() {
+        let curve = EccCtx::new();
+        let mut points = Vec::new();
+        for i in 1..10u32 {
+            points.push(curve.g_mul(&BigUint::from_u32(i * 7919).unwrap()));
+        }
+        points.insert(0, curve.zero());
+        points.insert(5, curve.zero());
+
+        let affine = curve.batch_to_affine(&points);
+        for (p, a) in points.iter().zip(affine.iter()) {
+            if p.is_zero() {
+                assert!(a.is_none());
+            } else {
+                assert!(*a == Some(curve.to_affine(p)));
+            }
+        }
+        assert!(curve.batch_to_affine(&[]).is_empty());
+    }
+
     #[test]
     fn test_inv_n() {
         let curve = EccCtx::new();
 
         for _ in 0..20 {
-            let r = curve.random_uint();
-            let r_inv = curve.inv_n(&r);
+            let r = curve.random_uint_with(&mut rand_core::OsRng);
+            let r_inv = curve.inv_n(&r).unwrap();
 
             let product = r * r_inv;
             let product = product % curve.get_n();
 
             assert_eq!(product, BigUint::one());
         }
+        assert!(curve.inv_n(&BigUint::zero()).is_none());
+        assert!(curve.inv_n(curve.get_n()).is_none());
     }
 
     #[test]
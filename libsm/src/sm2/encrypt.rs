@@ -0,0 +1,391 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::vec::*;
+use super::ecc::*;
+use super::field::FieldElem;
+use num_bigint::BigUint;
+use sm3::hash::Sm3Hash;
+use sm3::kdf::kdf;
+use sm2::error::Sm2Error;
+use rand_core::{CryptoRng, RngCore};
+#[cfg(feature = "getrandom")]
+use rand_core::OsRng;
+use subtle::ConstantTimeEq;
+use yasna;
+
+/// Byte order of the ciphertext components, C1 is always the uncompressed point 04 || x1 || y1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CipherMode {
+    /// C1 || C3 || C2, the order of GB/T 32918.4-2016
+    #[default]
+    C1C3C2,
+    /// C1 || C2 || C3, the order of the 2010 draft, still used by many implementations
+    C1C2C3,
+}
+
+
+// (C1, C2, C3)
+type Parts = (Vec<u8>, Vec<u8>, Vec<u8>);
+
+pub struct EncryptCtx {
+    curve: EccCtx,
+}
+
+impl EncryptCtx {
+    pub fn new() -> EncryptCtx {
+        EncryptCtx {
+            curve: EccCtx::new(),
+        }
+    }
+
+    /// Encrypt `msg` for `pk` with `OsRng`, the ciphertext is C1 || C3 || C2.
+    #[cfg(feature = "getrandom")]
+    pub fn encrypt(&self, pk: &Point, msg: &[u8]) -> Result<Vec<u8>, Sm2Error> {
+        self.encrypt_with_rng(pk, msg, CipherMode::C1C3C2, &mut OsRng)
+    }
+
+    #[cfg(feature = "getrandom")]
+    pub fn encrypt_with_mode(
+        &self,
+        pk: &Point,
+        msg: &[u8],
+        mode: CipherMode,
+    ) -> Result<Vec<u8>, Sm2Error> {
+        self.encrypt_with_rng(pk, msg, mode, &mut OsRng)
+    }
+
+    /// Encrypt `msg` for `pk`, k is drawn from `rng`.
+    pub fn encrypt_with_rng<R: RngCore + CryptoRng>(
+        &self,
+        pk: &Point,
+        msg: &[u8],
+        mode: CipherMode,
+        rng: &mut R,
+    ) -> Result<Vec<u8>, Sm2Error> {
+        let (c1, c2, c3) = self.encrypt_raw(pk, msg, rng)?;
+        Ok(concat(&c1, &c2, &c3, mode))
+    }
+
+    /// Encrypt `msg` for `pk` with `OsRng`, the ciphertext is the DER encoding of
+    /// SEQUENCE { x INTEGER, y INTEGER, hash OCTET STRING, cipher OCTET STRING } (GM/T 0009).
+    #[cfg(feature = "getrandom")]
+    pub fn encrypt_der(&self, pk: &Point, msg: &[u8]) -> Result<Vec<u8>, Sm2Error> {
+        self.encrypt_der_with_rng(pk, msg, &mut OsRng)
+    }
+
+    pub fn encrypt_der_with_rng<R: RngCore + CryptoRng>(
+        &self,
+        pk: &Point,
+        msg: &[u8],
+        rng: &mut R,
+    ) -> Result<Vec<u8>, Sm2Error> {
+        let (c1, c2, c3) = self.encrypt_raw(pk, msg, rng)?;
+        Ok(der_encode(&c1, &c2, &c3))
+    }
+
+    pub fn decrypt(&self, sk: &BigUint, cipher: &[u8]) -> Result<Vec<u8>, Sm2Error> {
+        self.decrypt_with_mode(sk, cipher, CipherMode::C1C3C2)
+    }
+
+    pub fn decrypt_with_mode(
+        &self,
+        sk: &BigUint,
+        cipher: &[u8],
+        mode: CipherMode,
+    ) -> Result<Vec<u8>, Sm2Error> {
+        let c1_len = match cipher.first() {
+            Some(0x04) => 65,
+            _ => return Err(Sm2Error::InvalidCiphertext),
+        };
+        if cipher.len() < c1_len + 32 {
+            return Err(Sm2Error::InvalidCiphertext);
+        }
+        let (c1, rest) = cipher.split_at(c1_len);
+        let (c2, c3) = match mode {
+            CipherMode::C1C3C2 => {
+                let (c3, c2) = rest.split_at(32);
+                (c2, c3)
+            }
+            CipherMode::C1C2C3 => rest.split_at(rest.len() - 32),
+        };
+        let c1 = self
+            .curve
+            .bytes_to_point(c1)
+            .map_err(|_| Sm2Error::InvalidCiphertext)?;
+        self.decrypt_raw(sk, &c1, c2, c3)
+    }
+
+    pub fn decrypt_der(&self, sk: &BigUint, cipher: &[u8]) -> Result<Vec<u8>, Sm2Error> {
+        let (x, y, c3, c2) = yasna::parse_der(cipher, |reader| {
+            reader.read_sequence(|reader| {
+                let x = reader.next().read_biguint()?;
+                let y = reader.next().read_biguint()?;
+                let c3 = reader.next().read_bytes()?;
+                let c2 = reader.next().read_bytes()?;
+                Ok((x, y, c3, c2))
+            })
+        })
+        .map_err(|_| Sm2Error::InvalidDer)?;
+        if x.bits() > 256 || y.bits() > 256 || c3.len() != 32 {
+            return Err(Sm2Error::InvalidCiphertext);
+        }
+        let c1 = self
+            .curve
+            .new_point(&FieldElem::from_biguint(&x), &FieldElem::from_biguint(&y))
+            .map_err(|_| Sm2Error::InvalidCiphertext)?;
+        self.decrypt_raw(sk, &c1, &c2, &c3)
+    }
+
+    fn encrypt_raw<R: RngCore + CryptoRng>(
+        &self,
+        pk: &Point,
+        msg: &[u8],
+        rng: &mut R,
+    ) -> Result<Parts, Sm2Error> {
+        if pk.is_zero() {
+            return Err(Sm2Error::InvalidPublic);
+        }
+        loop {
+            let k = self.curve.random_uint_with(rng);
+            if let Some(c) = self.encrypt_with_k(pk, msg, &k) {
+                return Ok(c);
+            }
+        }
+    }
+
+    // None when the derived key stream is all zero, the caller retries with another k
+    fn encrypt_with_k(
+        &self,
+        pk: &Point,
+        msg: &[u8],
+        k: &BigUint,
+    ) -> Option<Parts> {
+        let curve = &self.curve;
+        // C1 = [k]G
+        let c1 = curve.point_to_bytes(&curve.g_mul(k), false);
+
+        // (x2, y2) = [k]P_B
+        let (x2, y2) = curve.to_affine(&curve.mul(k, pk));
+        let (x2, y2) = (x2.to_bytes(), y2.to_bytes());
+
+        // t = KDF(x2 || y2, klen)
+        let mut z: Vec<u8> = Vec::with_capacity(64);
+        z.extend_from_slice(&x2);
+        z.extend_from_slice(&y2);
+        let t = kdf(&z, msg.len());
+        if !msg.is_empty() && t.iter().all(|b| *b == 0) {
+            return None;
+        }
+
+        // C2 = M ^ t
+        let c2: Vec<u8> = msg.iter().zip(t.iter()).map(|(m, t)| m ^ t).collect();
+
+        // C3 = Hash(x2 || M || y2)
+        let c3 = hash_c3(&x2, msg, &y2);
+        Some((c1, c2, c3))
+    }
+
+    fn decrypt_raw(
+        &self,
+        sk: &BigUint,
+        c1: &Point,
+        c2: &[u8],
+        c3: &[u8],
+    ) -> Result<Vec<u8>, Sm2Error> {
+        let curve = &self.curve;
+        if *sk >= *curve.get_n() || *sk == BigUint::from(0u32) {
+            return Err(Sm2Error::InvalidPrivate);
+        }
+
+        // (x2, y2) = [d_B]C1
+        let p = curve.mul(sk, c1);
+        if p.is_zero() {
+            return Err(Sm2Error::InvalidCiphertext);
+        }
+        let (x2, y2) = curve.to_affine(&p);
+        let (x2, y2) = (x2.to_bytes(), y2.to_bytes());
+
+        let mut z: Vec<u8> = Vec::with_capacity(64);
+        z.extend_from_slice(&x2);
+        z.extend_from_slice(&y2);
+        let t = kdf(&z, c2.len());
+        if !c2.is_empty() && t.iter().all(|b| *b == 0) {
+            return Err(Sm2Error::DecryptFailed);
+        }
+
+        let msg: Vec<u8> = c2.iter().zip(t.iter()).map(|(c, t)| c ^ t).collect();
+
+        // compare C3 without early exit
+        let u = hash_c3(&x2, &msg, &y2);
+        if !bool::from(u[..].ct_eq(c3)) {
+            return Err(Sm2Error::DecryptFailed);
+        }
+        Ok(msg)
+    }
+}
+
+impl Default for EncryptCtx {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Encrypt with a new context, see `EncryptCtx::encrypt`.
+#[cfg(feature = "getrandom")]
+pub fn encrypt(pk: &Point, msg: &[u8]) -> Result<Vec<u8>, Sm2Error> {
+    EncryptCtx::new().encrypt(pk, msg)
+}
+
+/// Decrypt with a new context, see `EncryptCtx::decrypt`.
+pub fn decrypt(sk: &BigUint, cipher: &[u8]) -> Result<Vec<u8>, Sm2Error> {
+    EncryptCtx::new().decrypt(sk, cipher)
+}
+
+fn hash_c3(x2: &[u8], msg: &[u8], y2: &[u8]) -> Vec<u8> {
+    let mut buf: Vec<u8> = Vec::with_capacity(x2.len() + msg.len() + y2.len());
+    buf.extend_from_slice(x2);
+    buf.extend_from_slice(msg);
+    buf.extend_from_slice(y2);
+    Sm3Hash::new(&buf[..]).get_hash().to_vec()
+}
+
+fn concat(c1: &[u8], c2: &[u8], c3: &[u8], mode: CipherMode) -> Vec<u8> {
+    let mut ret: Vec<u8> = Vec::with_capacity(c1.len() + c2.len() + c3.len());
+    ret.extend_from_slice(c1);
+    match mode {
+        CipherMode::C1C3C2 => {
+            ret.extend_from_slice(c3);
+            ret.extend_from_slice(c2);
+        }
+        CipherMode::C1C2C3 => {
+            ret.extend_from_slice(c2);
+            ret.extend_from_slice(c3);
+        }
+    }
+    ret
+}
+
+fn der_encode(c1: &[u8], c2: &[u8], c3: &[u8]) -> Vec<u8> {
+    let x = BigUint::from_bytes_be(&c1[1..33]);
+    let y = BigUint::from_bytes_be(&c1[33..65]);
+    yasna::construct_der(|writer| {
+        writer.write_sequence(|writer| {
+            writer.next().write_biguint(&x);
+            writer.next().write_biguint(&y);
+            writer.next().write_bytes(c3);
+            writer.next().write_bytes(c2);
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_traits::Num;
+    use rand_core::OsRng;
+
+    fn standard_key() -> (Point, BigUint) {
+        let curve = EccCtx::new();
+        let sk = BigUint::from_str_radix(
+            "3945208F7B2144B13F36E38AC6D39F95889393692860B51A42FB81EF4DF7C5B8",
+            16,
+        )
+        .unwrap();
+        (curve.g_mul(&sk), sk)
+    }
+
+    // example of GB/T 32918.5-2017 on the recommended curve
+    #[test]
+    fn test_standard_vector() {
+        let ctx = EncryptCtx::new();
+        let (pk, sk) = standard_key();
+        let k = BigUint::from_str_radix(
+            "59276E27D506861A16680F3AD9C02DCCEF3CC1FA3CDBE4CE6D54B80DEAC1BC21",
+            16,
+        )
+        .unwrap();
+        let msg = b"encryption standard";
+
+        let (c1, c2, c3) = ctx.encrypt_with_k(&pk, msg, &k).unwrap();
+        assert_eq!(
+            hex::encode(&c1).to_uppercase(),
+            "04\
+             04EBFC718E8D1798620432268E77FEB6415E2EDE0E073C0F4F640ECD2E149A73\
+             E858F9D81E5430A57B36DAAB8F950A3C64E6EE6A63094D99283AFF767E124DF0"
+        );
+        assert_eq!(hex::encode(&c2).to_uppercase(), "21886CA989CA9C7D58087307CA93092D651EFA");
+        assert_eq!(
+            hex::encode(&c3).to_uppercase(),
+            "59983C18F809E262923C53AEC295D30383B54E39D609D160AFCB1908D0BD8766"
+        );
+
+        let c1c3c2 = concat(&c1, &c2, &c3, CipherMode::C1C3C2);
+        assert_eq!(ctx.decrypt(&sk, &c1c3c2).unwrap(), msg);
+        let c1c2c3 = concat(&c1, &c2, &c3, CipherMode::C1C2C3);
+        assert_eq!(ctx.decrypt_with_mode(&sk, &c1c2c3, CipherMode::C1C2C3).unwrap(), msg);
+        assert_eq!(ctx.decrypt_der(&sk, &der_encode(&c1, &c2, &c3)).unwrap(), msg);
+    }
+
+    #[test]
+    fn test_encrypt_and_decrypt() {
+        let ctx = EncryptCtx::new();
+        let (pk, sk) = standard_key();
+        for msg in [&b""[..], b"a", &[0x5a; 100][..]].iter() {
+            let c = ctx.encrypt_with_rng(&pk, msg, CipherMode::C1C3C2, &mut OsRng).unwrap();
+            assert_eq!(c.len(), 65 + 32 + msg.len());
+            assert_eq!(decrypt(&sk, &c).unwrap(), *msg);
+
+            let c = ctx.encrypt_with_rng(&pk, msg, CipherMode::C1C2C3, &mut OsRng).unwrap();
+            assert_eq!(ctx.decrypt_with_mode(&sk, &c, CipherMode::C1C2C3).unwrap(), *msg);
+
+            let c = ctx.encrypt_der_with_rng(&pk, msg, &mut OsRng).unwrap();
+            assert_eq!(ctx.decrypt_der(&sk, &c).unwrap(), *msg);
+        }
+    }
+
+    #[test]
+    fn test_tampered_ciphertext() {
+        let ctx = EncryptCtx::new();
+        let (pk, sk) = standard_key();
+        let mut c = ctx.encrypt_with_rng(&pk, b"encryption standard", CipherMode::C1C3C2, &mut OsRng).unwrap();
+        let last = c.len() - 1;
+        c[last] ^= 1;
+        assert!(ctx.decrypt(&sk, &c).is_err());
+        assert!(ctx.decrypt(&sk, &c[..64]).is_err());
+
+        // wrong layout
+        let c = ctx.encrypt_with_rng(&pk, b"encryption standard", CipherMode::C1C3C2, &mut OsRng).unwrap();
+        assert!(ctx.decrypt_with_mode(&sk, &c, CipherMode::C1C2C3).is_err());
+
+        // wrong key
+        let wrong = &sk + BigUint::from(1u32);
+        assert!(ctx.decrypt(&wrong, &c).is_err());
+    }
+
+    // generated by `openssl pkeyutl -encrypt` of OpenSSL 3.0
+    #[test]
+    fn test_openssl_der() {
+        let ctx = EncryptCtx::new();
+        let sk = BigUint::from_str_radix(OPENSSL_SK, 16).unwrap();
+        let c = hex::decode(OPENSSL_CIPHER).unwrap();
+        assert_eq!(ctx.decrypt_der(&sk, &c).unwrap(), b"hello sm2");
+    }
+
+    const OPENSSL_SK: &str = "e2910f12b8084ad37de5f73f32bf908326560e287b241086097779ae73156fa5";
+    const OPENSSL_CIPHER: &str = "307202205b6ab36a6bf021ccfdb448487736cb09b50774689939beab5f74a14329fd\
+                                  9005022100c6f0c1ac7093cbf2eace83b9a376cc6902baf365759af2d4c466da6cc1\
+                                  d5ef2e0420f6a4f1ee66006188e887cbad96193543b319b04f5ee5de36e04c820233\
+                                  bee7270409df389f9e9024d22688";
+}
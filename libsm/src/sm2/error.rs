@@ -13,18 +13,20 @@
 // limitations under the License.
 
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Sm2Error {
     NotOnCurve,
     FieldSqrtError,
     InvalidDer,
     InvalidPublic,
     InvalidPrivate,
-}
-
-impl core::fmt::Debug for Sm2Error {
-    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "{:?}", self)
-    }
+    InvalidCiphertext,
+    DecryptFailed,
+    ExchangeFailed,
+    ConfirmFailed,
+    InvalidSignature,
+    InvalidPem,
+    InvalidDigest,
 }
 
 impl From<Sm2Error> for &str {
@@ -35,6 +37,22 @@ impl From<Sm2Error> for &str {
             Sm2Error::InvalidDer => "invalid der",
             Sm2Error::InvalidPublic => "invalid public key",
             Sm2Error::InvalidPrivate => "invalid private key",
+            Sm2Error::InvalidCiphertext => "invalid ciphertext",
+            Sm2Error::DecryptFailed => "decrypt failed",
+            Sm2Error::ExchangeFailed => "key exchange failed",
+            Sm2Error::ConfirmFailed => "key confirmation failed",
+            Sm2Error::InvalidSignature => "invalid signature",
+            Sm2Error::InvalidPem => "invalid pem",
+            Sm2Error::InvalidDigest => "the length of digest must be 32-bytes",
         }
     }
 }
+
+impl core::fmt::Display for Sm2Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str((*self).into())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Sm2Error {}
@@ -0,0 +1,369 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Key exchange protocol of GB/T 32918.3.
+//!
+//! ```text
+//! Initiator (A)                                 Responder (B)
+//! R_A = a.init()            --- R_A --->
+//!                                               (R_B, K_B, S_B) = b.exchange(R_A)
+//!                           <-- R_B, S_B ---
+//! (K_A, S_A) = a.exchange(R_B, Some(S_B))
+//!                           --- S_A --->
+//!                                               b.confirm(S_A)
+//! ```
+
+use alloc::vec::*;
+use super::ecc::*;
+use super::signature::z_a;
+use num_bigint::BigUint;
+use num_traits::*;
+use sm3::hash::Sm3Hash;
+use sm3::kdf::kdf;
+use sm2::error::Sm2Error;
+use rand_core::{CryptoRng, RngCore};
+#[cfg(feature = "getrandom")]
+use rand_core::OsRng;
+use subtle::ConstantTimeEq;
+
+// x_bar = 2^w + (x & (2^w - 1)), w = ceil(ceil(log2(n)) / 2) - 1 = 127
+fn x_bar(x: &BigUint) -> BigUint {
+    let two_w = BigUint::one() << 127;
+    &two_w + (x & (&two_w - BigUint::one()))
+}
+
+// shared secret of both sides, U for the initiator and V for the responder
+struct Shared {
+    x: Vec<u8>,
+    y: Vec<u8>,
+    // Hash(x || Z_A || Z_B || x_1 || y_1 || x_2 || y_2)
+    inner: [u8; 32],
+}
+
+impl Shared {
+    fn key(&self, z_a: &[u8], z_b: &[u8], klen: usize) -> Vec<u8> {
+        let mut z: Vec<u8> = Vec::with_capacity(128);
+        z.extend_from_slice(&self.x);
+        z.extend_from_slice(&self.y);
+        z.extend_from_slice(z_a);
+        z.extend_from_slice(z_b);
+        kdf(&z, klen)
+    }
+
+    // S_B and S_1 use tag 0x02, S_A and S_2 use tag 0x03
+    fn confirmation(&self, tag: u8) -> [u8; 32] {
+        let mut buf: Vec<u8> = Vec::with_capacity(65);
+        buf.push(tag);
+        buf.extend_from_slice(&self.y);
+        buf.extend_from_slice(&self.inner);
+        Sm3Hash::new(&buf[..]).get_hash()
+    }
+}
+
+struct Party {
+    curve: EccCtx,
+    klen: usize,
+    sk: BigUint,
+    z_a: [u8; 32],
+    z_b: [u8; 32],
+    peer: Point,
+}
+
+impl Party {
+    fn new(
+        klen: usize,
+        sk: &BigUint,
+        z_a: [u8; 32],
+        z_b: [u8; 32],
+        peer: &Point,
+        curve: EccCtx,
+    ) -> Result<Party, Sm2Error> {
+        if *sk >= *curve.get_n() || *sk == BigUint::zero() {
+            return Err(Sm2Error::InvalidPrivate);
+        }
+        if peer.is_zero() {
+            return Err(Sm2Error::InvalidPublic);
+        }
+        Ok(Party {
+            curve,
+            klen,
+            sk: sk.clone(),
+            z_a,
+            z_b,
+            peer: peer.clone(),
+        })
+    }
+
+    // [t](P_peer + [x_bar_peer]R_peer), t = (d + x_bar * r) mod n
+    fn agree(&self, r: &BigUint, own_r: &Point, peer_r: &Point) -> Result<Point, Sm2Error> {
+        let curve = &self.curve;
+        if peer_r.is_zero() {
+            return Err(Sm2Error::ExchangeFailed);
+        }
+        let (x, _) = curve.to_affine(own_r);
+        let t = (&self.sk + x_bar(&x.to_biguint()) * r) % curve.get_n();
+
+        let (x_peer, _) = curve.to_affine(peer_r);
+        let p = curve.add(&self.peer, &curve.mul(&x_bar(&x_peer.to_biguint()), peer_r));
+        // the cofactor h of the recommended curve is 1
+        let s = curve.mul(&t, &p);
+        if s.is_zero() {
+            return Err(Sm2Error::ExchangeFailed);
+        }
+        Ok(s)
+    }
+
+    fn shared(&self, s: &Point, r_a: &Point, r_b: &Point) -> Shared {
+        let curve = &self.curve;
+        let (x, y) = curve.to_affine(s);
+        let (x_1, y_1) = curve.to_affine(r_a);
+        let (x_2, y_2) = curve.to_affine(r_b);
+
+        let mut buf: Vec<u8> = Vec::with_capacity(224);
+        buf.extend_from_slice(&x.to_bytes());
+        buf.extend_from_slice(&self.z_a);
+        buf.extend_from_slice(&self.z_b);
+        buf.extend_from_slice(&x_1.to_bytes());
+        buf.extend_from_slice(&y_1.to_bytes());
+        buf.extend_from_slice(&x_2.to_bytes());
+        buf.extend_from_slice(&y_2.to_bytes());
+
+        Shared {
+            x: x.to_bytes(),
+            y: y.to_bytes(),
+            inner: Sm3Hash::new(&buf[..]).get_hash(),
+        }
+    }
+}
+
+/// User A of the protocol, who sends the first message.
+pub struct Initiator {
+    party: Party,
+    r: Option<(BigUint, Point)>,
+}
+
+impl Initiator {
+    /// `klen` is the byte length of the agreed key, `id_a`, `sk_a` and `pk_a` belong to the initiator,
+    /// `id_b` and `pk_b` to the responder.
+    pub fn new(
+        klen: usize,
+        id_a: &str,
+        sk_a: &BigUint,
+        pk_a: &Point,
+        id_b: &str,
+        pk_b: &Point,
+    ) -> Result<Initiator, Sm2Error> {
+        let curve = EccCtx::new();
+        let z_a = z_a_of(&curve, id_a, pk_a)?;
+        let z_b = z_a_of(&curve, id_b, pk_b)?;
+        Ok(Initiator {
+            party: Party::new(klen, sk_a, z_a, z_b, pk_b, curve)?,
+            r: None,
+        })
+    }
+
+    /// Generate the ephemeral key r_A with `OsRng`, returns R_A = [r_A]G to send to the responder.
+    #[cfg(feature = "getrandom")]
+    pub fn init(&mut self) -> Point {
+        self.init_with_rng(&mut OsRng)
+    }
+
+    pub fn init_with_rng<R: RngCore + CryptoRng>(&mut self, rng: &mut R) -> Point {
+        let r = self.party.curve.random_uint_with(rng);
+        self.init_with_r(r)
+    }
+
+    fn init_with_r(&mut self, r: BigUint) -> Point {
+        let big_r = self.party.curve.g_mul(&r);
+        self.r = Some((r, big_r.clone()));
+        big_r
+    }
+
+    /// Compute K_A from the responder's R_B, checks S_B when given.
+    /// Returns K_A and S_A, S_A is sent to the responder when key confirmation is used.
+    pub fn exchange(&mut self, r_b: &Point, s_b: Option<&[u8]>) -> Result<(Vec<u8>, [u8; 32]), Sm2Error> {
+        let (r, r_a) = match self.r.take() {
+            Some(r) => r,
+            None => return Err(Sm2Error::ExchangeFailed),
+        };
+        let party = &self.party;
+        let u = party.agree(&r, &r_a, r_b)?;
+        let shared = party.shared(&u, &r_a, r_b);
+        if let Some(s_b) = s_b {
+            if !bool::from(shared.confirmation(0x02)[..].ct_eq(s_b)) {
+                return Err(Sm2Error::ConfirmFailed);
+            }
+        }
+        let k = shared.key(&party.z_a, &party.z_b, party.klen);
+        Ok((k, shared.confirmation(0x03)))
+    }
+}
+
+/// User B of the protocol, who answers the initiator.
+pub struct Responder {
+    party: Party,
+    s_2: Option<[u8; 32]>,
+}
+
+impl Responder {
+    /// `klen` is the byte length of the agreed key, `id_b`, `sk_b` and `pk_b` belong to the responder,
+    /// `id_a` and `pk_a` to the initiator.
+    pub fn new(
+        klen: usize,
+        id_b: &str,
+        sk_b: &BigUint,
+        pk_b: &Point,
+        id_a: &str,
+        pk_a: &Point,
+    ) -> Result<Responder, Sm2Error> {
+        let curve = EccCtx::new();
+        let z_b = z_a_of(&curve, id_b, pk_b)?;
+        let z_a = z_a_of(&curve, id_a, pk_a)?;
+        Ok(Responder {
+            party: Party::new(klen, sk_b, z_a, z_b, pk_a, curve)?,
+            s_2: None,
+        })
+    }
+
+    /// Answer the initiator's R_A with r_B drawn from `OsRng`, returns R_B, K_B and S_B,
+    /// R_B and S_B are sent to the initiator.
+    #[cfg(feature = "getrandom")]
+    pub fn exchange(&mut self, r_a: &Point) -> Result<(Point, Vec<u8>, [u8; 32]), Sm2Error> {
+        self.exchange_with_rng(r_a, &mut OsRng)
+    }
+
+    pub fn exchange_with_rng<R: RngCore + CryptoRng>(
+        &mut self,
+        r_a: &Point,
+        rng: &mut R,
+    ) -> Result<(Point, Vec<u8>, [u8; 32]), Sm2Error> {
+        let r = self.party.curve.random_uint_with(rng);
+        self.exchange_with_r(r, r_a)
+    }
+
+    fn exchange_with_r(&mut self, r: BigUint, r_a: &Point) -> Result<(Point, Vec<u8>, [u8; 32]), Sm2Error> {
+        let party = &self.party;
+        let r_b = party.curve.g_mul(&r);
+        let v = party.agree(&r, &r_b, r_a)?;
+        let shared = party.shared(&v, r_a, &r_b);
+        let k = shared.key(&party.z_a, &party.z_b, party.klen);
+        self.s_2 = Some(shared.confirmation(0x03));
+        Ok((r_b, k, shared.confirmation(0x02)))
+    }
+
+    /// Check S_A of the initiator against S_2, optional.
+    pub fn confirm(&self, s_a: &[u8]) -> Result<(), Sm2Error> {
+        match self.s_2 {
+            Some(ref s_2) if bool::from(s_2[..].ct_eq(s_a)) => Ok(()),
+            Some(_) => Err(Sm2Error::ConfirmFailed),
+            None => Err(Sm2Error::ExchangeFailed),
+        }
+    }
+}
+
+fn z_a_of(curve: &EccCtx, id: &str, pk: &Point) -> Result<[u8; 32], Sm2Error> {
+    if pk.is_zero() {
+        return Err(Sm2Error::InvalidPublic);
+    }
+    Ok(z_a(curve, id, pk))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sm2::signature::{SigCtx, DEFAULT_ID};
+    use rand_core::OsRng;
+
+    fn key(sk: &str) -> (Point, BigUint) {
+        let sk = BigUint::from_str_radix(sk, 16).unwrap();
+        (EccCtx::new().g_mul(&sk), sk)
+    }
+
+    const ID_A: &str = "ALICE123@YAHOO.COM";
+    const ID_B: &str = "BILL456@YAHOO.COM";
+
+    #[test]
+    fn test_exchange() {
+        let ctx = SigCtx::new();
+        let (pk_a, sk_a) = ctx.new_keypair_with_rng(&mut OsRng);
+        let (pk_b, sk_b) = ctx.new_keypair_with_rng(&mut OsRng);
+
+        let mut a = Initiator::new(48, ID_A, &sk_a, &pk_a, ID_B, &pk_b).unwrap();
+        let mut b = Responder::new(48, ID_B, &sk_b, &pk_b, ID_A, &pk_a).unwrap();
+
+        let r_a = a.init_with_rng(&mut OsRng);
+        let (r_b, k_b, s_b) = b.exchange_with_rng(&r_a, &mut OsRng).unwrap();
+        let (k_a, s_a) = a.exchange(&r_b, Some(&s_b)).unwrap();
+        b.confirm(&s_a).unwrap();
+        assert_eq!(k_a, k_b);
+        assert_eq!(k_a.len(), 48);
+    }
+
+    // key exchange example of GM/T 0003.5-2012 (GB/T 32918.5-2017) Appendix B on the recommended
+    // curve, both parties using the default ID; every expected value is copied from the standard
+    #[test]
+    fn test_vector() {
+        let (pk_a, sk_a) = key("81EB26E941BB5AF16DF116495F90695272AE2CD63D6C4AE1678418BE48230029");
+        let (pk_b, sk_b) = key("785129917D45A9EA5437A59356B82338EAADDA6CEB199088F14AE10DEFA229B5");
+        let r_a = BigUint::from_str_radix("D4DE15474DB74D06491C440D305E012400990F3E390C7E87153C12DB2EA60BB3", 16).unwrap();
+        let r_b = BigUint::from_str_radix("7E07124814B309489125EAED101113164EBF0F3458C5BD88335C1F9D596243D6", 16).unwrap();
+
+        let ctx = EccCtx::new();
+        assert_eq!(hex::encode(ctx.point_to_bytes(&pk_a, false)), P_A);
+        assert_eq!(hex::encode(ctx.point_to_bytes(&pk_b, false)), P_B);
+
+        let mut a = Initiator::new(16, DEFAULT_ID, &sk_a, &pk_a, DEFAULT_ID, &pk_b).unwrap();
+        let mut b = Responder::new(16, DEFAULT_ID, &sk_b, &pk_b, DEFAULT_ID, &pk_a).unwrap();
+        let big_r_a = a.init_with_r(r_a);
+        let (big_r_b, k_b, s_b) = b.exchange_with_r(r_b, &big_r_a).unwrap();
+        assert_eq!(hex::encode(ctx.point_to_bytes(&big_r_a, false)), R_A);
+        assert_eq!(hex::encode(ctx.point_to_bytes(&big_r_b, false)), R_B);
+
+        let (k_a, s_a) = a.exchange(&big_r_b, Some(&s_b)).unwrap();
+        assert_eq!(hex::encode(&k_a), K);
+        assert_eq!(hex::encode(&k_b), K);
+        assert_eq!(hex::encode(s_b), S_B);
+        assert_eq!(hex::encode(s_a), S_A);
+        b.confirm(&s_a).unwrap();
+    }
+
+    const P_A: &str = "04160e12897df4edb61dd812feb96748fbd3ccf4ffe26aa6f6db9540af49c94232\
+                       4a7dad08bb9a459531694beb20aa489d6649975e1bfcf8c4741b78b4b223007f";
+    const P_B: &str = "046ae848c57c53c7b1b5fa99eb2286af078ba64c64591b8b566f7357d576f16dfb\
+                       ee489d771621a27b36c5c7992062e9cd09a9264386f3fbea54dff69305621c4d";
+    const R_A: &str = "0464ced1bdbc99d590049b434d0fd73428cf608a5db8fe5ce07f15026940bae40e\
+                       376629c7ab21e7db260922499ddb118f07ce8eaae3e7720afef6a5cc062070c0";
+    const R_B: &str = "04acc27688a6f7b706098bc91ff3ad1bff7dc2802cdb14ccccdb0a90471f9bd707\
+                       2fedac0494b2ffc4d6853876c79b8f301c6573ad0aa50f39fc87181e1a1b46fe";
+    const K: &str = "6c89347354de2484c60b4ab1fde4c6e5";
+    const S_B: &str = "d3a0fe15dee185ceae907a6b595cc32a266ed7b3367e9983a896dc32fa20f8eb";
+    const S_A: &str = "18c7894b3816df16cf07b05c5ec0bef5d655d58f779cc1b400a4f3884644db88";
+
+    #[test]
+    fn test_confirm_failed() {
+        let (pk_a, sk_a) = key("81EB26E941BB5AF16DF116495F90695272AE2CD63D6C4AE1678418BE48230029");
+        let (pk_b, sk_b) = key("785129917D45A9EA5437A59356B82338EAADDA6CEB199088F14AE10DEFA229B5");
+        let mut a = Initiator::new(16, ID_A, &sk_a, &pk_a, ID_B, &pk_b).unwrap();
+        // B believes A used another ID, so Z_A differs
+        let mut b = Responder::new(16, ID_B, &sk_b, &pk_b, DEFAULT_ID, &pk_a).unwrap();
+
+        let r_a = a.init_with_rng(&mut OsRng);
+        let (r_b, _, s_b) = b.exchange_with_rng(&r_a, &mut OsRng).unwrap();
+        assert!(a.exchange(&r_b, Some(&s_b)).is_err());
+        assert!(b.confirm(&[0u8; 32]).is_err());
+
+        // exchange before init
+        assert!(a.exchange(&r_b, None).is_err());
+    }
+}
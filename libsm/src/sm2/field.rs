@@ -19,6 +19,7 @@ use alloc::vec::*;
 use num_bigint::BigUint;
 use num_traits::Num;
 use sm2::error::Sm2Error;
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
 
 pub struct FieldCtx {
     modulus: FieldElem,
@@ -47,24 +48,29 @@ impl FieldCtx {
         }
     }
 
+    #[inline]
+    pub fn get_modulus(&self) -> &FieldElem {
+        &self.modulus
+    }
+
+    // All of add, sub, mul and inv run in constant time, the reductions are
+    // selected with masks instead of branches.
     pub fn add(&self, a: &FieldElem, b: &FieldElem) -> FieldElem {
         let (raw_sum, carry) = raw_add(a, b);
-        if carry == 1 || raw_sum >= self.modulus {
-            let (sum, _borrow) = raw_sub(&raw_sum, &self.modulus);
-            sum
-        } else {
-            raw_sum
-        }
+        self.reduce_once(&raw_sum, carry)
     }
 
     pub fn sub(&self, a: &FieldElem, b: &FieldElem) -> FieldElem {
         let (raw_diff, borrow) = raw_sub(a, b);
-        if borrow == 1 {
-            let (diff, _borrow) = raw_sub(&raw_diff, &self.modulus_complete);
-            diff
-        } else {
-            raw_diff
-        }
+        let (diff, _borrow) = raw_sub(&raw_diff, &self.modulus_complete);
+        FieldElem::conditional_select(&raw_diff, &diff, Choice::from(borrow as u8))
+    }
+
+    // x + carry * 2^256 - p if that is not negative, else x, requires x + carry * 2^256 < 2p
+    #[inline]
+    fn reduce_once(&self, x: &FieldElem, carry: u32) -> FieldElem {
+        let (diff, borrow) = raw_sub(x, &self.modulus);
+        FieldElem::conditional_select(&diff, x, Choice::from((borrow & !carry & 1) as u8))
     }
 
     // a quick algorithm to reduce elements on SCA-256 field
@@ -142,12 +148,11 @@ impl FieldCtx {
         sum = rt;
         carry -= rc as i32;
 
-        while carry > 0 || sum >= self.modulus {
-            let (rs, rb) = raw_sub(&sum, &self.modulus);
-            sum = rs;
-            carry -= rb as i32;
-        }
-        sum
+        // carry is in [0, 15], fold carry * 2^256 = carry * (2^224 + 2^96 - 2^64 + 1) mod p.
+        // Folding once more is enough, the second fold is of 1 and can't overflow.
+        let (rt, rc) = raw_add(&sum, &fold_carry(carry as u32));
+        let (rt, rc) = raw_add(&rt, &fold_carry(rc));
+        self.reduce_once(&rt, rc)
     }
 
     pub fn mul(&self, a: &FieldElem, b: &FieldElem) -> FieldElem {
@@ -165,49 +170,42 @@ impl FieldCtx {
         self.mul(a, &self.mul(a, a))
     }
 
-    // Extended Eulidean Algorithm(EEA) to calculate x^(-1) mod p
-    // Reference:
-    // http://delta.cs.cinvestav.mx/~francisco/arith/julio.pdf
-    pub fn inv(&self, x: &FieldElem) -> FieldElem {
+    // x^(p - 2) mod p by Fermat's little theorem, constant time unlike the
+    // extended Euclidean algorithm. The addition chain follows the bits of
+    // p - 2 = FFFFFFFE FFFFFFFF FFFFFFFF FFFFFFFF FFFFFFFF 00000000 FFFFFFFF FFFFFFFD
+    // and takes 255 squarings and 13 multiplications. None for zero.
+    pub fn inv(&self, x: &FieldElem) -> Option<FieldElem> {
         if x.is_zero() {
-            panic!("zero has no inversion in filed");
+            return None;
         }
 
-        let mut ru = *x;
-        let mut rv = self.modulus;
-        let mut ra = FieldElem::from_num(1);
-        let mut rc = FieldElem::zero();
-
-        while !ru.is_zero() {
-            if ru.is_even() {
-                ru = ru.div2(0);
-                if ra.is_even() {
-                    ra = ra.div2(0);
-                } else {
-                    let (sum, car) = raw_add(&ra, &self.modulus);
-                    ra = sum.div2(car);
-                }
-            }
-
-            if rv.is_even() {
-                rv = rv.div2(0);
-                if rc.is_even() {
-                    rc = rc.div2(0);
-                } else {
-                    let (sum, car) = raw_add(&rc, &self.modulus);
-                    rc = sum.div2(car);
-                }
-            }
+        // xn = x^(2^n - 1)
+        let x2 = self.mul(&self.square(x), x);
+        let x3 = self.mul(&self.square(&x2), x);
+        let x6 = self.mul(&self.square_n(&x3, 3), &x3);
+        let x12 = self.mul(&self.square_n(&x6, 6), &x6);
+        let x24 = self.mul(&self.square_n(&x12, 12), &x12);
+        let x30 = self.mul(&self.square_n(&x24, 6), &x6);
+        let x31 = self.mul(&self.square(&x30), x);
+        let x32 = self.mul(&self.square(&x31), x);
+
+        let mut t = self.square(&x31);
+        for _ in 0..4 {
+            t = self.mul(&self.square_n(&t, 32), &x32);
+        }
+        t = self.square_n(&t, 32);
+        t = self.mul(&self.square_n(&t, 32), &x32);
+        t = self.mul(&self.square_n(&t, 30), &x30);
+        Some(self.mul(&self.square_n(&t, 2), x))
+    }
 
-            if ru >= rv {
-                ru = self.sub(&ru, &rv);
-                ra = self.sub(&ra, &rc);
-            } else {
-                rv = self.sub(&rv, &ru);
-                rc = self.sub(&rc, &ra);
-            }
+    #[inline]
+    fn square_n(&self, x: &FieldElem, n: usize) -> FieldElem {
+        let mut t = *x;
+        for _ in 0..n {
+            t = self.square(&t);
         }
-        rc
+        t
     }
 
     pub fn neg(&self, x: &FieldElem) -> FieldElem {
@@ -315,18 +313,21 @@ fn raw_sub(a: &FieldElem, b: &FieldElem) -> (FieldElem, u32) {
     while j < 8 {
         let i = 7 - j;
         let t_sum: i64 = i64::from(a.value[i]) - i64::from(b.value[i]) - i64::from(borrow);
-        if t_sum < 0 {
-            sum.value[i] = (t_sum + (1 << 32)) as u32;
-            borrow = 1;
-        } else {
-            sum.value[i] = t_sum as u32;
-            borrow = 0;
-        }
+        // the low 32 bits are the limb of two's complement, the sign bit is the borrow
+        sum.value[i] = t_sum as u32;
+        borrow = ((t_sum >> 63) & 0x01) as u32;
         j += 1;
     }
     (sum, borrow)
 }
 
+// carry * 2^256 mod p = carry * (2^224 + 2^96 - 2^64 + 1) for carry < 2^32
+#[inline(always)]
+fn fold_carry(carry: u32) -> FieldElem {
+    let mid = u64::from(carry) * 0xffff_ffff;
+    FieldElem::new([carry, 0, 0, 0, (mid >> 32) as u32, mid as u32, 0, carry])
+}
+
 #[inline(always)]
 fn u32_mul(a: u32, b: u32) -> (u64, u64) {
     let uv = u64::from(a) * u64::from(b);
@@ -369,6 +370,22 @@ fn raw_mul(a: &FieldElem, b: &FieldElem) -> [u32; 16] {
     ret
 }
 
+impl ConstantTimeEq for FieldElem {
+    fn ct_eq(&self, other: &FieldElem) -> Choice {
+        self.value[..].ct_eq(&other.value[..])
+    }
+}
+
+impl ConditionallySelectable for FieldElem {
+    fn conditional_select(a: &FieldElem, b: &FieldElem, choice: Choice) -> FieldElem {
+        let mut ret = FieldElem::zero();
+        for i in 0..8 {
+            ret.value[i] = u32::conditional_select(&a.value[i], &b.value[i], choice);
+        }
+        ret
+    }
+}
+
 impl FieldElem {
     pub fn new(x: [u32; 8]) -> FieldElem {
         FieldElem { value: x }
@@ -560,11 +577,12 @@ mod tests {
 
         for _x in 1..100 {
             let x = rand_elem();
-            let xinv = ctx.inv(&x);
+            let xinv = ctx.inv(&x).unwrap();
 
             let y = ctx.mul(&x, &xinv);
             assert!(y == one);
         }
+        assert!(ctx.inv(&FieldElem::zero()).is_none());
     }
 
     #[test]
@@ -12,11 +12,30 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+//! SM2 signatures, encryption and key exchange of GB/T 32918.
+//!
+//! Point multiplication and the field arithmetic under it run in constant
+//! time. These paths still take time that depends on secret values:
+//!
+//! - scalar arithmetic modulo n goes through `BigUint`: `EccCtx::inv_n` (a
+//!   binary extended Euclidean algorithm) and the products and reductions
+//!   computing s = (1 + d)^-1 * (k - r * d) in signing
+//! - t = (d + x_bar * r) mod n in the key exchange
+//! - the RFC 6979 nonce generation compares and reduces k and the private
+//!   key as `BigUint`
+//! - converting a private key or k from `BigUint` to bytes, whose cost
+//!   depends on the number of leading zero bytes
+
 pub mod ecc;
+#[cfg(test)]
+mod dudect;
+pub mod encrypt;
 pub mod error;
+pub mod exchange;
 pub mod field;
+pub mod keys;
+pub mod nonce;
+pub mod prepared;
 pub mod signature;
 
-pub fn seed(s: u64) {
-    unsafe { ecc::SEED = s } ; 
-}
+pub use self::prepared::PreparedPublicKey;
@@ -0,0 +1,95 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deterministic nonces of RFC 6979 section 3.2, with HMAC-SM3 as the HMAC.
+
+use num_bigint::BigUint;
+use num_traits::*;
+use sm3::hmac::{hmac_sm3, HmacSm3};
+
+// big-endian, left padded to 32 bytes
+fn int2octets(x: &BigUint) -> [u8; 32] {
+    let v = x.to_bytes_be();
+    let mut ret = [0u8; 32];
+    ret[32 - v.len()..].copy_from_slice(&v);
+    ret
+}
+
+/// HMAC_DRBG of RFC 6979 keyed by the secret key and the digest, yields the candidates of k in order.
+pub struct NonceGen {
+    k: [u8; 32],
+    v: [u8; 32],
+    n: BigUint,
+}
+
+impl NonceGen {
+    /// `n` is the group order, both `sk` and `digest` are reduced modulo `n`.
+    pub fn new(sk: &BigUint, digest: &[u8], n: &BigUint) -> NonceGen {
+        let x = int2octets(&(sk % n));
+        // bits2octets(h1), the order of the curve is 256 bits so bits2int takes the digest as is
+        let h = int2octets(&(BigUint::from_bytes_be(digest) % n));
+
+        let mut k = [0u8; 32];
+        let mut v = [1u8; 32];
+        // K = HMAC_K(V || T || x || h) and V = HMAC_K(V), for T = 0x00 then 0x01
+        for t in 0..2u8 {
+            let mut mac = HmacSm3::new(&k);
+            mac.update(&v);
+            mac.update(&[t]);
+            mac.update(&x);
+            mac.update(&h);
+            k = mac.finalize();
+            v = hmac_sm3(&k, &v);
+        }
+        NonceGen { k, v, n: n.clone() }
+    }
+
+    /// Next k in [1, n - 1], called again when the signature of the previous k is rejected.
+    pub fn next_k(&mut self) -> BigUint {
+        loop {
+            self.v = hmac_sm3(&self.k, &self.v);
+            let k = BigUint::from_bytes_be(&self.v);
+
+            // K = HMAC_K(V || 0x00), V = HMAC_K(V), state for the next candidate
+            let mut mac = HmacSm3::new(&self.k);
+            mac.update(&self.v);
+            mac.update(&[0x00]);
+            self.k = mac.finalize();
+            self.v = hmac_sm3(&self.k, &self.v);
+
+            if k != BigUint::zero() && k < self.n {
+                return k;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nonce_sequence() {
+        let n = BigUint::parse_bytes(b"FFFFFFFEFFFFFFFFFFFFFFFFFFFFFFFF7203DF6B21C6052B53BBF40939D54123", 16).unwrap();
+        let sk = BigUint::parse_bytes(b"3945208F7B2144B13F36E38AC6D39F95889393692860B51A42FB81EF4DF7C5B8", 16).unwrap();
+        let e = hex::decode("F0B43E94BA45ACCAACE692ED534382EB17E6AB5A19CE7B31F4486FDFC0D28640").unwrap();
+        let mut gen = NonceGen::new(&sk, &e, &n);
+        assert_eq!(gen.next_k().to_str_radix(16), K_1);
+        assert_eq!(gen.next_k().to_str_radix(16), K_2);
+    }
+
+    // computed with python's hmac, see `SigCtx` tests for the signature of K_1
+    const K_1: &str = "f7d1eea09846e85224fe81ca11453a10827c315a97b924765c3a1e96d9611628";
+    const K_2: &str = "8ed9ac7d6dc8c2f20dd0bb763305c6f32e60d88df557e11f733c99b95af17b7";
+}
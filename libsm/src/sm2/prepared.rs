@@ -0,0 +1,159 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Public keys prepared once for repeated verification.
+
+use alloc::vec::*;
+use super::ecc::*;
+use super::field::FieldElem;
+use super::signature::{z_a, DEFAULT_ID};
+use sm2::error::Sm2Error;
+
+/// A public key with its affine coordinates, Z_A of one ID and a table of its
+/// odd multiples, used by `SigCtx::verify_prepared`.
+#[derive(Clone)]
+pub struct PreparedPublicKey {
+    // affine, z = 1
+    point: Point,
+    z_a: [u8; 32],
+    // affine odd multiples for a wNAF of width WNAF_G
+    table: Vec<Point>,
+}
+
+impl PreparedPublicKey {
+    /// Prepare `pk` for the default ID.
+    pub fn new(pk: &Point) -> Result<PreparedPublicKey, Sm2Error> {
+        PreparedPublicKey::with_id(DEFAULT_ID, pk)
+    }
+
+    /// Prepare `pk` for `id`.
+    pub fn with_id(id: &str, pk: &Point) -> Result<PreparedPublicKey, Sm2Error> {
+        if pk.is_zero() {
+            return Err(Sm2Error::InvalidPublic);
+        }
+        let curve = EccCtx::new();
+        let (x, y) = curve.to_affine(pk);
+        let point = curve.new_point(&x, &y)?;
+        let z_a = z_a(&curve, id, &point);
+        Ok(PreparedPublicKey::build(&curve, point, z_a))
+    }
+
+    /// Decode a compressed or uncompressed public key and prepare it for `id`.
+    pub fn from_public_key(id: &str, buf: &[u8]) -> Result<PreparedPublicKey, Sm2Error> {
+        let curve = EccCtx::new();
+        let point = curve.bytes_to_point(buf)?;
+        let z_a = z_a(&curve, id, &point);
+        Ok(PreparedPublicKey::build(&curve, point, z_a))
+    }
+
+    fn build(curve: &EccCtx, point: Point, z_a: [u8; 32]) -> PreparedPublicKey {
+        let table = curve.odd_multiples_affine(&point, WNAF_G);
+        PreparedPublicKey { point, z_a, table }
+    }
+
+    #[inline]
+    pub fn point(&self) -> &Point {
+        &self.point
+    }
+
+    #[inline]
+    pub fn z_a(&self) -> &[u8; 32] {
+        &self.z_a
+    }
+
+    #[inline]
+    pub(crate) fn table(&self) -> &[Point] {
+        &self.table
+    }
+
+    /// 04 || x || y || Z_A, 97 bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut ret: Vec<u8> = Vec::with_capacity(97);
+        ret.push(0x04);
+        ret.extend_from_slice(&self.point.x.to_bytes());
+        ret.extend_from_slice(&self.point.y.to_bytes());
+        ret.extend_from_slice(&self.z_a);
+        ret
+    }
+
+    /// Inverse of `to_bytes`. The point is checked to be on the curve and the
+    /// table is rebuilt, Z_A is taken as is since the ID is not stored.
+    pub fn from_bytes(buf: &[u8]) -> Result<PreparedPublicKey, Sm2Error> {
+        if buf.len() != 97 || buf[0] != 0x04 {
+            return Err(Sm2Error::InvalidPublic);
+        }
+        let curve = EccCtx::new();
+        let x = FieldElem::from_bytes(&buf[1..33]);
+        let y = FieldElem::from_bytes(&buf[33..65]);
+        let point = curve.new_point(&x, &y)?;
+
+        let mut z_a = [0u8; 32];
+        z_a.copy_from_slice(&buf[65..97]);
+        Ok(PreparedPublicKey::build(&curve, point, z_a))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_core::OsRng;
+    use sm2::signature::SigCtx;
+
+    #[test]
+    fn test_verify_prepared() {
+        let ctx = SigCtx::new();
+        let (pk, sk) = ctx.new_keypair_with_rng(&mut OsRng);
+        let prepared = PreparedPublicKey::new(&pk).unwrap();
+        assert_eq!(*prepared.z_a(), ctx.z_a(DEFAULT_ID, &pk));
+
+        for i in 0..10u32 {
+            let msg = i.to_be_bytes();
+            let sig = ctx.sign(&msg, &sk, &pk);
+            assert!(ctx.verify_prepared(&msg, &prepared, &sig));
+            assert!(!ctx.verify_prepared(b"another message", &prepared, &sig));
+        }
+
+        // a key prepared for another ID
+        let id = "ALICE123@YAHOO.COM";
+        let digest = ctx.hash(id, &pk, b"message digest");
+        let sig = ctx.sign_raw(&digest, &sk);
+        let prepared = PreparedPublicKey::with_id(id, &pk).unwrap();
+        assert!(ctx.verify_prepared(b"message digest", &prepared, &sig));
+        assert!(ctx.verify_raw_prepared(&digest, &prepared, &sig));
+        assert!(!ctx.verify(b"message digest", &pk, &sig));
+    }
+
+    #[test]
+    fn test_serialization() {
+        let ctx = SigCtx::new();
+        let (pk, sk) = ctx.new_keypair_with_rng(&mut OsRng);
+        let sig = ctx.sign(b"abc", &sk, &pk);
+
+        let compressed = ctx.serialize_pubkey(&pk, true);
+        let prepared = PreparedPublicKey::from_public_key(DEFAULT_ID, &compressed).unwrap();
+        assert!(ctx.verify_prepared(b"abc", &prepared, &sig));
+
+        let bytes = prepared.to_bytes();
+        assert_eq!(bytes.len(), 97);
+        let loaded = PreparedPublicKey::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.to_bytes(), bytes);
+        assert!(ctx.verify_prepared(b"abc", &loaded, &sig));
+
+        let mut bad = bytes.clone();
+        bad[64] ^= 1;
+        assert!(PreparedPublicKey::from_bytes(&bad).is_err());
+        assert!(PreparedPublicKey::from_bytes(&bytes[..96]).is_err());
+        assert!(PreparedPublicKey::new(&EccCtx::new().zero()).is_err());
+    }
+}
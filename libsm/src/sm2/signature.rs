@@ -14,16 +14,33 @@
 
 use alloc::vec::*;
 use super::ecc::*;
-use super::field::FieldElem;
+use super::field::{FieldCtx, FieldElem};
 use num_bigint::BigUint;
 use num_traits::*;
-use sm3::hash::Sm3Hash;
+use sm3::hash::Sm3Hasher;
 use sm2::error::Sm2Error;
+use sm2::nonce::NonceGen;
+use sm2::prepared::PreparedPublicKey;
+use rand_core::{CryptoRng, RngCore};
+#[cfg(feature = "getrandom")]
+use rand_core::OsRng;
 use yasna;
 
 pub type Pubkey = Point;
 pub type Seckey = BigUint;
 
+/// Which DER encodings of a signature are accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DerMode {
+    /// Only canonical DER, as `Signature::der_decode`. A signature has one
+    /// encoding, so its bytes can't be changed without invalidating it.
+    Strict,
+    /// Also the non-canonical encodings of `Signature::der_decode_lax`.
+    /// Anyone can re-encode a valid signature into another valid one.
+    Lax,
+}
+
+#[derive(Clone)]
 pub struct Signature {
     r: BigUint,
     s: BigUint,
@@ -36,15 +53,43 @@ impl Signature {
         Signature { r, s }
     }
 
-    pub fn der_decode(buf: &[u8]) -> Result<Signature, yasna::ASN1Error> {
-        let (r, s) = yasna::parse_der(buf, |reader| {
-            reader.read_sequence(|reader| {
-                let r = reader.next().read_biguint()?;
-                let s = reader.next().read_biguint()?;
-                Ok((r, s))
-            })
-        })?;
-        Ok(Signature { r, s })
+    /// r || s, 32 bytes each, with r and s in [1, n - 1].
+    pub fn from_bytes(buf: &[u8]) -> Result<Signature, Sm2Error> {
+        if buf.len() != 64 {
+            return Err(Sm2Error::InvalidSignature);
+        }
+        Signature::checked(BigUint::from_bytes_be(&buf[..32]), BigUint::from_bytes_be(&buf[32..]))
+    }
+
+    /// r || s, 32 bytes each. Only the low 32 bytes of an oversized r or s
+    /// made by `new` are kept.
+    pub fn to_bytes(&self) -> [u8; 64] {
+        let mut ret = [0u8; 64];
+        for (out, x) in ret.chunks_mut(32).zip([&self.r, &self.s].iter()) {
+            let x = x.to_bytes_be();
+            let len = x.len().min(32);
+            out[32 - len..].copy_from_slice(&x[x.len() - len..]);
+        }
+        ret
+    }
+
+    /// Canonical DER, `SEQUENCE { INTEGER r, INTEGER s }`, with r and s in
+    /// [1, n - 1]. Any other encoding of the same signature is rejected, so
+    /// the bytes of a valid signature are unique.
+    pub fn der_decode(buf: &[u8]) -> Result<Signature, Sm2Error> {
+        Signature::decode_sequence(buf, true)
+    }
+
+    /// Like `der_decode` but accepts what some signers write: long form
+    /// lengths, integers with redundant leading zeros and integers missing
+    /// the zero that keeps them positive. The range of r and s is checked.
+    pub fn der_decode_lax(buf: &[u8]) -> Result<Signature, Sm2Error> {
+        Signature::decode_sequence(buf, false)
+    }
+
+    /// `der_decode` or `der_decode_lax`, as chosen by `mode`.
+    pub fn der_decode_with(buf: &[u8], mode: DerMode) -> Result<Signature, Sm2Error> {
+        Signature::decode_sequence(buf, mode == DerMode::Strict)
     }
 
     pub fn der_encode(&self) -> Vec<u8> {
@@ -54,28 +99,35 @@ impl Signature {
                 writer.next().write_biguint(&self.s);
             })
         })
-    }    
+    }
 
+    /// The two canonical DER integers without the SEQUENCE around them.
     pub fn der_decode_raw(buf: &[u8]) -> Result<Signature, Sm2Error> {
-        if buf[0] != 0x02 {
-            return Err(Sm2Error::InvalidDer);
-        }
-        let r_len: usize = buf[1] as usize;
-        if buf.len() <= r_len + 4 {
-            return Err(Sm2Error::InvalidDer);
-        }
-        let r = BigUint::from_bytes_be(&buf[2..2 + r_len]);
+        Signature::decode_integers(buf, true)
+    }
 
-        let buf = &buf[2 + r_len..];
-        if buf[0] != 0x02 {
+    fn decode_sequence(buf: &[u8], strict: bool) -> Result<Signature, Sm2Error> {
+        let (content, rest) = der_read(buf, 0x30, strict)?;
+        if !rest.is_empty() {
             return Err(Sm2Error::InvalidDer);
         }
-        let s_len: usize = buf[1] as usize;
-        if buf.len() < s_len + 2 {
+        Signature::decode_integers(content, strict)
+    }
+
+    fn decode_integers(buf: &[u8], strict: bool) -> Result<Signature, Sm2Error> {
+        let (r, rest) = der_read(buf, 0x02, strict)?;
+        let (s, rest) = der_read(rest, 0x02, strict)?;
+        if !rest.is_empty() {
             return Err(Sm2Error::InvalidDer);
         }
-        let s = BigUint::from_bytes_be(&buf[2..2 + s_len]);
+        Signature::checked(der_uint(r, strict)?, der_uint(s, strict)?)
+    }
 
+    fn checked(r: BigUint, s: BigUint) -> Result<Signature, Sm2Error> {
+        let n = EccCtx::new().get_n().clone();
+        if r.is_zero() || s.is_zero() || r >= n || s >= n {
+            return Err(Sm2Error::InvalidSignature);
+        }
         Ok(Signature { r, s })
     }
 
@@ -98,94 +150,119 @@ impl Signature {
     }
 }
 
-pub struct SigCtx {
-    curve: EccCtx,
+// the content of the DER element at the start of `buf` with the given tag,
+// and the bytes after it. A signature is at most 72 bytes, so canonical
+// lengths are always in the short form.
+fn der_read(buf: &[u8], tag: u8, strict: bool) -> Result<(&[u8], &[u8]), Sm2Error> {
+    if buf.len() < 2 || buf[0] != tag {
+        return Err(Sm2Error::InvalidDer);
+    }
+    let (len, header) = match buf[1] {
+        len if len < 0x80 => (len as usize, 2),
+        0x81 if !strict && buf.len() > 2 => (buf[2] as usize, 3),
+        0x82 if !strict && buf.len() > 3 => (((buf[2] as usize) << 8) | buf[3] as usize, 4),
+        _ => return Err(Sm2Error::InvalidDer),
+    };
+    if buf.len() - header < len {
+        return Err(Sm2Error::InvalidDer);
+    }
+    Ok((&buf[header..header + len], &buf[header + len..]))
 }
 
-impl SigCtx {
-    pub fn new() -> SigCtx {
-        SigCtx {
-            curve: EccCtx::new(),
-        }
+// a non-negative INTEGER of at most 32 bytes besides leading zeros
+fn der_uint(content: &[u8], strict: bool) -> Result<BigUint, Sm2Error> {
+    if content.is_empty() {
+        return Err(Sm2Error::InvalidDer);
     }
-
-    pub fn hash(&self, id: &str, pk: &Point, msg: &[u8]) -> [u8; 32] {
-        let curve = &self.curve;
-
-        let mut prepend: Vec<u8> = Vec::new();
-        if id.len() * 8 > 65535 {
-            panic!("ID is too long.");
-        }
-        let to_write = (id.len() * 8) as u16;
-        prepend.extend_from_slice(&to_write.to_be_bytes());
-        for c in id.bytes() {
-            prepend.push(c);
+    if strict {
+        // negative, or a zero byte that isn't needed for the sign
+        if content[0] & 0x80 != 0 || (content.len() > 1 && content[0] == 0 && content[1] & 0x80 == 0) {
+            return Err(Sm2Error::InvalidDer);
         }
+    }
+    let start = content.iter().position(|b| *b != 0).unwrap_or(content.len());
+    if content.len() - start > 32 {
+        return Err(Sm2Error::InvalidSignature);
+    }
+    Ok(BigUint::from_bytes_be(&content[start..]))
+}
 
-        let mut a = curve.get_a().to_bytes();
-        let mut b = curve.get_b().to_bytes();
+// the default ID of GB/T 35276, used when the caller gives none
+pub(crate) const DEFAULT_ID: &str = "1234567812345678";
 
-        prepend.append(&mut a);
-        prepend.append(&mut b);
+// entries of a batch per thread, below this threads cost more than they save
+#[cfg(feature = "std")]
+const BATCH_CHUNK_MIN: usize = 16;
 
-        let (x_g, y_g) = curve.to_affine(&curve.generator());
-        let (mut x_g, mut y_g) = (x_g.to_bytes(), y_g.to_bytes());
-        prepend.append(&mut x_g);
-        prepend.append(&mut y_g);
+// ENTL_A || ID_A || a || b || x_G || y_G, the part of Z_A shared by all keys
+fn z_a_prefix(curve: &EccCtx, id: &str) -> Vec<u8> {
+    let mut prepend: Vec<u8> = Vec::new();
+    if id.len() * 8 > 65535 {
+        panic!("ID is too long.");
+    }
+    let to_write = (id.len() * 8) as u16;
+    prepend.extend_from_slice(&to_write.to_be_bytes());
+    for c in id.bytes() {
+        prepend.push(c);
+    }
 
-        let (x_a, y_a) = curve.to_affine(pk);
-        let (mut x_a, mut y_a) = (x_a.to_bytes(), y_a.to_bytes());
-        prepend.append(&mut x_a);
-        prepend.append(&mut y_a);
+    let mut a = curve.get_a().to_bytes();
+    let mut b = curve.get_b().to_bytes();
 
-        let mut hasher = Sm3Hash::new(&prepend[..]);
-        let z_a = hasher.get_hash();
+    prepend.append(&mut a);
+    prepend.append(&mut b);
 
-        // Z_A = HASH_256(ID_LEN || ID || x_G || y_G || x_A || y_A)
+    // the generator is already affine
+    let g = curve.generator();
+    let (mut x_g, mut y_g) = (g.x.to_bytes(), g.y.to_bytes());
+    prepend.append(&mut x_g);
+    prepend.append(&mut y_g);
+    prepend
+}
 
-        // e = HASH_256(Z_A || M)
+fn z_a_with_prefix(prefix: &[u8], x_a: &FieldElem, y_a: &FieldElem) -> [u8; 32] {
+    let mut hasher = Sm3Hasher::new();
+    hasher.update(prefix);
+    hasher.update(&x_a.to_bytes());
+    hasher.update(&y_a.to_bytes());
+    hasher.finalize()
+}
 
-        let mut prepended_msg: Vec<u8> = Vec::new();
-        prepended_msg.extend_from_slice(&z_a[..]);
-        prepended_msg.extend_from_slice(msg);
+// e = HASH_256(Z_A || M)
+fn e_hash(z_a: &[u8], msg: &[u8]) -> [u8; 32] {
+    let mut hasher = Sm3Hasher::new();
+    hasher.update(z_a);
+    hasher.update(msg);
+    hasher.finalize()
+}
 
-        let mut hasher = Sm3Hash::new(&prepended_msg[..]);
-        hasher.get_hash()
-    }
+pub(crate) fn z_a(curve: &EccCtx, id: &str, pk: &Point) -> [u8; 32] {
+    let (x_a, y_a) = curve.to_affine(pk);
+    z_a_with_prefix(&z_a_prefix(curve, id), &x_a, &y_a)
+}
 
-    pub fn recid_combine(&self, id: &str, pk: &Point, msg: &[u8]) -> Vec<u8> {
-        let curve = &self.curve;
+pub struct SigCtx {
+    curve: EccCtx,
+}
 
-        let mut prepend: Vec<u8> = Vec::new();
-        if id.len() * 8 > 65535 {
-            panic!("ID is too long.");
-        }
-        let to_write = (id.len() * 8) as u16;
-        prepend.extend_from_slice(&to_write.to_be_bytes());
-        for c in id.bytes() {
-            prepend.push(c);
+impl SigCtx {
+    pub fn new() -> SigCtx {
+        SigCtx {
+            curve: EccCtx::new(),
         }
+    }
 
-        let mut a = curve.get_a().to_bytes();
-        let mut b = curve.get_b().to_bytes();
-
-        prepend.append(&mut a);
-        prepend.append(&mut b);
-
-        let (x_g, y_g) = curve.to_affine(&curve.generator());
-        let (mut x_g, mut y_g) = (x_g.to_bytes(), y_g.to_bytes());
-        prepend.append(&mut x_g);
-        prepend.append(&mut y_g);
-
-        let (x_a, y_a) = curve.to_affine(pk);
-        let (mut x_a, mut y_a) = (x_a.to_bytes(), y_a.to_bytes());
-        prepend.append(&mut x_a);
-        prepend.append(&mut y_a);
+    /// Z_A = HASH_256(ENTL_A || ID_A || a || b || x_G || y_G || x_A || y_A)
+    pub fn z_a(&self, id: &str, pk: &Point) -> [u8; 32] {
+        z_a(&self.curve, id, pk)
+    }
 
-        let mut hasher = Sm3Hash::new(&prepend[..]);
-        let z_a = hasher.get_hash();
+    pub fn hash(&self, id: &str, pk: &Point, msg: &[u8]) -> [u8; 32] {
+        e_hash(&self.z_a(id, pk), msg)
+    }
 
-        // Z_A = HASH_256(ID_LEN || ID || x_G || y_G || x_A || y_A)
+    pub fn recid_combine(&self, id: &str, pk: &Point, msg: &[u8]) -> Vec<u8> {
+        let z_a = self.z_a(id, pk);
 
         // e = HASH_256(Z_A || M)
 
@@ -197,27 +274,84 @@ impl SigCtx {
     }
 
     pub fn sign(&self, msg: &[u8], sk: &BigUint, pk: &Point) -> Signature {
+        self.sign_with_id(DEFAULT_ID, msg, sk, pk)
+    }
+
+    /// Sign `msg` with Z_A of the distinguishing identifier `id` instead of the default ID.
+    pub fn sign_with_id(&self, id: &str, msg: &[u8], sk: &BigUint, pk: &Point) -> Signature {
         // Get the value "e", which is the hash of message and ID, EC parameters and public key
-        let digest = self.hash("1234567812345678", pk, msg);
+        let digest = self.hash(id, pk, msg);
 
         self.sign_raw(&digest[..], sk)
     }
 
+    /// Sign `digest` with the deterministic k of RFC 6979 derived by HMAC-SM3 from `sk` and `digest`.
     pub fn sign_raw(&self, digest: &[u8], sk: &BigUint) -> Signature {
+        self.sign_recoverable(digest, sk).0
+    }
+
+    /// Like `sign_raw`, but a private key outside [1, n - 2] or a digest that is not 32 bytes is an
+    /// error instead of a panic.
+    pub fn sign_raw_checked(&self, digest: &[u8], sk: &BigUint) -> Result<Signature, Sm2Error> {
+        Ok(self.sign_recoverable_checked(digest, sk)?.0)
+    }
+
+    /// Sign `digest` with k drawn from `rng` instead of the deterministic k.
+    pub fn sign_raw_with_rng<R: RngCore + CryptoRng>(
+        &self,
+        digest: &[u8],
+        sk: &BigUint,
+        rng: &mut R,
+    ) -> Signature {
+        self.sign_with_nonce(digest, sk, || self.curve.random_uint_with(rng))
+            .0
+    }
+
+    /// Sign `digest` like `sign_raw`, also returns the recovery id for `recover_public_key`.
+    /// Bit 0 of the recovery id is the parity of y_1, bit 1 is set when x_1 >= n.
+    pub fn sign_recoverable(&self, digest: &[u8], sk: &BigUint) -> (Signature, u8) {
+        let mut nonce = NonceGen::new(sk, digest, self.curve.get_n());
+        self.sign_with_nonce(digest, sk, || nonce.next_k())
+    }
+
+    /// Like `sign_recoverable`, but a private key outside [1, n - 2] or a digest that is not 32 bytes
+    /// is an error instead of a panic.
+    pub fn sign_recoverable_checked(&self, digest: &[u8], sk: &BigUint) -> Result<(Signature, u8), Sm2Error> {
+        if digest.len() != 32 {
+            return Err(Sm2Error::InvalidDigest);
+        }
+        self.check_seckey(sk)?;
+        Ok(self.sign_recoverable(digest, sk))
+    }
+
+    fn sign_with_nonce<F: FnMut() -> BigUint>(
+        &self,
+        digest: &[u8],
+        sk: &BigUint,
+        mut next_k: F,
+    ) -> (Signature, u8) {
         let curve = &self.curve;
         // Get the value "e", which is the hash of message and ID, EC parameters and public key
 
         let e = BigUint::from_bytes_be(digest);
+        // (1 + sk)^-1, sk = n - 1 is outside [1, n - 2] and has no signature
+        let s1 = curve
+            .inv_n(&(sk + BigUint::one()))
+            .expect("the private key must be in [1, n - 2]");
 
         // two while loops
         loop {
             // k = rand()
             // (x_1, y_1) = g^kg
-            let k = self.curve.random_uint();
+            let k = next_k();
 
             let p_1 = curve.g_mul(&k);
-            let (x_1, _) = curve.to_affine(&p_1);
+            let (x_1, y_1) = curve.to_affine(&p_1);
             let x_1 = x_1.to_biguint();
+            let mut recid = if y_1.is_even() { 0 } else { 1 };
+            if x_1 >= *curve.get_n() {
+                recid |= 2;
+            }
 
             // r = e + x_1
             let r = (&e + x_1) % curve.get_n();
@@ -226,8 +360,6 @@ impl SigCtx {
             }
 
             // s = (1 + sk)^-1 * (k - r * sk)
-            let s1 = curve.inv_n(&(sk + BigUint::one()));
-
             let mut s2_1 = &r * sk;
             if s2_1 < k {
                 s2_1 += curve.get_n();
@@ -236,24 +368,117 @@ impl SigCtx {
             s2 %= curve.get_n();
             let s2 = curve.get_n() - s2;
 
-            let s = (s1 * s2) % curve.get_n();
+            let s = (&s1 * s2) % curve.get_n();
 
             if s != BigUint::zero() {
                 // Output the signature (r, s)
-                return Signature { r, s };
+                return (Signature { r, s }, recid);
             }
-            panic!("cannot sign")
         }
     }
 
+    /// Recover the public key from a signature of `sign_recoverable`.
+    /// `digest` must not depend on the public key, i.e. it is not the e of `sign` which contains Z_A.
+    pub fn recover_public_key(&self, digest: &[u8], sig: &Signature, recid: u8) -> Result<Point, Sm2Error> {
+        let curve = &self.curve;
+        let n = curve.get_n();
+        let (r, s) = (sig.get_r(), sig.get_s());
+        if recid > 3 || *r == BigUint::zero() || *s == BigUint::zero() || r >= n || s >= n {
+            return Err(Sm2Error::InvalidSignature);
+        }
+
+        // x_1 = r - e mod n, k = s + (r + s) * sk
+        let e = BigUint::from_bytes_be(digest) % n;
+        let mut x_1 = (r + n - e) % n;
+        if recid & 2 != 0 {
+            x_1 += n;
+            if x_1 >= FieldCtx::new().get_modulus().to_biguint() {
+                return Err(Sm2Error::InvalidSignature);
+            }
+        }
+        let t = (r + s) % n;
+        if t == BigUint::zero() {
+            return Err(Sm2Error::InvalidSignature);
+        }
+
+        let mut buf = FieldElem::from_biguint(&x_1).to_bytes();
+        buf.insert(0, 0x02 | (recid & 1));
+        let p_1 = curve
+            .bytes_to_point(&buf)
+            .map_err(|_| Sm2Error::InvalidSignature)?;
+
+        // pk = (r + s)^-1 * (R - [s]G) = [-s * (r + s)^-1]G + [(r + s)^-1]R
+        let n = curve.get_n();
+        let t_inv = curve.inv_n(&t).ok_or(Sm2Error::InvalidSignature)?;
+        let a = (n - (s * &t_inv) % n) % n;
+        let pk = curve.double_scalar_mul(&a, &t_inv, &p_1);
+        if pk.is_zero() {
+            return Err(Sm2Error::InvalidSignature);
+        }
+        Ok(pk)
+    }
+
     pub fn verify(&self, msg: &[u8], pk: &Point, sig: &Signature) -> bool {
+        self.verify_with_id(DEFAULT_ID, msg, pk, sig)
+    }
+
+    /// Verify a signature made by `sign_with_id` with the same `id`.
+    pub fn verify_with_id(&self, id: &str, msg: &[u8], pk: &Point, sig: &Signature) -> bool {
         //Get hash value
-        let digest = self.hash("1234567812345678", pk, msg);
+        let digest = self.hash(id, pk, msg);
         //println!("digest: {:?}", digest);
         self.verify_raw(&digest[..], pk, sig)
     }
 
+    /// Verify a DER signature, parsed as `mode` allows. A signature that
+    /// doesn't parse doesn't verify.
+    pub fn verify_der(&self, msg: &[u8], pk: &Point, der: &[u8], mode: DerMode) -> bool {
+        self.verify_der_with_id(DEFAULT_ID, msg, pk, der, mode)
+    }
+
+    pub fn verify_der_with_id(
+        &self,
+        id: &str,
+        msg: &[u8],
+        pk: &Point,
+        der: &[u8],
+        mode: DerMode,
+    ) -> bool {
+        match Signature::der_decode_with(der, mode) {
+            Ok(sig) => self.verify_with_id(id, msg, pk, &sig),
+            Err(_) => false,
+        }
+    }
+
     pub fn verify_raw(&self, digest: &[u8], pk: &Point, sig: &Signature) -> bool {
+        self.verify_with(digest, sig, |s, t| self.curve.double_scalar_mul(s, t, pk))
+    }
+
+    /// Like `verify_raw`, but a digest that is not 32 bytes is an error instead of a panic.
+    pub fn verify_raw_checked(&self, digest: &[u8], pk: &Point, sig: &Signature) -> Result<bool, Sm2Error> {
+        if digest.len() != 32 {
+            return Err(Sm2Error::InvalidDigest);
+        }
+        Ok(self.verify_raw(digest, pk, sig))
+    }
+
+    /// `verify` with a prepared public key, the ID is the one the key was prepared for.
+    pub fn verify_prepared(&self, msg: &[u8], pk: &PreparedPublicKey, sig: &Signature) -> bool {
+        let digest = e_hash(pk.z_a(), msg);
+        self.verify_raw_prepared(&digest, pk, sig)
+    }
+
+    pub fn verify_raw_prepared(&self, digest: &[u8], pk: &PreparedPublicKey, sig: &Signature) -> bool {
+        self.verify_with(digest, sig, |s, t| {
+            self.curve.double_scalar_mul_table(s, t, pk.table(), WNAF_G)
+        })
+    }
+
+    // checks of verify_raw around p_1 = mul(s, t) = [s]G + [t]P
+    fn verify_with<F>(&self, digest: &[u8], sig: &Signature, mul: F) -> bool
+    where
+        F: FnOnce(&BigUint, &BigUint) -> Point,
+    {
         if digest.len() != 32 {
             panic!("the length of digest must be 32-bytes.");
         }
@@ -274,7 +499,10 @@ impl SigCtx {
             return false;
         }
 
-        let p_1 = curve.add(&curve.g_mul(sig.get_s()), &curve.mul(&t, pk));
+        let p_1 = mul(sig.get_s(), &t);
+        if p_1.is_zero() {
+            return false;
+        }
         let (x_1, _) = curve.to_affine(&p_1);
         let x_1 = x_1.to_biguint();
 
@@ -284,16 +512,109 @@ impl SigCtx {
         r_ == *sig.get_r()
     }
 
+    /// Verify `(msg, pk, sig)` entries with the default ID, the error holds the
+    /// indices of the entries that fail.
+    ///
+    /// Z_A shares its curve part across entries and the affine conversions of
+    /// a batch take a single field inversion. With the `std` feature large
+    /// batches are split across threads.
+    pub fn verify_batch(&self, items: &[(&[u8], &Point, &Signature)]) -> Result<(), Vec<usize>> {
+        let failed: Vec<usize> = self
+            .verify_batch_flags(items)
+            .iter()
+            .enumerate()
+            .filter(|(_, ok)| !**ok)
+            .map(|(i, _)| i)
+            .collect();
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(failed)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn verify_batch_flags(&self, items: &[(&[u8], &Point, &Signature)]) -> Vec<bool> {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk = std::cmp::max(BATCH_CHUNK_MIN, items.len().div_ceil(threads));
+        if items.len() <= chunk {
+            return self.verify_chunk(items);
+        }
+
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = items
+                .chunks(chunk)
+                .map(|c| scope.spawn(move || self.verify_chunk(c)))
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+                .collect()
+        })
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn verify_batch_flags(&self, items: &[(&[u8], &Point, &Signature)]) -> Vec<bool> {
+        self.verify_chunk(items)
+    }
+
+    fn verify_chunk(&self, items: &[(&[u8], &Point, &Signature)]) -> Vec<bool> {
+        let curve = &self.curve;
+        let n = curve.get_n();
+        let prefix = z_a_prefix(curve, DEFAULT_ID);
+
+        let pks: Vec<Point> = items.iter().map(|item| item.1.clone()).collect();
+        let pks = curve.batch_to_affine(&pks);
+
+        // e and [s]G + [t]P of each entry, None once the entry fails
+        let mut es: Vec<Option<BigUint>> = Vec::with_capacity(items.len());
+        let mut points: Vec<Point> = Vec::with_capacity(items.len());
+        for (&(msg, pk, sig), pk_affine) in items.iter().zip(pks.iter()) {
+            let (r, s) = (sig.get_r(), sig.get_s());
+            let t = (s + r) % n;
+            let (x_a, y_a) = match pk_affine {
+                Some(ref xy) if !r.is_zero() && !s.is_zero() && r < n && s < n && !t.is_zero() => xy,
+                _ => {
+                    es.push(None);
+                    points.push(curve.zero());
+                    continue;
+                }
+            };
+
+            let digest = e_hash(&z_a_with_prefix(&prefix, x_a, y_a), msg);
+
+            es.push(Some(BigUint::from_bytes_be(&digest)));
+            points.push(curve.double_scalar_mul(s, &t, pk));
+        }
+
+        curve
+            .batch_to_affine(&points)
+            .into_iter()
+            .zip(es)
+            .zip(items.iter())
+            .map(|((p_1, e), item)| match (p_1, e) {
+                (Some((x_1, _)), Some(e)) => (e + x_1.to_biguint()) % n == *item.2.get_r(),
+                _ => false,
+            })
+            .collect()
+    }
+
+    /// Generate a key pair with `OsRng`.
+    #[cfg(feature = "getrandom")]
     pub fn new_keypair(&self) -> (Point, BigUint) {
+        self.new_keypair_with_rng(&mut OsRng)
+    }
+
+    pub fn new_keypair_with_rng<R: RngCore + CryptoRng>(&self, rng: &mut R) -> (Point, BigUint) {
         let curve = &self.curve;
-        let mut sk: BigUint = curve.random_uint();
+        let mut sk: BigUint = curve.random_uint_with(rng);
         let mut pk: Point = curve.g_mul(&sk);
 
         loop {
             if !pk.is_zero() {
                 break;
             }
-            sk = curve.random_uint();
+            sk = curve.random_uint_with(rng);
             pk = curve.g_mul(&sk);
         }
 
@@ -302,18 +623,23 @@ impl SigCtx {
 
     pub fn pk_from_sk(&self, sk: &BigUint) -> Point {
         let curve = &self.curve;
-        if *sk >= *curve.get_n() || *sk == BigUint::zero() {
+        if *sk >= curve.get_n() - BigUint::one() || *sk == BigUint::zero() {
             panic!("invalid seckey");
         }
-        curve.mul(&sk, &curve.generator())
+        curve.mul(sk, &curve.generator())
     }
 
     pub fn pk_from_sk_checked(&self, sk: &BigUint) -> Result<Point, Sm2Error> {
-        let curve = &self.curve;
-        if *sk >= *curve.get_n() || *sk == BigUint::zero() {
-            return Err(Sm2Error::InvalidPrivate)
+        self.check_seckey(sk)?;
+        Ok(self.curve.mul(sk, &self.curve.generator()))
+    }
+
+    // a private key is in [1, n - 2], n - 1 has no signature as 1 + sk isn't invertible
+    fn check_seckey(&self, sk: &BigUint) -> Result<(), Sm2Error> {
+        if *sk >= self.curve.get_n() - BigUint::one() || *sk == BigUint::zero() {
+            return Err(Sm2Error::InvalidPrivate);
         }
-        Ok(curve.mul(&sk, &curve.generator()))
+        Ok(())
     }
 
     pub fn load_pubkey(&self, buf: &[u8]) -> Result<Point, Sm2Error> {
@@ -329,11 +655,8 @@ impl SigCtx {
             return Err(Sm2Error::InvalidPrivate);
         }
         let sk = BigUint::from_bytes_be(buf);
-        if sk > *self.curve.get_n() {
-            Err(Sm2Error::InvalidPrivate)
-        } else {
-            Ok(sk)
-        }
+        self.check_seckey(&sk)?;
+        Ok(sk)
     }
 
     pub fn serialize_seckey(&self, x: &BigUint) -> Vec<u8> {
@@ -343,6 +666,13 @@ impl SigCtx {
         let x = FieldElem::from_biguint(x);
         x.to_bytes()
     }
+
+    pub fn serialize_seckey_checked(&self, x: &BigUint) -> Result<Vec<u8>, Sm2Error> {
+        if *x >= *self.curve.get_n() || *x == BigUint::zero() {
+            return Err(Sm2Error::InvalidPrivate);
+        }
+        Ok(FieldElem::from_biguint(x).to_bytes())
+    }
 }
 
 impl Default for SigCtx {
@@ -354,6 +684,8 @@ impl Default for SigCtx {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand_core::OsRng;
+    use sm3::hash::Sm3Hash;
 
     #[test]
     fn test_sign_and_verify() {
@@ -361,7 +693,7 @@ mod tests {
         let msg = string.as_bytes();
 
         let ctx = SigCtx::new();
-        let (pk, sk) = ctx.new_keypair();
+        let (pk, sk) = ctx.new_keypair_with_rng(&mut OsRng);
         let signature = ctx.sign(msg, &sk, &pk);
 
         assert!(ctx.verify(msg, &pk, &signature));
@@ -369,10 +701,48 @@ mod tests {
 
   
 
+    #[test]
+    fn test_verify_batch() {
+        let ctx = SigCtx::new();
+        let mut keys = Vec::new();
+        let mut msgs = Vec::new();
+        let mut sigs = Vec::new();
+        for i in 0..40u32 {
+            let (pk, sk) = ctx.new_keypair_with_rng(&mut OsRng);
+            let msg = i.to_be_bytes().to_vec();
+            sigs.push(ctx.sign(&msg, &sk, &pk));
+            keys.push(pk);
+            msgs.push(msg);
+        }
+
+        let items: Vec<(&[u8], &Point, &Signature)> = (0..40)
+            .map(|i| (&msgs[i][..], &keys[i], &sigs[i]))
+            .collect();
+        assert!(ctx.verify_batch(&items).is_ok());
+        assert!(ctx.verify_batch(&[]).is_ok());
+
+        let n = ctx.curve.get_n().clone();
+        let zero = ctx.curve.zero();
+        let high_s = Signature { r: sigs[30].r.clone(), s: n };
+        let mut bad = items.clone();
+        bad[3].0 = &b"forged"[..];
+        bad[17].1 = &keys[18];
+        bad[25].1 = &zero;
+        bad[30].2 = &high_s;
+        bad[39].2 = &sigs[0];
+        assert_eq!(ctx.verify_batch(&bad), Err(vec![3, 17, 25, 30, 39]));
+
+        for (i, (msg, pk, sig)) in bad.iter().enumerate() {
+            if !pk.is_zero() {
+                assert_eq!(ctx.verify(msg, pk, sig), ![3, 17, 25, 30, 39].contains(&i));
+            }
+        }
+    }
+
     #[test]
     fn test_key_serialization() {
         let ctx = SigCtx::new();
-        let (pk, sk) = ctx.new_keypair();
+        let (pk, sk) = ctx.new_keypair_with_rng(&mut OsRng);
 
         let pk_v = ctx.serialize_pubkey(&pk, true);
         let new_pk = ctx.load_pubkey(&pk_v[..]).unwrap();
@@ -381,11 +751,139 @@ mod tests {
         let sk_v = ctx.serialize_seckey(&sk);
         let new_sk = ctx.load_seckey(&sk_v[..]).unwrap();
         assert_eq!(new_sk, sk);
+        assert_eq!(ctx.serialize_seckey_checked(&sk).unwrap(), sk_v);
+    }
+
+    #[test]
+    fn test_checked() {
+        let ctx = SigCtx::new();
+        let (pk, sk) = ctx.new_keypair_with_rng(&mut OsRng);
+        let n = ctx.curve.get_n();
+
+        assert_eq!(ctx.pk_from_sk_checked(n).err(), Some(Sm2Error::InvalidPrivate));
+        // n - 1 has no signature as 1 + sk isn't invertible
+        assert_eq!(
+            ctx.pk_from_sk_checked(&(n - BigUint::one())).err(),
+            Some(Sm2Error::InvalidPrivate)
+        );
+        assert_eq!(ctx.serialize_seckey_checked(n).err(), Some(Sm2Error::InvalidPrivate));
+        assert_eq!(
+            ctx.serialize_seckey_checked(&BigUint::zero()).err(),
+            Some(Sm2Error::InvalidPrivate)
+        );
+
+        let digest = [7u8; 32];
+        let sig = ctx.sign_raw(&digest, &sk);
+        assert_eq!(ctx.sign_raw_checked(&digest, &sk).unwrap().to_bytes(), sig.to_bytes());
+        let (checked, recid) = ctx.sign_recoverable_checked(&digest, &sk).unwrap();
+        assert_eq!((checked.to_bytes(), recid), (sig.to_bytes(), ctx.sign_recoverable(&digest, &sk).1));
+        for bad in [BigUint::zero(), n - BigUint::one(), n.clone()].iter() {
+            assert_eq!(ctx.sign_raw_checked(&digest, bad).err(), Some(Sm2Error::InvalidPrivate));
+            let bytes = FieldElem::from_biguint(bad).to_bytes();
+            assert_eq!(ctx.load_seckey(&bytes).err(), Some(Sm2Error::InvalidPrivate));
+        }
+        assert_eq!(ctx.sign_raw_checked(&digest[1..], &sk).err(), Some(Sm2Error::InvalidDigest));
+        assert_eq!(ctx.verify_raw_checked(&digest, &pk, &sig), Ok(true));
+        assert_eq!(ctx.verify_raw_checked(&digest[1..], &pk, &sig), Err(Sm2Error::InvalidDigest));
+    }
+
+    // k = HMAC_DRBG-SM3(d, e), d and e of the signature example in GB/T 32918.5-2017,
+    // expected values computed with python's hmac and an independent curve implementation
+    #[test]
+    fn test_deterministic_nonce() {
+        let ctx = SigCtx::new();
+        let sk = BigUint::from_str_radix(
+            "3945208F7B2144B13F36E38AC6D39F95889393692860B51A42FB81EF4DF7C5B8",
+            16,
+        )
+        .unwrap();
+        let pk = ctx.pk_from_sk(&sk);
+        let e = hex::decode("F0B43E94BA45ACCAACE692ED534382EB17E6AB5A19CE7B31F4486FDFC0D28640").unwrap();
+
+        let sig = ctx.sign_raw(&e, &sk);
+        assert_eq!(
+            hex::encode(sig.r_bytes()),
+            "24858ee71d63e687feefe41f5af80a59f0791eb1dabc2bbe71daf0e57f06c367"
+        );
+        assert_eq!(
+            hex::encode(sig.s_bytes()),
+            "3d15550de52785a435004c937256ac715c0e04176ac57062c6722fa692f7a491"
+        );
+        assert!(ctx.verify_raw(&e, &pk, &sig));
+
+        // same digest, same signature
+        assert_eq!(ctx.sign_raw(&e, &sk).der_encode(), sig.der_encode());
+        // another digest gets another k
+        let e2 = Sm3Hash::new(b"another").get_hash();
+        assert_ne!(ctx.sign_raw(&e2, &sk).get_r(), sig.get_r());
+    }
+
+    #[test]
+    fn test_sign_with_rng() {
+        let ctx = SigCtx::new();
+        let (pk, sk) = ctx.new_keypair_with_rng(&mut OsRng);
+        let digest = Sm3Hash::new(b"rng").get_hash();
+        let s1 = ctx.sign_raw_with_rng(&digest, &sk, &mut OsRng);
+        let s2 = ctx.sign_raw_with_rng(&digest, &sk, &mut OsRng);
+        assert!(ctx.verify_raw(&digest, &pk, &s1));
+        assert!(ctx.verify_raw(&digest, &pk, &s2));
+        assert_ne!(s1.get_r(), s2.get_r());
     }
 
+    #[test]
+    fn test_recover_public_key() {
+        let ctx = SigCtx::new();
+        let (pk, sk) = ctx.new_keypair_with_rng(&mut OsRng);
+        for i in 0..8u8 {
+            let digest = Sm3Hash::new(&[i]).get_hash();
+            let (sig, recid) = ctx.sign_recoverable(&digest, &sk);
+            assert!(ctx.verify_raw(&digest, &pk, &sig));
+
+            let recovered = ctx.recover_public_key(&digest, &sig, recid).unwrap();
+            assert!(ctx.curve.eq(&recovered, &pk));
+
+            // the other parity gives another key
+            if let Ok(p) = ctx.recover_public_key(&digest, &sig, recid ^ 1) {
+                assert!(!ctx.curve.eq(&p, &pk));
+            }
+        }
+
+        let digest = Sm3Hash::new(b"recover").get_hash();
+        let (sig, _) = ctx.sign_recoverable(&digest, &sk);
+        assert!(ctx.recover_public_key(&digest, &sig, 4).is_err());
+        let zero = Signature::new(&[0], &[1]);
+        assert!(ctx.recover_public_key(&digest, &zero, 0).is_err());
+    }
+
+    // signed by OpenSSL 3 with the key of GB/T 32918.5 and the distinguishing
+    // identifier ALICE123@YAHOO.COM
+    #[test]
+    fn test_sign_with_id() {
+        let ctx = SigCtx::new();
+        let id = "ALICE123@YAHOO.COM";
+        let msg = b"message digest";
+        let pk = ctx.load_pubkey(&hex::decode(OPENSSL_PK).unwrap()).unwrap();
+        let sig = Signature::der_decode(&hex::decode(OPENSSL_SIG_ALICE).unwrap()).unwrap();
+        assert!(ctx.verify_with_id(id, msg, &pk, &sig));
+        assert!(!ctx.verify(msg, &pk, &sig));
+
+        let sk = BigUint::from_str_radix(
+            "3945208F7B2144B13F36E38AC6D39F95889393692860B51A42FB81EF4DF7C5B8",
+            16,
+        )
+        .unwrap();
+        let sig = ctx.sign_with_id(id, msg, &sk, &pk);
+        assert!(ctx.verify_with_id(id, msg, &pk, &sig));
+        assert!(!ctx.verify_with_id("BILL456@YAHOO.COM", msg, &pk, &sig));
+        assert!(!ctx.verify(msg, &pk, &sig));
+        assert!(ctx.verify(msg, &pk, &ctx.sign_with_id(DEFAULT_ID, msg, &sk, &pk)));
+    }
+
+    const OPENSSL_PK: &str = "0409f9df311e5421a150dd7d161e4bc5c672179fad1833fc076bb08ff356f35020ccea490ce26775a52dc6ea718cc1aa600aed05fbf35e084a6632f6072da9ad13";
+    const OPENSSL_SIG_ALICE: &str = "3046022100b0d9d8546e59807373ebcf3876ce53c470d59d160359832a936f213f03d415e1022100bee3b45781b037b0793bea1b51415cdbd73001e1639bea8698501e1de3b46661";
+
     #[test]
     fn test_gmssl() {
-        seed(12415151);
         let msg: &[u8] = &[
             0x66, 0xc7, 0xf0, 0xf4, 0x62, 0xee, 0xed, 0xd9, 0xd1, 0xf2, 0xd4, 0x6b, 0xdc, 0x10,
             0xe4, 0xe2, 0x41, 0x67, 0xc4, 0x87, 0x5c, 0xf2, 0xf7, 0xa2, 0x29, 0x7d, 0xa0, 0x2b,
@@ -409,16 +907,15 @@ mod tests {
         let curve = EccCtx::new();
         let ctx = SigCtx::new();
 
-        let pk = curve.bytes_to_point(&pk).unwrap();
+        let pk = curve.bytes_to_point(pk).unwrap();
 
-        let sig = Signature::der_decode(&sig).unwrap();
+        let sig = Signature::der_decode(sig).unwrap();
 
         assert!(ctx.verify_raw(msg, &pk, &sig));
     }
 
     #[test]
     fn verify_third_test() {
-        seed(32109328023);
         let ctx = SigCtx::new();
         let msg = "jonllen".to_string().into_bytes();
 
@@ -431,4 +928,136 @@ mod tests {
 
         assert!(ctx.verify(&msg, &pk, &sig));
     }
+
+    #[test]
+    fn test_der_strict() {
+        let der = hex::decode(OPENSSL_SIG_ALICE).unwrap();
+        let sig = Signature::der_decode(&der).unwrap();
+        assert_eq!(sig.der_encode(), der);
+        assert_eq!(Signature::der_decode_raw(&der[2..]).unwrap().der_encode(), der);
+        assert_eq!(Signature::from_bytes(&sig.to_bytes()).unwrap().der_encode(), der);
+
+        // r and s both need a zero in front, 33 bytes each
+        let (r, s) = (&der[4..37], &der[39..]);
+        let encode = |r: &[u8], s: &[u8]| -> Vec<u8> {
+            let mut ints = vec![0x02, r.len() as u8];
+            ints.extend_from_slice(r);
+            ints.extend_from_slice(&[0x02, s.len() as u8]);
+            ints.extend_from_slice(s);
+            let mut der = vec![0x30, ints.len() as u8];
+            der.extend_from_slice(&ints);
+            der
+        };
+        assert_eq!(encode(r, s), der);
+
+        // non-canonical encodings of the same signature
+        let cases = [
+            // without the zero keeping r positive
+            encode(&r[1..], s),
+            // a redundant leading zero
+            encode(r, &[&[0u8][..], s].concat()),
+            // the length of the sequence in the long form
+            [&[0x30, 0x81][..], &der[1..]].concat(),
+        ];
+        for case in cases.iter() {
+            assert!(Signature::der_decode(case).is_err());
+            assert_eq!(Signature::der_decode_lax(case).unwrap().der_encode(), der);
+        }
+
+        // rejected in both modes
+        let n = EccCtx::new().get_n().to_bytes_be();
+        let n_minus_one = (EccCtx::new().get_n() - BigUint::one()).to_bytes_be();
+        let mut cases = vec![
+            Vec::new(),
+            vec![0x30],
+            vec![0x30, 0x00],
+            [&der[..], &[0x00][..]].concat(),
+            [&[0x31][..], &der[1..]].concat(),
+            encode(&[0x00], s),
+            encode(r, &[0x00]),
+            encode(&[0x00][..], &n_minus_one),
+            encode(&[&[0u8][..], &n[..]].concat(), s),
+            encode(r, &[0x01; 33]),
+        ];
+        // a sequence longer than its content
+        let mut long = der.clone();
+        long[1] += 1;
+        cases.push(long);
+        for case in cases.iter() {
+            assert!(Signature::der_decode(case).is_err());
+            assert!(Signature::der_decode_lax(case).is_err());
+        }
+        assert!(Signature::der_decode_raw(&der).is_err());
+        assert!(Signature::der_decode_raw(&[0x02]).is_err());
+    }
+
+    #[test]
+    fn test_malformed_signatures() {
+        let der = hex::decode(OPENSSL_SIG_ALICE).unwrap();
+        for i in 0..der.len() {
+            assert!(Signature::der_decode(&der[..i]).is_err());
+            assert!(Signature::der_decode_lax(&der[..i]).is_err());
+            assert!(Signature::der_decode_raw(&der[2..i.max(2)]).is_err());
+            // any single changed byte, none may panic
+            for b in [0x00u8, 0x01, 0x7f, 0x80, 0x81, 0x82, 0xff].iter() {
+                let mut bad = der.clone();
+                bad[i] = *b;
+                let _ = Signature::der_decode(&bad);
+                let _ = Signature::der_decode_lax(&bad);
+                let _ = Signature::der_decode_raw(&bad);
+            }
+        }
+
+        let mut rng = OsRng;
+        let mut buf = [0u8; 80];
+        for _ in 0..10000 {
+            rng.fill_bytes(&mut buf);
+            let len = (buf[79] % 80) as usize;
+            buf[0] = 0x30;
+            let _ = Signature::der_decode(&buf[..len]);
+            let _ = Signature::der_decode_lax(&buf[..len]);
+            let _ = Signature::from_bytes(&buf[..len]);
+        }
+
+        assert!(Signature::from_bytes(&[0u8; 64]).is_err());
+        assert!(Signature::from_bytes(&[0xffu8; 64]).is_err());
+        assert!(Signature::from_bytes(&[1u8; 63]).is_err());
+        assert!(Signature::from_bytes(&[1u8; 64]).is_ok());
+    }
+
+    #[test]
+    fn test_no_s_malleability() {
+        // (r, n - s) is not a signature of the same message, unlike ECDSA there
+        // is no low-S rule to enforce
+        let ctx = SigCtx::new();
+        let (pk, sk) = ctx.new_keypair_with_rng(&mut OsRng);
+        let sig = ctx.sign(b"abc", &sk, &pk);
+        let n = ctx.curve.get_n();
+        let twin = Signature {
+            r: sig.r.clone(),
+            s: n - &sig.s,
+        };
+        assert!(ctx.verify(b"abc", &pk, &sig));
+        assert!(!ctx.verify(b"abc", &pk, &twin));
+    }
+
+    #[test]
+    fn test_verify_der_mode() {
+        let ctx = SigCtx::new();
+        let (pk, sk) = ctx.new_keypair_with_rng(&mut OsRng);
+        let der = ctx.sign(b"abc", &sk, &pk).der_encode();
+        // the same signature with the length of the sequence in the long form
+        let long = [&[0x30, 0x81][..], &der[1..]].concat();
+
+        assert!(ctx.verify_der(b"abc", &pk, &der, DerMode::Strict));
+        assert!(ctx.verify_der(b"abc", &pk, &der, DerMode::Lax));
+        assert!(!ctx.verify_der(b"abc", &pk, &long, DerMode::Strict));
+        assert!(ctx.verify_der(b"abc", &pk, &long, DerMode::Lax));
+        assert!(!ctx.verify_der(b"abd", &pk, &long, DerMode::Lax));
+        assert!(!ctx.verify_der(b"abc", &pk, &der[..der.len() - 1], DerMode::Lax));
+
+        let sig = ctx.sign_with_id("bob", b"abc", &sk, &pk).der_encode();
+        assert!(ctx.verify_der_with_id("bob", b"abc", &pk, &sig, DerMode::Strict));
+        assert!(!ctx.verify_der(b"abc", &pk, &sig, DerMode::Strict));
+    }
 }
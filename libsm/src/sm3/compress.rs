@@ -0,0 +1,324 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The SM3 compression function, and on x86_64 an AVX2 version that
+//! compresses a block of eight independent messages at once.
+
+#[inline(always)]
+fn ff0(x: u32, y: u32, z: u32) -> u32 {
+    x ^ y ^ z
+}
+
+#[inline(always)]
+fn ff1(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) | (x & z) | (y & z)
+}
+
+#[inline(always)]
+fn gg0(x: u32, y: u32, z: u32) -> u32 {
+    x ^ y ^ z
+}
+
+#[inline(always)]
+fn gg1(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) | (!x & z)
+}
+
+#[inline(always)]
+fn p0(x: u32) -> u32 {
+    x ^ x.rotate_left(9) ^ x.rotate_left(17)
+}
+
+#[inline(always)]
+fn p1(x: u32) -> u32 {
+    x ^ x.rotate_left(15) ^ x.rotate_left(23)
+}
+
+pub(crate) const IV: [u32; 8] = [
+    0x7380_166f,
+    0x4914_b2b9,
+    0x1724_42d7,
+    0xda8a_0600,
+    0xa96f_30bc,
+    0x1631_38aa,
+    0xe38d_ee4d,
+    0xb0fb_0e4e,
+];
+
+// T_j <<< (j mod 32)
+const T: [u32; 64] = {
+    let mut t = [0u32; 64];
+    let mut j = 0;
+    while j < 64 {
+        let t_j: u32 = if j < 16 { 0x79cc_4519 } else { 0x7a87_9d8a };
+        t[j] = t_j.rotate_left(j as u32 % 32);
+        j += 1;
+    }
+    t
+};
+
+// W_0 .. W_67, W'_j is computed in the rounds as W_j ^ W_(j + 4)
+#[inline(always)]
+fn expand(block: &[u8; 64]) -> [u32; 68] {
+    let mut w = [0u32; 68];
+    for (w, b) in w.iter_mut().zip(block.chunks_exact(4)) {
+        *w = u32::from_be_bytes([b[0], b[1], b[2], b[3]]);
+    }
+    for j in 16..68 {
+        w[j] = p1(w[j - 16] ^ w[j - 9] ^ w[j - 3].rotate_left(15))
+            ^ w[j - 13].rotate_left(7)
+            ^ w[j - 6];
+    }
+    w
+}
+
+// One round with the registers renamed instead of moved: on return $d holds
+// A', $h holds E', and B, F are rotated in place. Calling it with
+// (d, a, b, c, h, e, f, g) continues with the next round.
+macro_rules! round {
+    ($ff:ident, $gg:ident, $a:ident, $b:ident, $c:ident, $d:ident,
+     $e:ident, $f:ident, $g:ident, $h:ident, $w:ident, $j:expr) => {{
+        let a12 = $a.rotate_left(12);
+        let ss1 = a12.wrapping_add($e).wrapping_add(T[$j]).rotate_left(7);
+        let ss2 = ss1 ^ a12;
+        let tt1 = $ff($a, $b, $c)
+            .wrapping_add($d)
+            .wrapping_add(ss2)
+            .wrapping_add($w[$j] ^ $w[$j + 4]);
+        let tt2 = $gg($e, $f, $g)
+            .wrapping_add($h)
+            .wrapping_add(ss1)
+            .wrapping_add($w[$j]);
+        $b = $b.rotate_left(9);
+        $f = $f.rotate_left(19);
+        $d = tt1;
+        $h = p0(tt2);
+    }};
+}
+
+// four rounds, after which the names are back in place
+macro_rules! rounds4 {
+    ($ff:ident, $gg:ident, $a:ident, $b:ident, $c:ident, $d:ident,
+     $e:ident, $f:ident, $g:ident, $h:ident, $w:ident, $j:expr) => {{
+        round!($ff, $gg, $a, $b, $c, $d, $e, $f, $g, $h, $w, $j);
+        round!($ff, $gg, $d, $a, $b, $c, $h, $e, $f, $g, $w, $j + 1);
+        round!($ff, $gg, $c, $d, $a, $b, $g, $h, $e, $f, $w, $j + 2);
+        round!($ff, $gg, $b, $c, $d, $a, $f, $g, $h, $e, $w, $j + 3);
+    }};
+}
+
+pub(crate) fn compress(state: &mut [u32; 8], block: &[u8; 64]) {
+    let w = expand(block);
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    rounds4!(ff0, gg0, a, b, c, d, e, f, g, h, w, 0);
+    rounds4!(ff0, gg0, a, b, c, d, e, f, g, h, w, 4);
+    rounds4!(ff0, gg0, a, b, c, d, e, f, g, h, w, 8);
+    rounds4!(ff0, gg0, a, b, c, d, e, f, g, h, w, 12);
+    let mut j = 16;
+    while j < 64 {
+        rounds4!(ff1, gg1, a, b, c, d, e, f, g, h, w, j);
+        j += 4;
+    }
+
+    state[0] ^= a;
+    state[1] ^= b;
+    state[2] ^= c;
+    state[3] ^= d;
+    state[4] ^= e;
+    state[5] ^= f;
+    state[6] ^= g;
+    state[7] ^= h;
+}
+
+#[cfg(target_arch = "x86_64")]
+pub(crate) mod avx2 {
+    // libsm is only no_std on wasm32
+    use std::arch::x86_64::*;
+    use std::convert::TryFrom;
+
+    use super::{IV, T};
+
+    pub(crate) const LANES: usize = 8;
+
+    macro_rules! rotl {
+        ($x:expr, $n:literal) => {
+            _mm256_or_si256(_mm256_slli_epi32($x, $n), _mm256_srli_epi32($x, 32 - $n))
+        };
+    }
+
+    #[inline(always)]
+    unsafe fn add(x: __m256i, y: __m256i) -> __m256i {
+        _mm256_add_epi32(x, y)
+    }
+
+    #[inline(always)]
+    unsafe fn xor(x: __m256i, y: __m256i) -> __m256i {
+        _mm256_xor_si256(x, y)
+    }
+
+    #[inline(always)]
+    unsafe fn ff0(x: __m256i, y: __m256i, z: __m256i) -> __m256i {
+        xor(xor(x, y), z)
+    }
+
+    #[inline(always)]
+    unsafe fn ff1(x: __m256i, y: __m256i, z: __m256i) -> __m256i {
+        _mm256_or_si256(
+            _mm256_and_si256(x, y),
+            _mm256_and_si256(z, _mm256_or_si256(x, y)),
+        )
+    }
+
+    #[inline(always)]
+    unsafe fn gg1(x: __m256i, y: __m256i, z: __m256i) -> __m256i {
+        _mm256_or_si256(_mm256_and_si256(x, y), _mm256_andnot_si256(x, z))
+    }
+
+    #[inline(always)]
+    unsafe fn p0(x: __m256i) -> __m256i {
+        xor(xor(x, rotl!(x, 9)), rotl!(x, 17))
+    }
+
+    #[inline(always)]
+    unsafe fn p1(x: __m256i) -> __m256i {
+        xor(xor(x, rotl!(x, 15)), rotl!(x, 23))
+    }
+
+    /// Compresses one block per lane into the transposed state, word i of
+    /// lane l is lane l of `state[i]`. Lanes whose bit is clear in `active`
+    /// keep their state.
+    #[target_feature(enable = "avx2")]
+    pub(crate) unsafe fn compress8(
+        state: &mut [__m256i; 8],
+        blocks: &[&[u8; 64]; LANES],
+        active: __m256i,
+    ) {
+        let mut w = [_mm256_setzero_si256(); 68];
+        for (i, w) in w.iter_mut().take(16).enumerate() {
+            let word = |l: usize| {
+                let b: &[u8; 64] = blocks[l];
+                u32::from_be_bytes([b[4 * i], b[4 * i + 1], b[4 * i + 2], b[4 * i + 3]]) as i32
+            };
+            *w = _mm256_set_epi32(
+                word(7),
+                word(6),
+                word(5),
+                word(4),
+                word(3),
+                word(2),
+                word(1),
+                word(0),
+            );
+        }
+        for j in 16..68 {
+            w[j] = xor(
+                xor(
+                    p1(xor(xor(w[j - 16], w[j - 9]), rotl!(w[j - 3], 15))),
+                    rotl!(w[j - 13], 7),
+                ),
+                w[j - 6],
+            );
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+        for j in 0..64 {
+            let a12 = rotl!(a, 12);
+            let ss1 = add(add(a12, e), _mm256_set1_epi32(T[j] as i32));
+            let ss1 = rotl!(ss1, 7);
+            let ss2 = xor(ss1, a12);
+            let (fa, ge) = if j < 16 {
+                (ff0(a, b, c), ff0(e, f, g))
+            } else {
+                (ff1(a, b, c), gg1(e, f, g))
+            };
+            let tt1 = add(add(add(fa, d), ss2), xor(w[j], w[j + 4]));
+            let tt2 = add(add(add(ge, h), ss1), w[j]);
+            d = c;
+            c = rotl!(b, 9);
+            b = a;
+            a = tt1;
+            h = g;
+            g = rotl!(f, 19);
+            f = e;
+            e = p0(tt2);
+        }
+
+        let new = [a, b, c, d, e, f, g, h];
+        for (s, n) in state.iter_mut().zip(new.iter()) {
+            *s = _mm256_blendv_epi8(*s, xor(*s, *n), active);
+        }
+    }
+
+    fn as_block(s: &[u8]) -> &[u8; 64] {
+        <&[u8; 64]>::try_from(&s[..64]).unwrap()
+    }
+
+    /// SM3 of up to eight messages of any lengths. Every lane is padded on
+    /// its own and sits out the blocks after its last one.
+    #[target_feature(enable = "avx2")]
+    pub(crate) unsafe fn hash8(msgs: &[&[u8]], out: &mut [[u8; 32]]) {
+        debug_assert!(msgs.len() <= LANES && msgs.len() == out.len());
+
+        // the padding and the part of the last block of the message, one or two blocks
+        let mut tails = [[0u8; 128]; LANES];
+        let mut full = [0usize; LANES];
+        let mut total = [0usize; LANES];
+        for (l, msg) in msgs.iter().enumerate() {
+            let rem = msg.len() % 64;
+            let tail_len = if rem < 56 { 64 } else { 128 };
+            let tail = &mut tails[l];
+            tail[..rem].copy_from_slice(&msg[msg.len() - rem..]);
+            tail[rem] = 0x80;
+            let bits = (msg.len() as u64).wrapping_mul(8);
+            tail[tail_len - 8..tail_len].copy_from_slice(&bits.to_be_bytes());
+            full[l] = msg.len() / 64;
+            total[l] = full[l] + tail_len / 64;
+        }
+
+        let mut state = [_mm256_setzero_si256(); 8];
+        for (s, iv) in state.iter_mut().zip(IV.iter()) {
+            *s = _mm256_set1_epi32(*iv as i32);
+        }
+
+        let zero = [0u8; 64];
+        let max = total.iter().take(msgs.len()).max().cloned().unwrap_or(0);
+        for i in 0..max {
+            let mut blocks = [&zero; LANES];
+            let mut mask = [0i32; LANES];
+            for (l, msg) in msgs.iter().enumerate() {
+                if i < full[l] {
+                    blocks[l] = as_block(&msg[i * 64..]);
+                } else if i < total[l] {
+                    blocks[l] = as_block(&tails[l][(i - full[l]) * 64..]);
+                } else {
+                    continue;
+                }
+                mask[l] = -1;
+            }
+            let active = _mm256_loadu_si256(mask.as_ptr() as *const __m256i);
+            compress8(&mut state, &blocks, active);
+        }
+
+        let mut words = [[0u32; LANES]; 8];
+        for (w, s) in words.iter_mut().zip(state.iter()) {
+            _mm256_storeu_si256(w.as_mut_ptr() as *mut __m256i, *s);
+        }
+        for (l, digest) in out.iter_mut().enumerate() {
+            for (d, w) in digest.chunks_exact_mut(4).zip(words.iter()) {
+                d.copy_from_slice(&w[l].to_be_bytes());
+            }
+        }
+    }
+}
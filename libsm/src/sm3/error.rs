@@ -0,0 +1,39 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sm3Error {
+    InvalidOutputLength,
+    InvalidProof,
+    InvalidIterations,
+}
+
+impl From<Sm3Error> for &str {
+    fn from(e: Sm3Error) -> Self {
+        match e {
+            Sm3Error::InvalidOutputLength => "invalid output length",
+            Sm3Error::InvalidProof => "invalid merkle proof",
+            Sm3Error::InvalidIterations => "invalid iteration count",
+        }
+    }
+}
+
+impl core::fmt::Display for Sm3Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str((*self).into())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Sm3Error {}
@@ -22,226 +22,167 @@
 // Outpuf:debe9ff9 2275b8a1 38604889 c18e5a4d 6fdb70e5 387e5765 293dcba3 9c0c5732
 
 use alloc::vec::*;
+use digest::consts::{U32, U64};
+use digest::core_api::BlockSizeUser;
+use digest::{FixedOutput, FixedOutputReset, HashMarker, Output, OutputSizeUser, Reset, Update};
+
+use super::compress::{compress, IV};
+#[cfg(target_arch = "x86_64")]
+use super::compress::avx2;
+
+/// Incremental SM3. The input is compressed as soon as a 64-byte block is
+/// complete, so only a partial block is kept between calls of `update`.
+/// Cloning keeps the midstate, e.g. to hash many messages sharing a prefix.
+#[derive(Clone)]
+pub struct Sm3Hasher {
+    digest: [u32; 8],
+    // bytes hashed so far
+    length: u64,
+    buffer: [u8; 64],
+    buffer_len: usize,
+}
+
+impl Sm3Hasher {
+    pub fn new() -> Sm3Hasher {
+        Sm3Hasher {
+            digest: IV,
+            length: 0,
+            buffer: [0; 64],
+            buffer_len: 0,
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.length = self.length.wrapping_add(data.len() as u64);
+        let mut data = data;
+
+        if self.buffer_len > 0 {
+            let n = core::cmp::min(64 - self.buffer_len, data.len());
+            self.buffer[self.buffer_len..self.buffer_len + n].copy_from_slice(&data[..n]);
+            self.buffer_len += n;
+            data = &data[n..];
+            if self.buffer_len < 64 {
+                return;
+            }
+            let block = self.buffer;
+            compress(&mut self.digest, &block);
+            self.buffer_len = 0;
+        }
+
+        let mut blocks = data.chunks_exact(64);
+        for block in &mut blocks {
+            if let Ok(block) = core::convert::TryFrom::try_from(block) {
+                compress(&mut self.digest, block);
+            }
+        }
+        let rest = blocks.remainder();
+        self.buffer[..rest.len()].copy_from_slice(rest);
+        self.buffer_len = rest.len();
+    }
+
+    pub fn finalize(mut self) -> [u8; 32] {
+        let bits = self.length.wrapping_mul(8);
+        let mut last = [0u8; 64];
+        last[..self.buffer_len].copy_from_slice(&self.buffer[..self.buffer_len]);
+        last[self.buffer_len] = 0x80;
+        if self.buffer_len >= 56 {
+            compress(&mut self.digest, &last);
+            last = [0u8; 64];
+        }
+        last[56..].copy_from_slice(&bits.to_be_bytes());
+        compress(&mut self.digest, &last);
+
+        let mut output = [0u8; 32];
+        for (out, d) in output.chunks_exact_mut(4).zip(self.digest.iter()) {
+            out.copy_from_slice(&d.to_be_bytes());
+        }
+        output
+    }
 
-#[inline(always)]
-fn ff0(x: u32, y: u32, z: u32) -> u32 {
-    x ^ y ^ z
+    /// `finalize` that leaves the hasher reset for the next message.
+    pub fn finalize_reset(&mut self) -> [u8; 32] {
+        core::mem::take(self).finalize()
+    }
 }
 
-#[inline(always)]
-fn ff1(x: u32, y: u32, z: u32) -> u32 {
-    (x & y) | (x & z) | (y & z)
+impl Default for Sm3Hasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The RustCrypto traits, so that `Sm3Hasher` implements `digest::Digest`
+// and works with `hmac::SimpleHmac` and the crates built on them.
+impl HashMarker for Sm3Hasher {}
+
+impl OutputSizeUser for Sm3Hasher {
+    type OutputSize = U32;
 }
 
-#[inline(always)]
-fn gg0(x: u32, y: u32, z: u32) -> u32 {
-    x ^ y ^ z
+impl BlockSizeUser for Sm3Hasher {
+    type BlockSize = U64;
 }
 
-#[inline(always)]
-fn gg1(x: u32, y: u32, z: u32) -> u32 {
-    (x & y) | (!x & z)
+impl Update for Sm3Hasher {
+    fn update(&mut self, data: &[u8]) {
+        Sm3Hasher::update(self, data)
+    }
 }
 
-#[inline(always)]
-fn p0(x: u32) -> u32 {
-    x ^ x.rotate_left(9) ^ x.rotate_left(17)
+impl FixedOutput for Sm3Hasher {
+    fn finalize_into(self, out: &mut Output<Self>) {
+        out.copy_from_slice(&Sm3Hasher::finalize(self))
+    }
 }
 
-#[inline(always)]
-fn p1(x: u32) -> u32 {
-    x ^ x.rotate_left(15) ^ x.rotate_left(23)
+impl Reset for Sm3Hasher {
+    fn reset(&mut self) {
+        *self = Sm3Hasher::new();
+    }
 }
 
-#[inline(always)]
-fn get_u32_be(b: &[u8; 64], i: usize) -> u32 {
-    u32::from(b[i]) << 24
-        | u32::from(b[i + 1]) << 16
-        | u32::from(b[i + 2]) << 8
-        | u32::from(b[i + 3])
+impl FixedOutputReset for Sm3Hasher {
+    fn finalize_into_reset(&mut self, out: &mut Output<Self>) {
+        out.copy_from_slice(&Sm3Hasher::finalize_reset(self))
+    }
 }
 
+/// SM3 of a message at hand, see `Sm3Hasher` for input given in pieces.
 pub struct Sm3Hash {
-    digest: [u32; 8],
-    length: u64,
-    unhandle_msg: Vec<u8>,
+    hasher: Sm3Hasher,
 }
 
 impl Sm3Hash {
     pub fn new(data: &[u8]) -> Sm3Hash {
-        let mut hash = Sm3Hash {
-            digest: [
-                0x7380_166f,
-                0x4914_b2b9,
-                0x1724_42d7,
-                0xda8a_0600,
-                0xa96f_30bc,
-                0x1631_38aa,
-                0xe38d_ee4d,
-                0xb0fb_0e4e,
-            ],
-            length: (data.len() << 3) as u64,
-            unhandle_msg: Vec::new(),
-        };
-        for i in data.iter() {
-            hash.unhandle_msg.push(*i);
-        }
-        hash
+        let mut hasher = Sm3Hasher::new();
+        hasher.update(data);
+        Sm3Hash { hasher }
     }
 
     pub fn get_hash(&mut self) -> [u8; 32] {
-        let mut output: [u8; 32] = [0; 32];
-        self.pad();
-        let len = self.unhandle_msg.len();
-        let mut count: usize = 0;
-        let mut buffer: [u8; 64] = [0; 64];
-
-        while count * 64 != len {
-            for i in (count * 64)..(count * 64 + 64) {
-                buffer[i - count * 64] = self.unhandle_msg[i];
-            }
-            self.update(&buffer);
-            count += 1;
-        }
-        let mut i = 0;
-        while i < 8 {
-            output[i * 4] = (self.digest[i] >> 24) as u8;
-            output[i * 4 + 1] = (self.digest[i] >> 16) as u8;
-            output[i * 4 + 2] = (self.digest[i] >> 8) as u8;
-            output[i * 4 + 3] = self.digest[i] as u8;
-
-            i += 1;
-        }
-        output
+        self.hasher.clone().finalize()
     }
+}
 
-    fn pad(&mut self) {
-        self.unhandle_msg.push(0x80);
-        let blocksize = 64;
-        while self.unhandle_msg.len() % blocksize != 56 {
-            self.unhandle_msg.push(0x00);
-        }
-
-        self.unhandle_msg.push((self.length >> 56 & 0xff) as u8);
-        self.unhandle_msg.push((self.length >> 48 & 0xff) as u8);
-        self.unhandle_msg.push((self.length >> 40 & 0xff) as u8);
-        self.unhandle_msg.push((self.length >> 32 & 0xff) as u8);
-        self.unhandle_msg.push((self.length >> 24 & 0xff) as u8);
-        self.unhandle_msg.push((self.length >> 16 & 0xff) as u8);
-        self.unhandle_msg.push((self.length >> 8 & 0xff) as u8);
-        self.unhandle_msg.push((self.length & 0xff) as u8);
-
-        if self.unhandle_msg.len() % 64 != 0 {
-            panic!("-------SM3 Pad: error msgLen ------");
+/// SM3 of many independent messages. On x86_64 CPUs with AVX2 eight
+/// messages are hashed at once, which pays off for many short inputs such
+/// as the nodes of a Merkle tree.
+pub fn hash_many(msgs: &[&[u8]]) -> Vec<[u8; 32]> {
+    let mut out = vec![[0u8; 32]; msgs.len()];
+    #[cfg(target_arch = "x86_64")]
+    {
+        if msgs.len() > 1 && is_x86_feature_detected!("avx2") {
+            for (msgs, out) in msgs.chunks(avx2::LANES).zip(out.chunks_mut(avx2::LANES)) {
+                // the CPU supports AVX2
+                unsafe { avx2::hash8(msgs, out) };
+            }
+            return out;
         }
     }
-
-    fn update(&mut self, buffer: &[u8; 64]) {
-        //get expend
-        let mut w: [u32; 68] = [0; 68];
-        let mut w1: [u32; 64] = [0; 64];
-
-        let mut i = 0;
-        while i < 16 {
-            w[i] = get_u32_be(&buffer, i * 4);
-
-            i += 1;
-        }
-
-        i = 16;
-        while i < 68 {
-            w[i] = p1(w[i - 16] ^ w[i - 9] ^ w[i - 3].rotate_left(15))
-                ^ w[i - 13].rotate_left(7)
-                ^ w[i - 6];
-
-            i += 1;
-        }
-
-        i = 0;
-        while i < 64 {
-            w1[i] = w[i] ^ w[i + 4];
-
-            i += 1;
-        }
-
-        let mut ra = self.digest[0];
-        let mut rb = self.digest[1];
-        let mut rc = self.digest[2];
-        let mut rd = self.digest[3];
-        let mut re = self.digest[4];
-        let mut rf = self.digest[5];
-        let mut rg = self.digest[6];
-        let mut rh = self.digest[7];
-        let mut ss1: u32;
-        let mut ss2: u32;
-        let mut tt1: u32;
-        let mut tt2: u32;
-
-        i = 0;
-        while i < 16 {
-            ss1 = ra
-                .rotate_left(12)
-                .wrapping_add(re)
-                .wrapping_add(0x79cc_4519u32.rotate_left(i as u32))
-                .rotate_left(7);
-            ss2 = ss1 ^ ra.rotate_left(12);
-            tt1 = ff0(ra, rb, rc)
-                .wrapping_add(rd)
-                .wrapping_add(ss2)
-                .wrapping_add(w1[i]);
-            tt2 = gg0(re, rf, rg)
-                .wrapping_add(rh)
-                .wrapping_add(ss1)
-                .wrapping_add(w[i]);
-            rd = rc;
-            rc = rb.rotate_left(9);
-            rb = ra;
-            ra = tt1;
-            rh = rg;
-            rg = rf.rotate_left(19);
-            rf = re;
-            re = p0(tt2);
-
-            i += 1;
-        }
-
-        i = 16;
-        while i < 64 {
-            ss1 = ra
-                .rotate_left(12)
-                .wrapping_add(re)
-                .wrapping_add(0x7a87_9d8au32.rotate_left(i as u32))
-                .rotate_left(7);
-            ss2 = ss1 ^ ra.rotate_left(12);
-            tt1 = ff1(ra, rb, rc)
-                .wrapping_add(rd)
-                .wrapping_add(ss2)
-                .wrapping_add(w1[i]);
-            tt2 = gg1(re, rf, rg)
-                .wrapping_add(rh)
-                .wrapping_add(ss1)
-                .wrapping_add(w[i]);
-            rd = rc;
-            rc = rb.rotate_left(9);
-            rb = ra;
-            ra = tt1;
-            rh = rg;
-            rg = rf.rotate_left(19);
-            rf = re;
-            re = p0(tt2);
-
-            i += 1;
-        }
-
-        self.digest[0] ^= ra;
-        self.digest[1] ^= rb;
-        self.digest[2] ^= rc;
-        self.digest[3] ^= rd;
-        self.digest[4] ^= re;
-        self.digest[5] ^= rf;
-        self.digest[6] ^= rg;
-        self.digest[7] ^= rh;
+    for (msg, out) in msgs.iter().zip(out.iter_mut()) {
+        *out = Sm3Hash::new(msg).get_hash();
     }
+    out
 }
 
 #[cfg(test)]
@@ -291,4 +232,104 @@ mod tests {
             assert_eq!(standrad_hash[i], hash[i]);
         }
     }
+
+    #[test]
+    fn test_streaming() {
+        let data: Vec<u8> = (0..300u32).map(|i| (i * 7) as u8).collect();
+        let expected = Sm3Hash::new(&data).get_hash();
+
+        // every split point, across and inside block boundaries
+        for i in 0..data.len() {
+            let mut hasher = Sm3Hasher::new();
+            hasher.update(&data[..i]);
+            hasher.update(&data[i..]);
+            assert_eq!(hasher.finalize(), expected);
+        }
+
+        let mut hasher = Sm3Hasher::new();
+        for b in data.iter() {
+            hasher.update(&[*b]);
+        }
+        assert_eq!(hasher.finalize(), expected);
+
+        // 55, 56 and 64 bytes end right around the length field
+        for len in [0, 55, 56, 63, 64, 65].iter() {
+            let mut hasher = Sm3Hasher::new();
+            hasher.update(&data[..*len]);
+            assert_eq!(hasher.finalize(), Sm3Hash::new(&data[..*len]).get_hash());
+        }
+
+        let mut hasher = Sm3Hasher::new();
+        let chunk = [b'a'; 1000];
+        for _ in 0..1000 {
+            hasher.update(&chunk);
+        }
+        assert_eq!(
+            hex::encode(hasher.finalize()),
+            "c8aaf89429554029e231941a2acc0ad61ff2a5acd8fadd25847a3a732b3b02c3"
+        );
+    }
+
+    #[test]
+    fn test_midstate() {
+        let mut prefix = Sm3Hasher::new();
+        prefix.update(b"abcdabcdabcdabcdabcdabcdabcdabcd");
+        let mut a = prefix.clone();
+        a.update(b"abcdabcdabcdabcdabcdabcdabcdabcd");
+        let mut b = prefix;
+        b.update(b"abc");
+        assert_eq!(
+            hex::encode(a.finalize_reset()),
+            "debe9ff92275b8a138604889c18e5a4d6fdb70e5387e5765293dcba39c0c5732"
+        );
+        assert_eq!(
+            b.finalize(),
+            Sm3Hash::new(b"abcdabcdabcdabcdabcdabcdabcdabcdabc").get_hash()
+        );
+
+        a.update(b"abc");
+        assert_eq!(
+            hex::encode(a.finalize()),
+            "66c7f0f462eeedd9d1f2d46bdc10e4e24167c4875cf2f7a2297da02b8f4ba8e0"
+        );
+    }
+
+    #[test]
+    fn test_digest_traits() {
+        use digest::Digest;
+
+        let hash = <Sm3Hasher as Digest>::digest(b"abc");
+        assert_eq!(
+            hex::encode(hash),
+            "66c7f0f462eeedd9d1f2d46bdc10e4e24167c4875cf2f7a2297da02b8f4ba8e0"
+        );
+
+        // openssl dgst -sm3 -hmac Jefe
+        type HmacSm3 = hmac::SimpleHmac<Sm3Hasher>;
+        let mut mac = <HmacSm3 as hmac::Mac>::new_from_slice(b"Jefe").unwrap();
+        hmac::Mac::update(&mut mac, b"what do ya want for nothing?");
+        assert_eq!(
+            hex::encode(hmac::Mac::finalize(mac).into_bytes()),
+            "2e87f1d16862e6d964b50a5200bf2b10b764faa9680a296a2405f24bec39f882"
+        );
+    }
+
+    #[test]
+    fn test_hash_many() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 13 + 5) as u8).collect();
+        // lengths around the padding boundaries, different in every lane
+        let lens = [0, 1, 55, 56, 63, 64, 65, 119, 120, 128, 200, 1000, 3, 64, 64, 64, 64, 17];
+        let msgs: Vec<&[u8]> = lens.iter().map(|&n| &data[..n]).collect();
+        for n in 0..=msgs.len() {
+            let hashes = hash_many(&msgs[..n]);
+            assert_eq!(hashes.len(), n);
+            for (msg, hash) in msgs.iter().zip(hashes.iter()) {
+                assert_eq!(*hash, Sm3Hash::new(msg).get_hash());
+            }
+        }
+        assert_eq!(
+            hex::encode(hash_many(&[b"abc", b"abc"])[1]),
+            "66c7f0f462eeedd9d1f2d46bdc10e4e24167c4875cf2f7a2297da02b8f4ba8e0"
+        );
+    }
 }
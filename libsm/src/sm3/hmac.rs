@@ -0,0 +1,131 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! HMAC of RFC 2104 with SM3 as the hash function, as in GB/T 15852.2.
+
+use subtle::ConstantTimeEq;
+
+use super::hash::{Sm3Hash, Sm3Hasher};
+
+const BLOCK_SIZE: usize = 64;
+
+/// Incremental HMAC-SM3. Cloning keeps the keyed state, so one key can
+/// authenticate many messages without hashing the key again.
+#[derive(Clone)]
+pub struct HmacSm3 {
+    inner: Sm3Hasher,
+    outer: Sm3Hasher,
+}
+
+impl HmacSm3 {
+    /// Keys longer than the 64-byte block are hashed first.
+    pub fn new(key: &[u8]) -> HmacSm3 {
+        let mut k = [0u8; BLOCK_SIZE];
+        if key.len() > BLOCK_SIZE {
+            k[..32].copy_from_slice(&Sm3Hash::new(key).get_hash());
+        } else {
+            k[..key.len()].copy_from_slice(key);
+        }
+
+        let mut inner = Sm3Hasher::new();
+        let mut outer = Sm3Hasher::new();
+        let mut pad = [0u8; BLOCK_SIZE];
+        for (p, k) in pad.iter_mut().zip(k.iter()) {
+            *p = k ^ 0x36;
+        }
+        inner.update(&pad);
+        for (p, k) in pad.iter_mut().zip(k.iter()) {
+            *p = k ^ 0x5c;
+        }
+        outer.update(&pad);
+        HmacSm3 { inner, outer }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    pub fn finalize(self) -> [u8; 32] {
+        let mut outer = self.outer;
+        outer.update(&self.inner.finalize());
+        outer.finalize()
+    }
+
+    /// Compares the MAC with `tag` in constant time.
+    pub fn verify(self, tag: &[u8]) -> bool {
+        bool::from(self.finalize().ct_eq(tag))
+    }
+}
+
+pub fn hmac_sm3(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut mac = HmacSm3::new(key);
+    mac.update(data);
+    mac.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // keys and data of RFC 4231, expected values from `openssl mac -digest SM3 HMAC`
+    #[test]
+    fn test_hmac_sm3() {
+        let mac = hmac_sm3(&[0x0b; 20], b"Hi There");
+        assert_eq!(
+            hex::encode(mac),
+            "51b00d1fb49832bfb01c3ce27848e59f871d9ba938dc563b338ca964755cce70"
+        );
+
+        let mut mac = HmacSm3::new(b"Jefe");
+        mac.update(b"what do ya want ");
+        mac.update(b"for nothing?");
+        assert_eq!(
+            hex::encode(mac.finalize()),
+            "2e87f1d16862e6d964b50a5200bf2b10b764faa9680a296a2405f24bec39f882"
+        );
+
+        let mac = hmac_sm3(&[0xaa; 20], &[0xdd; 50]);
+        assert_eq!(
+            hex::encode(mac),
+            "dd9421e1c725bdf52ec1aa34edadb3c97f5951a83a2fa93f73a7902bc1dcc777"
+        );
+
+        // the key is longer than a block
+        let mac = hmac_sm3(
+            &[0xaa; 131],
+            b"Test Using Larger Than Block-Size Key - Hash Key First",
+        );
+        assert_eq!(
+            hex::encode(mac),
+            "b4fd844e13342002f0b2e0690ea7741f1497d993a70494cea601e657bedf67a0"
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let tag = hmac_sm3(b"key", b"message");
+        let mac = HmacSm3::new(b"key");
+        let mut a = mac.clone();
+        a.update(b"message");
+        assert!(a.verify(&tag));
+
+        let mut b = mac.clone();
+        b.update(b"massage");
+        assert!(!b.verify(&tag));
+
+        let mut c = mac;
+        c.update(b"message");
+        assert!(!c.verify(&tag[..31]));
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Key derivation over SM3: the KDF of GB/T 32918, HKDF of RFC 5869 and
+//! PBKDF2 of RFC 8018.
+
+use alloc::vec::*;
+
+use super::error::Sm3Error;
+use super::hash::Sm3Hasher;
+use super::hmac::HmacSm3;
+
+/// Key derivation function of GB/T 32918.4, K = H(Z || 1) || H(Z || 2) || ... truncated to klen bytes.
+/// It is the KDF of SM2 encryption and key exchange.
+pub fn kdf(z: &[u8], klen: usize) -> Vec<u8> {
+    let mut k: Vec<u8> = Vec::with_capacity(klen + 32);
+    let mut ct: u32 = 1;
+    while k.len() < klen {
+        let mut hasher = Sm3Hasher::new();
+        hasher.update(z);
+        hasher.update(&ct.to_be_bytes());
+        k.extend_from_slice(&hasher.finalize());
+        ct += 1;
+    }
+    k.truncate(klen);
+    k
+}
+
+/// PRK = HMAC-SM3(salt, IKM), an empty salt stands for 32 zero bytes.
+pub fn hkdf_extract(salt: &[u8], ikm: &[u8]) -> [u8; 32] {
+    let mut mac = HmacSm3::new(salt);
+    mac.update(ikm);
+    mac.finalize()
+}
+
+/// Fills `okm` with T(1) || T(2) || ..., T(i) = HMAC-SM3(PRK, T(i - 1) || info || i).
+/// At most 255 * 32 bytes can be derived.
+pub fn hkdf_expand(prk: &[u8], info: &[u8], okm: &mut [u8]) -> Result<(), Sm3Error> {
+    if okm.len() > 255 * 32 {
+        return Err(Sm3Error::InvalidOutputLength);
+    }
+    let key = HmacSm3::new(prk);
+    let mut t: [u8; 32] = [0; 32];
+    for (i, chunk) in okm.chunks_mut(32).enumerate() {
+        let mut mac = key.clone();
+        if i > 0 {
+            mac.update(&t);
+        }
+        mac.update(info);
+        mac.update(&[i as u8 + 1]);
+        t = mac.finalize();
+        chunk.copy_from_slice(&t[..chunk.len()]);
+    }
+    Ok(())
+}
+
+/// `hkdf_extract` followed by `hkdf_expand`.
+pub fn hkdf(salt: &[u8], ikm: &[u8], info: &[u8], okm: &mut [u8]) -> Result<(), Sm3Error> {
+    hkdf_expand(&hkdf_extract(salt, ikm), info, okm)
+}
+
+/// Upper bound on the iteration count of `pbkdf2_hmac_sm3_checked`, so an
+/// iteration count from a file or a caller can't stall the process.
+pub const PBKDF2_MAX_ITERATIONS: u32 = 10_000_000;
+
+/// PBKDF2 with HMAC-SM3 as the PRF, fills `out` with the derived key.
+/// An iteration count of 0 is treated as 1.
+pub fn pbkdf2_hmac_sm3(password: &[u8], salt: &[u8], iterations: u32, out: &mut [u8]) {
+    let key = HmacSm3::new(password);
+    for (i, chunk) in out.chunks_mut(32).enumerate() {
+        let mut mac = key.clone();
+        mac.update(salt);
+        mac.update(&(i as u32 + 1).to_be_bytes());
+        let mut u = mac.finalize();
+        let mut t = u;
+        for _ in 1..iterations {
+            let mut mac = key.clone();
+            mac.update(&u);
+            u = mac.finalize();
+            for (t, u) in t.iter_mut().zip(u.iter()) {
+                *t ^= u;
+            }
+        }
+        chunk.copy_from_slice(&t[..chunk.len()]);
+    }
+}
+
+/// Like `pbkdf2_hmac_sm3`, but an iteration count of 0 or above
+/// `PBKDF2_MAX_ITERATIONS` is an error.
+pub fn pbkdf2_hmac_sm3_checked(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    out: &mut [u8],
+) -> Result<(), Sm3Error> {
+    if iterations == 0 || iterations > PBKDF2_MAX_ITERATIONS {
+        return Err(Sm3Error::InvalidIterations);
+    }
+    pbkdf2_hmac_sm3(password, salt, iterations, out);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sm3::hash::Sm3Hash;
+
+    #[test]
+    fn test_kdf() {
+        // t = H(z || 1) || H(z || 2)
+        let t = kdf(b"abc", 40);
+        assert_eq!(t.len(), 40);
+        assert_eq!(
+            &t[..32],
+            &Sm3Hash::new(b"abc\x00\x00\x00\x01").get_hash()[..]
+        );
+        assert_eq!(
+            &t[32..],
+            &Sm3Hash::new(b"abc\x00\x00\x00\x02").get_hash()[..8]
+        );
+
+        // x_2 || y_2 and t of the encryption example of GB/T 32918.4-2016 A.2
+        let z = hex::decode(
+            "64d20d27d0632957f8028c1e024f6b02edf23102a566c932ae8bd613a8e865fe\
+             58d225eca784ae300a81a2d48281a828e1cedf11c4219099840265375077bf78",
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(kdf(&z, 19)),
+            "006e30dae231b071dfad8aa379e90264491603"
+        );
+    }
+
+    // inputs of RFC 5869 test cases 1 and 3, expected values from
+    // `openssl kdf -kdfopt digest:SM3 HKDF`
+    #[test]
+    fn test_hkdf() {
+        let ikm = [0x0b; 22];
+        let salt = hex::decode("000102030405060708090a0b0c").unwrap();
+        let info = hex::decode("f0f1f2f3f4f5f6f7f8f9").unwrap();
+        let prk = hkdf_extract(&salt, &ikm);
+        assert_eq!(
+            hex::encode(prk),
+            "e0d6f7b0bd056327b7659f1f39ad850561fbcf4fb10fb58e88eafa55cf7cd01e"
+        );
+        let mut okm = [0u8; 42];
+        hkdf_expand(&prk, &info, &mut okm).unwrap();
+        assert_eq!(
+            hex::encode(&okm[..]),
+            "c69fe91b7aaee2dd5718d72dcaee0cce93f1b8e41f792da51261b6a517e68b36ed2c595572b01dfa359b"
+        );
+
+        let mut okm = [0u8; 42];
+        hkdf(&[], &ikm, &[], &mut okm).unwrap();
+        assert_eq!(
+            hex::encode(&okm[..]),
+            "c8c91a38ae2fb3b023a7c38ce9f0748f28230d59b6b950ba3ba949bf0d713a5774815778801741cb2034"
+        );
+        assert_eq!(
+            hex::encode(hkdf_extract(&[], &ikm)),
+            "004fc37143377d072d74e82ff480e8d7937ec607411bc1ec65dd34401871ff9c"
+        );
+
+        let mut okm = vec![0u8; 255 * 32 + 1];
+        assert_eq!(
+            hkdf(&[], &ikm, &[], &mut okm),
+            Err(Sm3Error::InvalidOutputLength)
+        );
+        assert!(hkdf(&[], &ikm, &[], &mut okm[1..]).is_ok());
+    }
+
+    // inputs of RFC 6070, expected values from `openssl kdf -kdfopt digest:SM3 PBKDF2`
+    #[test]
+    fn test_pbkdf2() {
+        let cases: [(&[u8], &[u8], u32, &str); 4] = [
+            (
+                b"password",
+                b"salt",
+                1,
+                "4612f922a1fdcefaf4312fc6f8f3322b489cbf24f2ea361b44c2bd8fa2c6dcb0",
+            ),
+            (
+                b"password",
+                b"salt",
+                2,
+                "fee723a2bc966e11dffb66133f4e8df577383c78ade30e3298edbd3e54ed85b7",
+            ),
+            (
+                b"password",
+                b"salt",
+                4096,
+                "b6e8f2074c87432b78f62e5ced980fdff89e86af2f693dab1638e2b3683045dd",
+            ),
+            (
+                b"passwordPASSWORDpassword",
+                b"saltSALTsaltSALTsaltSALTsaltSALTsalt",
+                4096,
+                "3b6282ac8519f059e465abff0ea37b0dbfe6c672a76e6b805312d53900db630732ccc1a88fa5512a",
+            ),
+        ];
+        for &(password, salt, iterations, expected) in cases.iter() {
+            let mut key = vec![0u8; expected.len() / 2];
+            pbkdf2_hmac_sm3(password, salt, iterations, &mut key);
+            assert_eq!(hex::encode(&key), expected);
+            pbkdf2_hmac_sm3_checked(password, salt, iterations, &mut key).unwrap();
+            assert_eq!(hex::encode(key), expected);
+        }
+
+        let mut key = [0u8; 32];
+        for &iterations in [0, PBKDF2_MAX_ITERATIONS + 1].iter() {
+            assert_eq!(
+                pbkdf2_hmac_sm3_checked(b"password", b"salt", iterations, &mut key),
+                Err(Sm3Error::InvalidIterations)
+            );
+        }
+    }
+}
@@ -0,0 +1,613 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Binary Merkle trees over SM3 with the shape and domain separation of
+//! RFC 6962: a leaf is SM3(0x00 || data), a node is SM3(0x01 || left ||
+//! right), and a level with an odd number of nodes moves its last node up
+//! unchanged. Leaves can't be passed off as nodes and a tree can't be
+//! extended by duplicating its last leaf.
+//!
+//! A root doesn't fix the number of leaves: the root of [a, b, c] is also
+//! the root of the two leaf hashes [SM3(0x01 || H(a) || H(b)), H(c)]. The
+//! verifier has to know the size of the tree along with its root, e.g. both
+//! stored by a contract or signed in a tree head, and passes it to
+//! `verify`. The leaf count of a proof is only what the prover claims, a
+//! proof made for another size is rejected. With the size fixed, a proof
+//! also binds the index of its leaf.
+//!
+//! Verification only needs `alloc`, so proofs can be checked by contracts.
+
+use alloc::vec::*;
+
+use super::error::Sm3Error;
+use super::hash::{hash_many, Sm3Hash, Sm3Hasher};
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+pub fn hash_leaf(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Sm3Hasher::new();
+    hasher.update(&[LEAF_PREFIX]);
+    hasher.update(data);
+    hasher.finalize()
+}
+
+pub fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sm3Hasher::new();
+    hasher.update(&[NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize()
+}
+
+/// Root of an empty tree, the hash of the empty string.
+pub fn empty_root() -> [u8; 32] {
+    Sm3Hash::new(&[]).get_hash()
+}
+
+fn parent_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    let pairs: Vec<[u8; 65]> = level
+        .chunks_exact(2)
+        .map(|pair| {
+            let mut msg = [0u8; 65];
+            msg[0] = NODE_PREFIX;
+            msg[1..33].copy_from_slice(&pair[0]);
+            msg[33..].copy_from_slice(&pair[1]);
+            msg
+        })
+        .collect();
+    let msgs: Vec<&[u8]> = pairs.iter().map(|m| &m[..]).collect();
+    let mut parents = hash_many(&msgs);
+    if level.len() % 2 == 1 {
+        parents.push(level[level.len() - 1]);
+    }
+    parents
+}
+
+/// A tree with every level kept, to answer proofs.
+#[derive(Clone)]
+pub struct MerkleTree {
+    // levels[0] holds the leaf hashes, the last level the root
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    pub fn new<T: AsRef<[u8]>>(leaves: &[T]) -> MerkleTree {
+        let msgs: Vec<Vec<u8>> = leaves
+            .iter()
+            .map(|leaf| {
+                let leaf = leaf.as_ref();
+                let mut msg = Vec::with_capacity(1 + leaf.len());
+                msg.push(LEAF_PREFIX);
+                msg.extend_from_slice(leaf);
+                msg
+            })
+            .collect();
+        let msgs: Vec<&[u8]> = msgs.iter().map(|m| &m[..]).collect();
+        MerkleTree::from_leaf_hashes(hash_many(&msgs))
+    }
+
+    /// A tree over leaves already hashed with `hash_leaf`.
+    pub fn from_leaf_hashes(leaf_hashes: Vec<[u8; 32]>) -> MerkleTree {
+        let mut levels = vec![leaf_hashes];
+        while levels[levels.len() - 1].len() > 1 {
+            let parents = parent_level(&levels[levels.len() - 1]);
+            levels.push(parents);
+        }
+        MerkleTree { levels }
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        match self.levels[self.levels.len() - 1].first() {
+            Some(root) => *root,
+            None => empty_root(),
+        }
+    }
+
+    pub fn leaf_hash(&self, index: usize) -> Option<[u8; 32]> {
+        self.levels[0].get(index).cloned()
+    }
+
+    /// Inclusion proof of the leaf at `index`, None if it is out of range.
+    pub fn proof(&self, index: usize) -> Option<Proof> {
+        if index >= self.len() {
+            return None;
+        }
+        let mut siblings = Vec::new();
+        let mut i = index;
+        for level in self.levels.iter().take(self.levels.len() - 1) {
+            let sibling = i ^ 1;
+            if sibling < level.len() {
+                siblings.push(level[sibling]);
+            }
+            i /= 2;
+        }
+        Some(Proof {
+            index,
+            leaf_count: self.len(),
+            siblings,
+        })
+    }
+
+    /// One proof for the leaves at `indices`, sharing the nodes their paths
+    /// have in common. None if an index is out of range or `indices` is
+    /// empty.
+    pub fn multiproof(&self, indices: &[usize]) -> Option<MultiProof> {
+        let mut known: Vec<usize> = indices.to_vec();
+        known.sort_unstable();
+        known.dedup();
+        if known.is_empty() || known[known.len() - 1] >= self.len() {
+            return None;
+        }
+        let leaves = known.clone();
+
+        let mut hashes = Vec::new();
+        for level in self.levels.iter().take(self.levels.len() - 1) {
+            let mut next = Vec::with_capacity(known.len());
+            let mut k = 0;
+            while k < known.len() {
+                let i = known[k];
+                if i.is_multiple_of(2) && k + 1 < known.len() && known[k + 1] == i + 1 {
+                    // both children are known
+                    k += 1;
+                } else if (i ^ 1) < level.len() {
+                    hashes.push(level[i ^ 1]);
+                }
+                next.push(i / 2);
+                k += 1;
+            }
+            known = next;
+        }
+        Some(MultiProof {
+            indices: leaves,
+            leaf_count: self.len(),
+            hashes,
+        })
+    }
+}
+
+/// Inclusion proof of one leaf: the siblings on the path to the root,
+/// bottom up. Levels where the node is moved up unchanged have no sibling.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proof {
+    index: usize,
+    leaf_count: usize,
+    siblings: Vec<[u8; 32]>,
+}
+
+impl Proof {
+    pub fn new(index: usize, leaf_count: usize, siblings: Vec<[u8; 32]>) -> Proof {
+        Proof {
+            index,
+            leaf_count,
+            siblings,
+        }
+    }
+
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    #[inline]
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_count
+    }
+
+    #[inline]
+    pub fn siblings(&self) -> &[[u8; 32]] {
+        &self.siblings
+    }
+
+    /// Root of the tree of `leaf_count` leaves the leaf hash belongs to,
+    /// None if the proof was made for another size or doesn't fit the index.
+    pub fn compute_root(&self, leaf_count: usize, leaf_hash: &[u8; 32]) -> Option<[u8; 32]> {
+        if self.leaf_count != leaf_count || self.index >= self.leaf_count {
+            return None;
+        }
+        let mut siblings = self.siblings.iter();
+        let mut hash = *leaf_hash;
+        let mut i = self.index;
+        let mut n = self.leaf_count;
+        while n > 1 {
+            if i % 2 == 1 {
+                hash = hash_node(siblings.next()?, &hash);
+            } else if i + 1 < n {
+                hash = hash_node(&hash, siblings.next()?);
+            }
+            i /= 2;
+            n = n.div_ceil(2);
+        }
+        if siblings.next().is_some() {
+            return None;
+        }
+        Some(hash)
+    }
+
+    /// Whether `leaf` is in the tree with `root` and `leaf_count` leaves,
+    /// both known to the verifier rather than taken from the proof.
+    pub fn verify(&self, root: &[u8; 32], leaf_count: usize, leaf: &[u8]) -> bool {
+        self.verify_hash(root, leaf_count, &hash_leaf(leaf))
+    }
+
+    pub fn verify_hash(&self, root: &[u8; 32], leaf_count: usize, leaf_hash: &[u8; 32]) -> bool {
+        self.compute_root(leaf_count, leaf_hash).as_ref() == Some(root)
+    }
+
+    /// index (4 bytes) || leaf count (4 bytes) || siblings, big-endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(8 + 32 * self.siblings.len());
+        buf.extend_from_slice(&(self.index as u32).to_be_bytes());
+        buf.extend_from_slice(&(self.leaf_count as u32).to_be_bytes());
+        for s in self.siblings.iter() {
+            buf.extend_from_slice(s);
+        }
+        buf
+    }
+
+    pub fn from_bytes(buf: &[u8]) -> Result<Proof, Sm3Error> {
+        if buf.len() < 8 || !(buf.len() - 8).is_multiple_of(32) {
+            return Err(Sm3Error::InvalidProof);
+        }
+        let index = read_u32(&buf[0..4]);
+        let leaf_count = read_u32(&buf[4..8]);
+        if index >= leaf_count {
+            return Err(Sm3Error::InvalidProof);
+        }
+        Ok(Proof {
+            index,
+            leaf_count,
+            siblings: read_hashes(&buf[8..]),
+        })
+    }
+}
+
+/// Inclusion proof of several leaves. The hashes are the nodes the
+/// verifier can't compute, level by level from the leaves, left to right.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiProof {
+    indices: Vec<usize>,
+    leaf_count: usize,
+    hashes: Vec<[u8; 32]>,
+}
+
+impl MultiProof {
+    /// `indices` must be sorted without duplicates.
+    pub fn new(indices: Vec<usize>, leaf_count: usize, hashes: Vec<[u8; 32]>) -> MultiProof {
+        MultiProof {
+            indices,
+            leaf_count,
+            hashes,
+        }
+    }
+
+    #[inline]
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    #[inline]
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_count
+    }
+
+    #[inline]
+    pub fn hashes(&self) -> &[[u8; 32]] {
+        &self.hashes
+    }
+
+    /// Root of the tree of `leaf_count` leaves the leaf hashes belong to, in
+    /// the order of `indices`. None if the proof was made for another size,
+    /// or the proof or the number of hashes doesn't fit.
+    pub fn compute_root(&self, leaf_count: usize, leaf_hashes: &[[u8; 32]]) -> Option<[u8; 32]> {
+        if self.leaf_count != leaf_count {
+            return None;
+        }
+        if self.indices.is_empty() || self.indices.len() != leaf_hashes.len() {
+            return None;
+        }
+        let sorted = self.indices.windows(2).all(|w| w[0] < w[1]);
+        if !sorted || self.indices[self.indices.len() - 1] >= self.leaf_count {
+            return None;
+        }
+
+        let mut known: Vec<(usize, [u8; 32])> = self
+            .indices
+            .iter()
+            .cloned()
+            .zip(leaf_hashes.iter().cloned())
+            .collect();
+        let mut proof = self.hashes.iter();
+        let mut n = self.leaf_count;
+        while n > 1 {
+            let mut next = Vec::with_capacity(known.len());
+            let mut k = 0;
+            while k < known.len() {
+                let (i, hash) = known[k];
+                let parent =
+                    if i.is_multiple_of(2) && k + 1 < known.len() && known[k + 1].0 == i + 1 {
+                        k += 1;
+                        hash_node(&hash, &known[k].1)
+                    } else if i % 2 == 1 {
+                        hash_node(proof.next()?, &hash)
+                    } else if i + 1 < n {
+                        hash_node(&hash, proof.next()?)
+                    } else {
+                        hash
+                    };
+                next.push((i / 2, parent));
+                k += 1;
+            }
+            known = next;
+            n = n.div_ceil(2);
+        }
+        if proof.next().is_some() {
+            return None;
+        }
+        Some(known[0].1)
+    }
+
+    /// Whether `leaves` are in the tree with `root` and `leaf_count` leaves,
+    /// both known to the verifier rather than taken from the proof.
+    pub fn verify<T: AsRef<[u8]>>(&self, root: &[u8; 32], leaf_count: usize, leaves: &[T]) -> bool {
+        let hashes: Vec<[u8; 32]> = leaves.iter().map(|l| hash_leaf(l.as_ref())).collect();
+        self.verify_hashes(root, leaf_count, &hashes)
+    }
+
+    pub fn verify_hashes(
+        &self,
+        root: &[u8; 32],
+        leaf_count: usize,
+        leaf_hashes: &[[u8; 32]],
+    ) -> bool {
+        self.compute_root(leaf_count, leaf_hashes).as_ref() == Some(root)
+    }
+
+    /// leaf count (4 bytes) || number of indices (4 bytes) || indices (4
+    /// bytes each) || hashes, big-endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(8 + 4 * self.indices.len() + 32 * self.hashes.len());
+        buf.extend_from_slice(&(self.leaf_count as u32).to_be_bytes());
+        buf.extend_from_slice(&(self.indices.len() as u32).to_be_bytes());
+        for i in self.indices.iter() {
+            buf.extend_from_slice(&(*i as u32).to_be_bytes());
+        }
+        for h in self.hashes.iter() {
+            buf.extend_from_slice(h);
+        }
+        buf
+    }
+
+    pub fn from_bytes(buf: &[u8]) -> Result<MultiProof, Sm3Error> {
+        if buf.len() < 8 {
+            return Err(Sm3Error::InvalidProof);
+        }
+        let leaf_count = read_u32(&buf[0..4]);
+        let count = read_u32(&buf[4..8]);
+        let rest = &buf[8..];
+        if count == 0 || rest.len() / 4 < count {
+            return Err(Sm3Error::InvalidProof);
+        }
+        let (indices, hashes) = rest.split_at(4 * count);
+        if !hashes.len().is_multiple_of(32) {
+            return Err(Sm3Error::InvalidProof);
+        }
+        let indices: Vec<usize> = indices.chunks_exact(4).map(read_u32).collect();
+        let sorted = indices.windows(2).all(|w| w[0] < w[1]);
+        if !sorted || indices[count - 1] >= leaf_count {
+            return Err(Sm3Error::InvalidProof);
+        }
+        Ok(MultiProof {
+            indices,
+            leaf_count,
+            hashes: read_hashes(hashes),
+        })
+    }
+}
+
+fn read_u32(b: &[u8]) -> usize {
+    u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize
+}
+
+fn read_hashes(buf: &[u8]) -> Vec<[u8; 32]> {
+    buf.chunks_exact(32)
+        .map(|c| {
+            let mut h = [0u8; 32];
+            h.copy_from_slice(c);
+            h
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(n: usize) -> Vec<Vec<u8>> {
+        (0..n).map(|i| format!("leaf {}", i).into_bytes()).collect()
+    }
+
+    // MTH of RFC 6962 section 2.1, split at the largest power of two below n
+    fn reference_root(leaves: &[Vec<u8>]) -> [u8; 32] {
+        match leaves.len() {
+            0 => empty_root(),
+            1 => hash_leaf(&leaves[0]),
+            n => {
+                let mut k = 1;
+                while k * 2 < n {
+                    k *= 2;
+                }
+                hash_node(&reference_root(&leaves[..k]), &reference_root(&leaves[k..]))
+            }
+        }
+    }
+
+    #[test]
+    fn test_root() {
+        for n in 0..40 {
+            let data = leaves(n);
+            assert_eq!(
+                MerkleTree::new(&data).root(),
+                reference_root(&data),
+                "n = {}",
+                n
+            );
+        }
+
+        let tree = MerkleTree::new(&[b"a", b"b", b"c"]);
+        let ab = hash_node(&hash_leaf(b"a"), &hash_leaf(b"b"));
+        assert_eq!(tree.root(), hash_node(&ab, &hash_leaf(b"c")));
+        assert_eq!(
+            hex::encode(MerkleTree::new::<&[u8]>(&[]).root()),
+            "1ab21d8355cfa17f8e61194831e81a8f22bec8c728fefb747ed035eb5082aa2b"
+        );
+
+        // a duplicated last leaf gives another root
+        let tree4 = MerkleTree::new(&[b"a", b"b", b"c", b"c"]);
+        assert_ne!(tree.root(), tree4.root());
+        // and a node can't be passed off as a leaf
+        let forged = MerkleTree::from_leaf_hashes(vec![ab, hash_leaf(b"c")]);
+        assert_eq!(forged.root(), tree.root());
+        assert_ne!(MerkleTree::new(&[&ab[..], b"c"]).root(), tree.root());
+    }
+
+    #[test]
+    fn test_proof() {
+        for n in 1..34 {
+            let data = leaves(n);
+            let tree = MerkleTree::new(&data);
+            let root = tree.root();
+            for (i, leaf) in data.iter().enumerate() {
+                let proof = tree.proof(i).unwrap();
+                assert!(proof.verify(&root, n, leaf));
+                assert!(!proof.verify(&root, n, b"other"));
+                assert!(!proof.verify(&root, n + 1, leaf));
+
+                let decoded = Proof::from_bytes(&proof.to_bytes()).unwrap();
+                assert_eq!(decoded, proof);
+
+                // another position, or another size
+                let moved = Proof::new((i + 1) % n, n, proof.siblings().to_vec());
+                assert!(n == 1 || !moved.verify(&root, n, leaf));
+                let resized = Proof::new(i, n + 1, proof.siblings().to_vec());
+                assert!(!resized.verify(&root, n, leaf));
+            }
+            assert!(tree.proof(n).is_none());
+        }
+
+        let tree = MerkleTree::new(&leaves(5));
+        let proof = tree.proof(4).unwrap();
+        // the last leaf of five is moved up twice
+        assert_eq!(proof.siblings().len(), 1);
+        let mut extra = proof.siblings().to_vec();
+        extra.push([0; 32]);
+        assert!(!Proof::new(4, 5, extra).verify(&tree.root(), 5, b"leaf 4"));
+
+        assert_eq!(Proof::from_bytes(&[0; 7]), Err(Sm3Error::InvalidProof));
+        assert_eq!(Proof::from_bytes(&[0; 8]), Err(Sm3Error::InvalidProof));
+        assert_eq!(
+            Proof::from_bytes(&[0, 0, 0, 0, 0, 0, 0, 1, 0]),
+            Err(Sm3Error::InvalidProof)
+        );
+    }
+
+    #[test]
+    fn test_forged_size() {
+        let tree = MerkleTree::new(&[b"a", b"b", b"c"]);
+        let root = tree.root();
+        let ab = hash_node(&hash_leaf(b"a"), &hash_leaf(b"b"));
+
+        // c as the second of two leaves [ab, c] gives the same root
+        let forged = Proof::new(1, 2, vec![ab]);
+        assert_eq!(forged.compute_root(2, &hash_leaf(b"c")), Some(root));
+        assert!(!forged.verify(&root, 3, b"c"));
+        assert!(!Proof::new(1, 3, vec![ab]).verify(&root, 3, b"c"));
+        assert!(tree.proof(2).unwrap().verify(&root, 3, b"c"));
+
+        let forged = MultiProof::new(vec![1], 2, vec![ab]);
+        assert_eq!(forged.compute_root(2, &[hash_leaf(b"c")]), Some(root));
+        assert!(!forged.verify(&root, 3, &[b"c"]));
+        assert!(!MultiProof::new(vec![1], 3, vec![ab]).verify(&root, 3, &[b"c"]));
+        assert!(tree.multiproof(&[2]).unwrap().verify(&root, 3, &[b"c"]));
+    }
+
+    #[test]
+    fn test_multiproof() {
+        for n in 1..20 {
+            let data = leaves(n);
+            let tree = MerkleTree::new(&data);
+            let root = tree.root();
+            // every subset of up to 4 leaves for small trees
+            for mask in 1u32..(1 << n.min(12)) {
+                let indices: Vec<usize> =
+                    (0..n).filter(|i| *i < 32 && mask & (1 << i) != 0).collect();
+                if indices.len() > 4 {
+                    continue;
+                }
+                let proof = tree.multiproof(&indices).unwrap();
+                let selected: Vec<&[u8]> = indices.iter().map(|i| &data[*i][..]).collect();
+                assert!(
+                    proof.verify(&root, n, &selected),
+                    "n = {}, {:?}",
+                    n,
+                    indices
+                );
+                assert!(!proof.verify(&root, n + 1, &selected));
+
+                let decoded = MultiProof::from_bytes(&proof.to_bytes()).unwrap();
+                assert_eq!(decoded, proof);
+
+                let mut wrong = selected.clone();
+                wrong[0] = b"other";
+                assert!(!proof.verify(&root, n, &wrong));
+            }
+        }
+
+        let data = leaves(16);
+        let tree = MerkleTree::new(&data);
+        // all leaves need no hashes, adjacent leaves share their path
+        let all: Vec<usize> = (0..16).collect();
+        assert!(tree.multiproof(&all).unwrap().hashes().is_empty());
+        assert_eq!(tree.multiproof(&[4, 5]).unwrap().hashes().len(), 3);
+        assert_eq!(tree.multiproof(&[5, 4, 5]).unwrap().indices(), &[4, 5]);
+
+        assert!(tree.multiproof(&[]).is_none());
+        assert!(tree.multiproof(&[16]).is_none());
+
+        let proof = tree.multiproof(&[1, 9]).unwrap();
+        let selected = [&data[1][..], &data[9][..]];
+        assert!(!proof.verify(&tree.root(), 16, &selected[..1]));
+        let swapped = MultiProof::new(vec![9, 1], 16, proof.hashes().to_vec());
+        assert!(!swapped.verify(&tree.root(), 16, &[&data[9][..], &data[1][..]]));
+        let short = MultiProof::new(vec![1, 9], 16, proof.hashes()[1..].to_vec());
+        assert!(!short.verify(&tree.root(), 16, &selected));
+
+        assert_eq!(
+            MultiProof::from_bytes(&[0, 0, 0, 16, 0, 0, 0, 0]),
+            Err(Sm3Error::InvalidProof)
+        );
+        assert_eq!(
+            MultiProof::from_bytes(&[0, 0, 0, 16, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 3]),
+            Err(Sm3Error::InvalidProof)
+        );
+    }
+}
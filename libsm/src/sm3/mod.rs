@@ -12,4 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod compress;
+pub mod error;
 pub mod hash;
+pub mod hmac;
+pub mod kdf;
+pub mod merkle;
+
+pub use self::error::Sm3Error;
+pub use self::hash::{hash_many, Sm3Hash, Sm3Hasher};
+pub use self::hmac::{hmac_sm3, HmacSm3};
@@ -0,0 +1,404 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! SM4-CCM, the AEAD_SM4_CCM of RFC 8998, with the tag and nonce lengths
+//! of NIST SP 800-38C.
+
+use alloc::vec::*;
+use subtle::ConstantTimeEq;
+
+use super::cipher::Sm4Cipher;
+use super::error::Sm4Error;
+
+// CBC-MAC over the formatted input, one byte at a time so the header, the
+// additional data and the message need not be copied together
+struct CbcMac<'a> {
+    cipher: &'a Sm4Cipher,
+    state: [u8; 16],
+    pos: usize,
+}
+
+impl<'a> CbcMac<'a> {
+    fn new(cipher: &'a Sm4Cipher, b0: &[u8; 16]) -> CbcMac<'a> {
+        CbcMac {
+            cipher,
+            state: cipher.encrypt(b0),
+            pos: 0,
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for b in data {
+            self.state[self.pos] ^= b;
+            self.pos += 1;
+            if self.pos == 16 {
+                self.state = self.cipher.encrypt(&self.state);
+                self.pos = 0;
+            }
+        }
+    }
+
+    /// Pads with zeros to a whole block.
+    fn pad(&mut self) {
+        if self.pos > 0 {
+            self.state = self.cipher.encrypt(&self.state);
+            self.pos = 0;
+        }
+    }
+}
+
+/// SM4 in Counter with CBC-MAC mode.
+///
+/// The tag is 4, 6, 8, 10, 12, 14 or 16 bytes and the nonce 7 to 13 bytes.
+/// A nonce of n bytes leaves 15 - n bytes for the length of the message.
+/// `TLS_SM4_CCM_SM3` of RFC 8998 uses 16-byte tags and 12-byte nonces.
+pub struct Sm4Ccm {
+    cipher: Sm4Cipher,
+    tag_len: usize,
+    nonce_len: usize,
+}
+
+impl Sm4Ccm {
+    pub fn new(key: &[u8], tag_len: usize, nonce_len: usize) -> Sm4Ccm {
+        match Sm4Ccm::new_checked(key, tag_len, nonce_len) {
+            Ok(ccm) => ccm,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Like `new`, but returns an error when the key is not 16 bytes or the
+    /// tag or nonce length is not allowed.
+    pub fn new_checked(key: &[u8], tag_len: usize, nonce_len: usize) -> Result<Sm4Ccm, Sm4Error> {
+        let cipher = Sm4Cipher::new_checked(key)?;
+        if !(4..=16).contains(&tag_len) || !tag_len.is_multiple_of(2) {
+            return Err(Sm4Error::InvalidTagLength);
+        }
+        if !(7..=13).contains(&nonce_len) {
+            return Err(Sm4Error::InvalidNonceLength);
+        }
+        Ok(Sm4Ccm {
+            cipher,
+            tag_len,
+            nonce_len,
+        })
+    }
+
+    #[inline]
+    pub fn tag_len(&self) -> usize {
+        self.tag_len
+    }
+
+    #[inline]
+    pub fn nonce_len(&self) -> usize {
+        self.nonce_len
+    }
+
+    /// Encrypts `plaintext` and returns the ciphertext followed by the tag.
+    pub fn seal(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Sm4Error> {
+        let mut out = Vec::with_capacity(plaintext.len() + self.tag_len);
+        out.extend_from_slice(plaintext);
+        let tag = self.seal_in_place(nonce, aad, &mut out)?;
+        out.extend_from_slice(&tag);
+        Ok(out)
+    }
+
+    /// Checks the tag at the end of `ciphertext` and returns the plaintext.
+    pub fn open(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Sm4Error> {
+        if ciphertext.len() < self.tag_len {
+            return Err(Sm4Error::InvalidTag);
+        }
+        let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - self.tag_len);
+        let mut out = ciphertext.to_vec();
+        self.open_in_place(nonce, aad, &mut out, tag)?;
+        Ok(out)
+    }
+
+    /// Encrypts `buffer` in place and returns the tag.
+    pub fn seal_in_place(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<Vec<u8>, Sm4Error> {
+        self.check(nonce, buffer)?;
+        let tag = self.tag(nonce, aad, buffer);
+        self.ctr(nonce, buffer);
+        Ok(tag)
+    }
+
+    /// Decrypts `buffer` in place if `tag` is right, otherwise leaves it
+    /// untouched. The tag is compared in constant time.
+    pub fn open_in_place(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8],
+    ) -> Result<(), Sm4Error> {
+        self.check(nonce, buffer)?;
+        // the MAC is over the plaintext, which is encrypted again on failure
+        self.ctr(nonce, buffer);
+        let expected = self.tag(nonce, aad, buffer);
+        if !bool::from(expected.ct_eq(tag)) {
+            self.ctr(nonce, buffer);
+            return Err(Sm4Error::InvalidTag);
+        }
+        Ok(())
+    }
+
+    fn check(&self, nonce: &[u8], buffer: &[u8]) -> Result<(), Sm4Error> {
+        if nonce.len() != self.nonce_len {
+            return Err(Sm4Error::InvalidNonceLength);
+        }
+        // the length of the message must fit in 15 - n bytes
+        let len_bytes = 15 - self.nonce_len;
+        if len_bytes < 8 && buffer.len() as u64 >= 1 << (8 * len_bytes) {
+            return Err(Sm4Error::InvalidMessageLength);
+        }
+        Ok(())
+    }
+
+    // flags || nonce || counter, with the counter in the last 15 - n bytes
+    fn block(&self, flags: u8, nonce: &[u8], counter: u64) -> [u8; 16] {
+        let mut block = [0u8; 16];
+        block[0] = flags;
+        block[1..1 + self.nonce_len].copy_from_slice(nonce);
+        let len_bytes = 15 - self.nonce_len;
+        block[16 - len_bytes..].copy_from_slice(&counter.to_be_bytes()[8 - len_bytes..]);
+        block
+    }
+
+    fn ctr(&self, nonce: &[u8], buffer: &mut [u8]) {
+        let flags = (14 - self.nonce_len) as u8;
+        for (i, chunk) in buffer.chunks_mut(16).enumerate() {
+            let ks = self.cipher.encrypt(&self.block(flags, nonce, i as u64 + 1));
+            for (b, k) in chunk.iter_mut().zip(ks.iter()) {
+                *b ^= k;
+            }
+        }
+    }
+
+    fn tag(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let adata = if aad.is_empty() { 0 } else { 0x40 };
+        let flags = adata | (((self.tag_len - 2) / 2) << 3) as u8 | (14 - self.nonce_len) as u8;
+        let mut mac = CbcMac::new(
+            &self.cipher,
+            &self.block(flags, nonce, plaintext.len() as u64),
+        );
+
+        if !aad.is_empty() {
+            let len = aad.len() as u64;
+            if len < 0xff00 {
+                mac.update(&(len as u16).to_be_bytes());
+            } else if len <= 0xffff_ffff {
+                mac.update(&[0xff, 0xfe]);
+                mac.update(&(len as u32).to_be_bytes());
+            } else {
+                mac.update(&[0xff, 0xff]);
+                mac.update(&len.to_be_bytes());
+            }
+            mac.update(aad);
+            mac.pad();
+        }
+        mac.update(plaintext);
+        mac.pad();
+
+        let s0 = self
+            .cipher
+            .encrypt(&self.block((14 - self.nonce_len) as u8, nonce, 0));
+        mac.state
+            .iter()
+            .zip(s0.iter())
+            .take(self.tag_len)
+            .map(|(t, s)| t ^ s)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Vector {
+        key: &'static str,
+        nonce: &'static str,
+        aad: &'static str,
+        plaintext: &'static str,
+        ciphertext: &'static str,
+        tag: &'static str,
+    }
+
+    const VECTORS: &[Vector] = &[
+        // RFC 8998 A.2
+        Vector {
+            key: "0123456789abcdeffedcba9876543210",
+            nonce: "00001234567800000000abcd",
+            aad: "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+            plaintext: "aaaaaaaaaaaaaaaabbbbbbbbbbbbbbbbccccccccccccccccdddddddddddddddd\
+                        eeeeeeeeeeeeeeeeffffffffffffffffeeeeeeeeeeeeeeeeaaaaaaaaaaaaaaaa",
+            ciphertext: "48af93501fa62adbcd414cce6034d895dda1bf8f132f042098661572e7483094\
+                         fd12e518ce062c98acee28d95df4416bed31a2f04476c18bb40c84a74b97dc5b",
+            tag: "16842d4fa186f56ab33256971fa110f4",
+        },
+        // the vectors below are from OpenSSL
+        // 7-byte nonce, 4-byte tag
+        Vector {
+            key: "b74f89fabb88284c6314b0535de75c52",
+            nonce: "97504509d9c0e8",
+            aad: "",
+            plaintext: "f1eb40eeef",
+            ciphertext: "ed072b14a1",
+            tag: "a2ff7795",
+        },
+        // 13-byte nonce, 8-byte tag
+        Vector {
+            key: "4ae43fd8358484a65b03cff3b3f0ebe5",
+            nonce: "796690d3d284ec098f0f30b58c",
+            aad: "e80fb65ac70384bd8bab0358d60b7cbe96de5b2de7",
+            plaintext: "8860646b40f17b10cfcb90a2c717a4df6c5976b9bd38201d2cd231d5a6d30e83",
+            ciphertext: "3e6dc7fadf478850cbe4c929c7af78baae93190ce57d808235d5a350746adffc",
+            tag: "423b656164511a48",
+        },
+        // 12-byte nonce, 16-byte tag, no data
+        Vector {
+            key: "cb67903a62d3d4d64e5c0a3b477e5223",
+            nonce: "ce915cd09dd0784b77f52305",
+            aad: "",
+            plaintext: "",
+            ciphertext: "",
+            tag: "74ec78b24733ed108fe84d700969d98e",
+        },
+        // 10-byte nonce, 10-byte tag, no message
+        Vector {
+            key: "04d4f434855c0fa0b549d59242d03c95",
+            nonce: "f3e53a294d1034964198",
+            aad: "9b7c67eadc8bb1285f3e79adee53b76f",
+            plaintext: "",
+            ciphertext: "",
+            tag: "c10156ec6fa5f205c4da",
+        },
+        // 8-byte nonce, 14-byte tag
+        Vector {
+            key: "f78759e918ed00c0ceb21653a5790664",
+            nonce: "c9c101378ae91029",
+            aad: "4b30ab",
+            plaintext: "535e3d623df2fc0ddf6c6ea1df0641ef7da288ae155911cf4ab0e8c81670ec5570fac0602f1d58a8953404e43efefe4e72af78d9b1b2dedef296224a2a148a07d6cdae511d1fbfb0e774e08b742949f39dcd1793a6f5b708fabc320441cd3dd1e5df1ab1",
+            ciphertext: "463584ea94d1036f3aa45b49d192aaa850dfb1706ffc484cafb226f2b088039948d7d17fa24761adb7d2a5ca1048f5e875b0ab8a2f5fa55daf2e87027f41256d28e534fb58a23183c8513100e7b60dfcc10f64bc11ce46badf936592c52eaf9a3db61a65",
+            tag: "25e9016f23cd44cb5c1ada05f8a7",
+        },
+    ];
+
+    #[test]
+    fn test_vectors() {
+        for v in VECTORS {
+            let tag = hex::decode(v.tag).unwrap();
+            let nonce = hex::decode(v.nonce).unwrap();
+            let ccm = Sm4Ccm::new(&hex::decode(v.key).unwrap(), tag.len(), nonce.len());
+            let aad = hex::decode(v.aad).unwrap();
+            let plaintext = hex::decode(v.plaintext).unwrap();
+            let mut sealed = hex::decode(v.ciphertext).unwrap();
+            sealed.extend_from_slice(&tag);
+
+            assert_eq!(ccm.seal(&nonce, &aad, &plaintext).unwrap(), sealed);
+            assert_eq!(ccm.open(&nonce, &aad, &sealed).unwrap(), plaintext);
+
+            for i in 0..sealed.len() {
+                let mut bad = sealed.clone();
+                bad[i] ^= 0x80;
+                assert_eq!(ccm.open(&nonce, &aad, &bad), Err(Sm4Error::InvalidTag));
+            }
+            let mut bad_aad = aad.clone();
+            bad_aad.push(0);
+            assert_eq!(
+                ccm.open(&nonce, &bad_aad, &sealed),
+                Err(Sm4Error::InvalidTag)
+            );
+        }
+    }
+
+    #[test]
+    fn test_long_aad() {
+        // the length of the additional data takes 2 bytes below 0xff00,
+        // 6 bytes from there
+        let ccm = Sm4Ccm::new(&[0; 16], 16, 12);
+        let tags = [
+            (0xfeff, "2fe48ed023986e0ba9cbe8243185c4bf"),
+            (0xff00, "0b8fd9e3815c75aeca5a2019a6947893"),
+        ];
+        for &(len, tag) in tags.iter() {
+            let sealed = ccm.seal(&[0; 12], &vec![0x5a; len], &[0]).unwrap();
+            assert_eq!(hex::encode(&sealed), format!("7d{}", tag));
+        }
+    }
+
+    #[test]
+    fn test_in_place() {
+        let ccm = Sm4Ccm::new(&[7u8; 16], 8, 13);
+        let plaintext = b"in place encryption".to_vec();
+        let mut buffer = plaintext.clone();
+        let tag = ccm.seal_in_place(&[1u8; 13], b"aad", &mut buffer).unwrap();
+        assert_eq!(tag.len(), 8);
+        assert_ne!(buffer, plaintext);
+
+        // a wrong tag leaves the ciphertext as it is
+        let ciphertext = buffer.clone();
+        let mut bad_tag = tag.clone();
+        bad_tag[7] ^= 1;
+        assert_eq!(
+            ccm.open_in_place(&[1u8; 13], b"aad", &mut buffer, &bad_tag),
+            Err(Sm4Error::InvalidTag)
+        );
+        assert_eq!(buffer, ciphertext);
+
+        ccm.open_in_place(&[1u8; 13], b"aad", &mut buffer, &tag)
+            .unwrap();
+        assert_eq!(buffer, plaintext);
+
+        // 13-byte nonces leave 2 bytes for the length
+        assert_eq!(
+            ccm.seal(&[1u8; 13], b"", &vec![0; 0x10000]),
+            Err(Sm4Error::InvalidMessageLength)
+        );
+        assert!(ccm.seal(&[1u8; 13], b"", &vec![0; 0xffff]).is_ok());
+        assert_eq!(
+            ccm.seal(&[1u8; 12], b"", b""),
+            Err(Sm4Error::InvalidNonceLength)
+        );
+        assert_eq!(
+            ccm.open(&[1u8; 13], b"", &[0; 7]),
+            Err(Sm4Error::InvalidTag)
+        );
+
+        assert_eq!(
+            Sm4Ccm::new_checked(&[0; 16], 5, 12).err(),
+            Some(Sm4Error::InvalidTagLength)
+        );
+        assert_eq!(
+            Sm4Ccm::new_checked(&[0; 16], 18, 12).err(),
+            Some(Sm4Error::InvalidTagLength)
+        );
+        assert_eq!(
+            Sm4Ccm::new_checked(&[0; 16], 16, 6).err(),
+            Some(Sm4Error::InvalidNonceLength)
+        );
+        assert_eq!(
+            Sm4Ccm::new_checked(&[0; 16], 16, 14).err(),
+            Some(Sm4Error::InvalidNonceLength)
+        );
+        assert_eq!(
+            Sm4Ccm::new_checked(&[0; 15], 16, 12).err(),
+            Some(Sm4Error::InvalidKeyLength)
+        );
+    }
+}
@@ -14,6 +14,8 @@
 
 use alloc::vec::*;
 
+use super::error::Sm4Error;
+
 static SBOX: [u8; 256] = [
     0xd6, 0x90, 0xe9, 0xfe, 0xcc, 0xe1, 0x3d, 0xb7, 0x16, 0xb6, 0x14, 0xc2, 0x28, 0xfb, 0x2c, 0x05,
     0x2b, 0x67, 0x9a, 0x76, 0x2a, 0xbe, 0x04, 0xc3, 0xaa, 0x44, 0x13, 0x26, 0x49, 0x86, 0x06, 0x99,
@@ -167,6 +169,15 @@ impl Sm4Cipher {
         cipher
     }
 
+    /// Like `new`, but returns an error instead of panicking when the key
+    /// is not 16 bytes.
+    pub fn new_checked(key: &[u8]) -> Result<Sm4Cipher, Sm4Error> {
+        if key.len() != 16 {
+            return Err(Sm4Error::InvalidKeyLength);
+        }
+        Ok(Sm4Cipher::new(key))
+    }
+
     pub fn encrypt(&self, block_in: &[u8]) -> [u8; 16] {
         let mut x: [u32; 4] = split_block(block_in);
         let rk = &self.rk;
@@ -192,6 +203,22 @@ impl Sm4Cipher {
         let y = [x[3], x[2], x[1], x[0]];
         combine_block(&y)
     }
+
+    /// Like `encrypt`, but returns an error when the block is not 16 bytes.
+    pub fn encrypt_checked(&self, block_in: &[u8]) -> Result<[u8; 16], Sm4Error> {
+        if block_in.len() != 16 {
+            return Err(Sm4Error::InvalidBlockLength);
+        }
+        Ok(self.encrypt(block_in))
+    }
+
+    /// Like `decrypt`, but returns an error when the block is not 16 bytes.
+    pub fn decrypt_checked(&self, block_in: &[u8]) -> Result<[u8; 16], Sm4Error> {
+        if block_in.len() != 16 {
+            return Err(Sm4Error::InvalidBlockLength);
+        }
+        Ok(self.decrypt(block_in))
+    }
 }
 
 // Tests below
@@ -241,4 +268,14 @@ mod tests {
             assert_eq!(pt[i], data[i]);
         }
     }
+
+    #[test]
+    fn test_checked() {
+        assert_eq!(Sm4Cipher::new_checked(&[0; 15]).err(), Some(Sm4Error::InvalidKeyLength));
+
+        let cipher = Sm4Cipher::new_checked(&[0; 16]).unwrap();
+        assert_eq!(cipher.encrypt_checked(&[0; 15]), Err(Sm4Error::InvalidBlockLength));
+        assert_eq!(cipher.decrypt_checked(&[0; 17]), Err(Sm4Error::InvalidBlockLength));
+        assert_eq!(cipher.encrypt_checked(&[0; 16]), Ok(cipher.encrypt(&[0; 16])));
+    }
 }
@@ -13,6 +13,7 @@
 // limitations under the License.
 use alloc::vec::*;
 use super::cipher::Sm4Cipher;
+use super::error::Sm4Error;
 
 pub enum CipherMode {
     Cfb,
@@ -53,26 +54,50 @@ impl Sm4CipherMode {
         Sm4CipherMode { cipher, mode }
     }
 
+    /// Like `new`, but returns an error when the key is not 16 bytes.
+    pub fn new_checked(key: &[u8], mode: CipherMode) -> Result<Sm4CipherMode, Sm4Error> {
+        let cipher = Sm4Cipher::new_checked(key)?;
+        Ok(Sm4CipherMode { cipher, mode })
+    }
+
     pub fn encrypt(&self, data: &[u8], iv: &[u8]) -> Vec<u8> {
+        match self.encrypt_checked(data, iv) {
+            Ok(out) => out,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    pub fn decrypt(&self, data: &[u8], iv: &[u8]) -> Vec<u8> {
+        match self.decrypt_checked(data, iv) {
+            Ok(out) => out,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Like `encrypt`, but returns an error when the iv is not 16 bytes.
+    pub fn encrypt_checked(&self, data: &[u8], iv: &[u8]) -> Result<Vec<u8>, Sm4Error> {
         if iv.len() != 16 {
-            panic!("the iv of sm4 must be 16-byte long");
+            return Err(Sm4Error::InvalidIvLength);
         }
-        match self.mode {
+        Ok(match self.mode {
             CipherMode::Cfb => self.cfb_encrypt(data, iv),
             CipherMode::Ofb => self.ofb_encrypt(data, iv),
             CipherMode::Ctr => self.ctr_encrypt(data, iv),
             CipherMode::Cbc => self.cbc_encrypt(data, iv),
-        }
+        })
     }
 
-    pub fn decrypt(&self, data: &[u8], iv: &[u8]) -> Vec<u8> {
+    /// Like `decrypt`, but returns an error when the iv is not 16 bytes, or
+    /// in CBC mode when the ciphertext is not whole blocks or the padding
+    /// is wrong.
+    pub fn decrypt_checked(&self, data: &[u8], iv: &[u8]) -> Result<Vec<u8>, Sm4Error> {
         if iv.len() != 16 {
-            panic!("the iv of sm4 must be 16-byte long");
+            return Err(Sm4Error::InvalidIvLength);
         }
         match self.mode {
-            CipherMode::Cfb => self.cfb_decrypt(data, iv),
-            CipherMode::Ofb => self.ofb_encrypt(data, iv),
-            CipherMode::Ctr => self.ctr_encrypt(data, iv),
+            CipherMode::Cfb => Ok(self.cfb_decrypt(data, iv)),
+            CipherMode::Ofb => Ok(self.ofb_encrypt(data, iv)),
+            CipherMode::Ctr => Ok(self.ctr_encrypt(data, iv)),
             CipherMode::Cbc => self.cbc_decrypt(data, iv),
         }
     }
@@ -219,10 +244,12 @@ impl Sm4CipherMode {
         out
     }
 
-    fn cbc_decrypt(&self, data: &[u8], iv: &[u8]) -> Vec<u8> {
+    fn cbc_decrypt(&self, data: &[u8], iv: &[u8]) -> Result<Vec<u8>, Sm4Error> {
         let data_len = data.len();
         let block_num = data_len / 16;
-        assert_eq!(data_len % 16, 0);
+        if data_len == 0 || !data_len.is_multiple_of(16) {
+            return Err(Sm4Error::InvalidCiphertext);
+        }
 
         let mut out: Vec<u8> = Vec::new();
         let mut vec_buf = [0; 16];
@@ -239,17 +266,17 @@ impl Sm4CipherMode {
             vec_buf.copy_from_slice(&data[i * 16..i * 16 + 16]);
         }
 
-        let last_u8 = out[data_len - 1];
-        assert!(last_u8 <= 0x10 && last_u8 != 0);
-        out.resize(data_len - last_u8 as usize, 0);
+        // PKCS#7, every padding byte is the padding length
+        let pad = out[data_len - 1] as usize;
+        if pad > 16 || pad == 0 || out[data_len - pad..].iter().any(|b| *b as usize != pad) {
+            return Err(Sm4Error::InvalidPadding);
+        }
+        out.truncate(data_len - pad);
 
-        out
+        Ok(out)
     }
 }
 
-// TODO: AEAD in SM4
-// pub struct SM4Gcm;
-
 // Tests below
 
 #[cfg(test)]
@@ -267,8 +294,7 @@ mod tests {
 
     fn rand_data(len: usize) -> Vec<u8> {
         let mut rng = rand::rngs::SmallRng::seed_from_u64(183223);
-        let mut dat: Vec<u8> = Vec::new();
-        dat.resize(len, 0);
+        let mut dat: Vec<u8> = vec![0; len];
         rng.try_fill_bytes(&mut dat[..]).unwrap();
         dat
     }
@@ -358,4 +384,40 @@ mod tests {
         let rhs: &[u8] = include_bytes!("example/text.sms4-cbc");
         assert_eq!(lhs, rhs);
     }
+
+    #[test]
+    fn test_checked() {
+        let key = rand_block();
+        let iv = rand_block();
+        assert_eq!(
+            Sm4CipherMode::new_checked(&key[..15], CipherMode::Cbc).err(),
+            Some(Sm4Error::InvalidKeyLength)
+        );
+
+        let cmode = Sm4CipherMode::new_checked(&key, CipherMode::Cbc).unwrap();
+        assert_eq!(cmode.encrypt_checked(b"abc", &iv[..8]), Err(Sm4Error::InvalidIvLength));
+        assert_eq!(cmode.decrypt_checked(&[0; 16], &iv[..8]), Err(Sm4Error::InvalidIvLength));
+        assert_eq!(cmode.decrypt_checked(&[], &iv), Err(Sm4Error::InvalidCiphertext));
+        assert_eq!(cmode.decrypt_checked(&[0; 17], &iv), Err(Sm4Error::InvalidCiphertext));
+
+        // without the padding block the plaintext ends with 0x00
+        let ct = cmode.encrypt_checked(&[0; 16], &iv).unwrap();
+        assert_eq!(cmode.decrypt_checked(&ct[..16], &iv), Err(Sm4Error::InvalidPadding));
+        let pt = cmode.decrypt_checked(&ct, &iv).unwrap();
+        assert_eq!(pt, vec![0; 16]);
+    }
+
+    #[test]
+    fn test_corrupted_padding() {
+        let key = rand_block();
+        let mut iv = rand_block();
+        let cmode = Sm4CipherMode::new(&key, CipherMode::Cbc);
+
+        // 13 bytes are padded with 03 03 03, flipping a bit of the IV flips
+        // the same bit of the first padding byte, the last one stays valid
+        let ct = cmode.encrypt(&[0x42; 13], &iv);
+        assert_eq!(cmode.decrypt_checked(&ct, &iv).unwrap(), vec![0x42; 13]);
+        iv[13] ^= 0x01;
+        assert_eq!(cmode.decrypt_checked(&ct, &iv), Err(Sm4Error::InvalidPadding));
+    }
 }
@@ -0,0 +1,51 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sm4Error {
+    InvalidKeyLength,
+    InvalidBlockLength,
+    InvalidIvLength,
+    InvalidCiphertext,
+    InvalidPadding,
+    InvalidNonceLength,
+    InvalidMessageLength,
+    InvalidTag,
+    InvalidTagLength,
+}
+
+impl From<Sm4Error> for &str {
+    fn from(e: Sm4Error) -> Self {
+        match e {
+            Sm4Error::InvalidKeyLength => "the key of sm4 must be 16-byte long",
+            Sm4Error::InvalidBlockLength => "the block size of sm4 must be 16",
+            Sm4Error::InvalidIvLength => "the iv of sm4 must be 16-byte long",
+            Sm4Error::InvalidCiphertext => "the length of ciphertext must be a multiple of 16",
+            Sm4Error::InvalidPadding => "invalid padding",
+            Sm4Error::InvalidNonceLength => "invalid nonce length",
+            Sm4Error::InvalidMessageLength => "the message is too long",
+            Sm4Error::InvalidTag => "authentication failed",
+            Sm4Error::InvalidTagLength => "invalid tag length",
+        }
+    }
+}
+
+impl core::fmt::Display for Sm4Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str((*self).into())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Sm4Error {}
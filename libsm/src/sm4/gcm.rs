@@ -0,0 +1,487 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! SM4-GCM, the AEAD_SM4_GCM of RFC 8998.
+
+use aead::consts::{U0, U12, U16};
+use aead::{AeadCore, AeadInPlace, Key, KeyInit, KeySizeUser, Nonce, Tag};
+use alloc::vec::*;
+use subtle::ConstantTimeEq;
+
+use super::cipher::Sm4Cipher;
+use super::error::Sm4Error;
+
+// 2^36 - 32 bytes, NIST SP 800-38D
+const MAX_MESSAGE_LEN: u64 = (1 << 36) - 32;
+
+// carry-less multiplication of 64-bit words with integer multiplications,
+// every fourth bit is kept so the carries fall into the holes (BearSSL
+// ghash_ctmul64)
+fn bmul64(x: u64, y: u64) -> u64 {
+    let x0 = x & 0x1111_1111_1111_1111;
+    let x1 = x & 0x2222_2222_2222_2222;
+    let x2 = x & 0x4444_4444_4444_4444;
+    let x3 = x & 0x8888_8888_8888_8888;
+    let y0 = y & 0x1111_1111_1111_1111;
+    let y1 = y & 0x2222_2222_2222_2222;
+    let y2 = y & 0x4444_4444_4444_4444;
+    let y3 = y & 0x8888_8888_8888_8888;
+    let mul = u64::wrapping_mul;
+    let z0 = mul(x0, y0) ^ mul(x1, y3) ^ mul(x2, y2) ^ mul(x3, y1);
+    let z1 = mul(x0, y1) ^ mul(x1, y0) ^ mul(x2, y3) ^ mul(x3, y2);
+    let z2 = mul(x0, y2) ^ mul(x1, y1) ^ mul(x2, y0) ^ mul(x3, y3);
+    let z3 = mul(x0, y3) ^ mul(x1, y2) ^ mul(x2, y1) ^ mul(x3, y0);
+    (z0 & 0x1111_1111_1111_1111)
+        | (z1 & 0x2222_2222_2222_2222)
+        | (z2 & 0x4444_4444_4444_4444)
+        | (z3 & 0x8888_8888_8888_8888)
+}
+
+/// GHASH in constant time, without tables indexed by secret data.
+#[derive(Clone)]
+struct GHash {
+    // H and the values Karatsuba needs, high and low halves
+    h1: u64,
+    h0: u64,
+    h2: u64,
+    h0r: u64,
+    h1r: u64,
+    h2r: u64,
+    y1: u64,
+    y0: u64,
+}
+
+impl GHash {
+    fn new(h: &[u8; 16]) -> GHash {
+        let h1 = read_u64(&h[..8]);
+        let h0 = read_u64(&h[8..]);
+        GHash {
+            h1,
+            h0,
+            h2: h0 ^ h1,
+            h0r: h0.reverse_bits(),
+            h1r: h1.reverse_bits(),
+            h2r: (h0 ^ h1).reverse_bits(),
+            y1: 0,
+            y0: 0,
+        }
+    }
+
+    fn block(&mut self, block: &[u8]) {
+        let y1 = self.y1 ^ read_u64(&block[..8]);
+        let y0 = self.y0 ^ read_u64(&block[8..]);
+        let y0r = y0.reverse_bits();
+        let y1r = y1.reverse_bits();
+        let y2 = y0 ^ y1;
+        let y2r = y0r ^ y1r;
+
+        let z0 = bmul64(y0, self.h0);
+        let z1 = bmul64(y1, self.h1);
+        let mut z2 = bmul64(y2, self.h2);
+        let mut z0h = bmul64(y0r, self.h0r);
+        let mut z1h = bmul64(y1r, self.h1r);
+        let mut z2h = bmul64(y2r, self.h2r);
+        z2 ^= z0 ^ z1;
+        z2h ^= z0h ^ z1h;
+        z0h = z0h.reverse_bits() >> 1;
+        z1h = z1h.reverse_bits() >> 1;
+        z2h = z2h.reverse_bits() >> 1;
+
+        // the 256-bit product, shifted since the bits are reflected
+        let mut v0 = z0;
+        let mut v1 = z0h ^ z2;
+        let mut v2 = z1 ^ z2h;
+        let mut v3 = z1h;
+        v3 = (v3 << 1) | (v2 >> 63);
+        v2 = (v2 << 1) | (v1 >> 63);
+        v1 = (v1 << 1) | (v0 >> 63);
+        v0 <<= 1;
+
+        // reduction modulo x^128 + x^7 + x^2 + x + 1
+        v2 ^= v0 ^ (v0 >> 1) ^ (v0 >> 2) ^ (v0 >> 7);
+        v1 ^= (v0 << 63) ^ (v0 << 62) ^ (v0 << 57);
+        v3 ^= v1 ^ (v1 >> 1) ^ (v1 >> 2) ^ (v1 >> 7);
+        v2 ^= (v1 << 63) ^ (v1 << 62) ^ (v1 << 57);
+
+        self.y0 = v2;
+        self.y1 = v3;
+    }
+
+    /// Absorbs `data` padded with zeros to whole blocks.
+    fn update_padded(&mut self, data: &[u8]) {
+        let mut chunks = data.chunks_exact(16);
+        for block in &mut chunks {
+            self.block(block);
+        }
+        let tail = chunks.remainder();
+        if !tail.is_empty() {
+            let mut block = [0u8; 16];
+            block[..tail.len()].copy_from_slice(tail);
+            self.block(&block);
+        }
+    }
+
+    /// Absorbs the bit lengths of the two inputs.
+    fn finalize(mut self, len_a: usize, len_b: usize) -> [u8; 16] {
+        let mut block = [0u8; 16];
+        block[..8].copy_from_slice(&(len_a as u64 * 8).to_be_bytes());
+        block[8..].copy_from_slice(&(len_b as u64 * 8).to_be_bytes());
+        self.block(&block);
+
+        let mut out = [0u8; 16];
+        out[..8].copy_from_slice(&self.y1.to_be_bytes());
+        out[8..].copy_from_slice(&self.y0.to_be_bytes());
+        out
+    }
+}
+
+fn read_u64(b: &[u8]) -> u64 {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(b);
+    u64::from_be_bytes(buf)
+}
+
+fn inc32(counter: &mut [u8; 16]) {
+    let mut ctr = [0u8; 4];
+    ctr.copy_from_slice(&counter[12..]);
+    let ctr = u32::from_be_bytes(ctr).wrapping_add(1);
+    counter[12..].copy_from_slice(&ctr.to_be_bytes());
+}
+
+/// SM4 in Galois/Counter Mode with 16-byte tags.
+///
+/// The nonce is 12 bytes in RFC 8998 and in the `aead` traits, `seal` and
+/// `open` also take nonces of other lengths, which are hashed into the
+/// initial counter as in NIST SP 800-38D.
+pub struct Sm4Gcm {
+    cipher: Sm4Cipher,
+    ghash: GHash,
+}
+
+impl Sm4Gcm {
+    pub fn new(key: &[u8]) -> Sm4Gcm {
+        match Sm4Gcm::new_checked(key) {
+            Ok(gcm) => gcm,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Like `new`, but returns an error when the key is not 16 bytes.
+    pub fn new_checked(key: &[u8]) -> Result<Sm4Gcm, Sm4Error> {
+        let cipher = Sm4Cipher::new_checked(key)?;
+        let h = cipher.encrypt(&[0u8; 16]);
+        Ok(Sm4Gcm {
+            cipher,
+            ghash: GHash::new(&h),
+        })
+    }
+
+    /// Encrypts `plaintext` and returns the ciphertext followed by the tag.
+    pub fn seal(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Sm4Error> {
+        let mut out = Vec::with_capacity(plaintext.len() + 16);
+        out.extend_from_slice(plaintext);
+        let tag = self.seal_in_place(nonce, aad, &mut out)?;
+        out.extend_from_slice(&tag);
+        Ok(out)
+    }
+
+    /// Checks the tag at the end of `ciphertext` and returns the plaintext.
+    pub fn open(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Sm4Error> {
+        if ciphertext.len() < 16 {
+            return Err(Sm4Error::InvalidTag);
+        }
+        let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - 16);
+        let mut out = ciphertext.to_vec();
+        self.open_in_place(nonce, aad, &mut out, tag)?;
+        Ok(out)
+    }
+
+    /// Encrypts `buffer` in place and returns the tag.
+    pub fn seal_in_place(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<[u8; 16], Sm4Error> {
+        if buffer.len() as u64 > MAX_MESSAGE_LEN {
+            return Err(Sm4Error::InvalidMessageLength);
+        }
+        let j0 = self.j0(nonce)?;
+        self.ctr(&j0, buffer);
+        Ok(self.tag(&j0, aad, buffer))
+    }
+
+    /// Decrypts `buffer` in place if `tag` is right, otherwise leaves it
+    /// untouched. The tag is compared in constant time.
+    pub fn open_in_place(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8],
+    ) -> Result<(), Sm4Error> {
+        if buffer.len() as u64 > MAX_MESSAGE_LEN {
+            return Err(Sm4Error::InvalidMessageLength);
+        }
+        let j0 = self.j0(nonce)?;
+        let expected = self.tag(&j0, aad, buffer);
+        if !bool::from(expected.ct_eq(tag)) {
+            return Err(Sm4Error::InvalidTag);
+        }
+        self.ctr(&j0, buffer);
+        Ok(())
+    }
+
+    // the pre-counter block
+    fn j0(&self, nonce: &[u8]) -> Result<[u8; 16], Sm4Error> {
+        let mut j0 = [0u8; 16];
+        if nonce.len() == 12 {
+            j0[..12].copy_from_slice(nonce);
+            j0[15] = 1;
+        } else if !nonce.is_empty() && (nonce.len() as u64) < (1 << 61) {
+            let mut ghash = self.ghash.clone();
+            ghash.update_padded(nonce);
+            j0 = ghash.finalize(0, nonce.len());
+        } else {
+            return Err(Sm4Error::InvalidNonceLength);
+        }
+        Ok(j0)
+    }
+
+    fn ctr(&self, j0: &[u8; 16], buffer: &mut [u8]) {
+        let mut counter = *j0;
+        for chunk in buffer.chunks_mut(16) {
+            inc32(&mut counter);
+            let ks = self.cipher.encrypt(&counter);
+            for (b, k) in chunk.iter_mut().zip(ks.iter()) {
+                *b ^= k;
+            }
+        }
+    }
+
+    fn tag(&self, j0: &[u8; 16], aad: &[u8], ciphertext: &[u8]) -> [u8; 16] {
+        let mut ghash = self.ghash.clone();
+        ghash.update_padded(aad);
+        ghash.update_padded(ciphertext);
+        let mut tag = ghash.finalize(aad.len(), ciphertext.len());
+        let mask = self.cipher.encrypt(j0);
+        for (t, m) in tag.iter_mut().zip(mask.iter()) {
+            *t ^= m;
+        }
+        tag
+    }
+}
+
+// The RustCrypto traits with 12-byte nonces, `aead::Aead` comes with them
+impl KeySizeUser for Sm4Gcm {
+    type KeySize = U16;
+}
+
+impl KeyInit for Sm4Gcm {
+    fn new(key: &Key<Self>) -> Self {
+        Sm4Gcm::new(key)
+    }
+}
+
+impl AeadCore for Sm4Gcm {
+    type NonceSize = U12;
+    type TagSize = U16;
+    type CiphertextOverhead = U0;
+}
+
+impl AeadInPlace for Sm4Gcm {
+    fn encrypt_in_place_detached(
+        &self,
+        nonce: &Nonce<Self>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+    ) -> aead::Result<Tag<Self>> {
+        self.seal_in_place(nonce, associated_data, buffer)
+            .map(Tag::<Self>::from)
+            .map_err(|_| aead::Error)
+    }
+
+    fn decrypt_in_place_detached(
+        &self,
+        nonce: &Nonce<Self>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &Tag<Self>,
+    ) -> aead::Result<()> {
+        self.open_in_place(nonce, associated_data, buffer, tag)
+            .map_err(|_| aead::Error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Vector {
+        key: &'static str,
+        nonce: &'static str,
+        aad: &'static str,
+        plaintext: &'static str,
+        ciphertext: &'static str,
+        tag: &'static str,
+    }
+
+    const VECTORS: &[Vector] = &[
+        // RFC 8998 A.1
+        Vector {
+            key: "0123456789abcdeffedcba9876543210",
+            nonce: "00001234567800000000abcd",
+            aad: "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+            plaintext: "aaaaaaaaaaaaaaaabbbbbbbbbbbbbbbbccccccccccccccccdddddddddddddddd\
+                        eeeeeeeeeeeeeeeeffffffffffffffffeeeeeeeeeeeeeeeeaaaaaaaaaaaaaaaa",
+            ciphertext: "17f399f08c67d5ee19d0dc9969c4bb7d5fd46fd3756489069157b282bb200735\
+                         d82710ca5c22f0ccfa7cbf93d496ac15a56834cbcf98c397b4024a2691233b8d",
+            tag: "83de3541e4c2b58177e065a9bf7b62ec",
+        },
+        // 12-byte nonce, no data
+        Vector {
+            key: "b74f89fabb88284c6314b0535de75c52",
+            nonce: "97504509d9c0e802f6f5b70d",
+            aad: "",
+            plaintext: "",
+            ciphertext: "",
+            tag: "dd3ab13ccb7437610dbd6910eb940be5",
+        },
+        // 8-byte nonce
+        Vector {
+            key: "4ae43fd8358484a65b03cff3b3f0ebe5",
+            nonce: "796690d3d284ec09",
+            aad: "e80fb65ac70384bd8bab0358d6",
+            plaintext: "8860646b40f17b10cfcb90a2c717a4df6c",
+            ciphertext: "1112341c1e9a577aa1723a27cc4999fc63",
+            tag: "da1f1811c7c5d4329ed157c3021b5e42",
+        },
+        // 16-byte nonce
+        Vector {
+            key: "cb67903a62d3d4d64e5c0a3b477e5223",
+            nonce: "ce915cd09dd0784b77f52305fcb4435d",
+            aad: "",
+            plaintext: "c1e2cf4aaa4b3565abc34ba4f2990a58588fe0198bf3cc3dd8e63ec4bc8467",
+            ciphertext: "2b8a0a93d1828217cff3b16080ad080063bdb01098b0b4375bb226f66ce344",
+            tag: "dca2a85a1fd8022e102613407c2520d1",
+        },
+        // 60-byte nonce
+        Vector {
+            key: "04d4f434855c0fa0b549d59242d03c95",
+            nonce: "f3e53a294d10349641982e4f018fc42e84997212e1bb8fee37412150b33606989a7b907af1ca0bce6e806cdae133131dff2f593100e8d2fa8031e4f9",
+            aad: "9b7c67eadc8bb1285f3e79adee53b76f63c8a044",
+            plaintext: "e9266ccd10a1aeb5c92fa19ee74155b21c32bb6d17c20830c702a0ed0bc0490257b8361ff5051fb43910564c505852cd5ee21105b07ac006ab313ebb16327b51",
+            ciphertext: "b908641054851a105f76b140e134629957174306ca97180cf169cab20ce7245844ccc373da853549e6b9986f35fdab3db5ce013c0e4abf0e65bd466278a5026f",
+            tag: "13451cc553bd813546b0a975f8900199",
+        },
+    ];
+
+    #[test]
+    fn test_vectors() {
+        for v in VECTORS {
+            let gcm = Sm4Gcm::new(&hex::decode(v.key).unwrap());
+            let nonce = hex::decode(v.nonce).unwrap();
+            let aad = hex::decode(v.aad).unwrap();
+            let plaintext = hex::decode(v.plaintext).unwrap();
+            let mut sealed = hex::decode(v.ciphertext).unwrap();
+            sealed.extend_from_slice(&hex::decode(v.tag).unwrap());
+
+            assert_eq!(gcm.seal(&nonce, &aad, &plaintext).unwrap(), sealed);
+            assert_eq!(gcm.open(&nonce, &aad, &sealed).unwrap(), plaintext);
+
+            for i in 0..sealed.len() {
+                let mut bad = sealed.clone();
+                bad[i] ^= 0x80;
+                assert_eq!(gcm.open(&nonce, &aad, &bad), Err(Sm4Error::InvalidTag));
+            }
+            let mut bad_aad = aad.clone();
+            bad_aad.push(0);
+            assert_eq!(
+                gcm.open(&nonce, &bad_aad, &sealed),
+                Err(Sm4Error::InvalidTag)
+            );
+        }
+    }
+
+    #[test]
+    fn test_in_place() {
+        let gcm = Sm4Gcm::new(&[7u8; 16]);
+        let plaintext = b"in place encryption".to_vec();
+        let mut buffer = plaintext.clone();
+        let tag = gcm.seal_in_place(&[1u8; 12], b"aad", &mut buffer).unwrap();
+        assert_ne!(buffer, plaintext);
+
+        // a wrong tag leaves the ciphertext as it is
+        let ciphertext = buffer.clone();
+        let mut bad_tag = tag;
+        bad_tag[15] ^= 1;
+        assert_eq!(
+            gcm.open_in_place(&[1u8; 12], b"aad", &mut buffer, &bad_tag),
+            Err(Sm4Error::InvalidTag)
+        );
+        assert_eq!(buffer, ciphertext);
+        assert_eq!(
+            gcm.open_in_place(&[1u8; 12], b"aad", &mut buffer, &tag[..15]),
+            Err(Sm4Error::InvalidTag)
+        );
+
+        gcm.open_in_place(&[1u8; 12], b"aad", &mut buffer, &tag)
+            .unwrap();
+        assert_eq!(buffer, plaintext);
+
+        assert_eq!(gcm.seal(&[], b"", b""), Err(Sm4Error::InvalidNonceLength));
+        assert_eq!(
+            gcm.open(&[1u8; 12], b"", &[0; 15]),
+            Err(Sm4Error::InvalidTag)
+        );
+        assert_eq!(
+            Sm4Gcm::new_checked(&[0; 15]).err(),
+            Some(Sm4Error::InvalidKeyLength)
+        );
+    }
+
+    #[test]
+    fn test_aead_traits() {
+        use aead::{Aead, Payload};
+
+        let v = &VECTORS[0];
+        let key = hex::decode(v.key).unwrap();
+        let gcm = <Sm4Gcm as KeyInit>::new_from_slice(&key).unwrap();
+        let nonce = Nonce::<Sm4Gcm>::clone_from_slice(&hex::decode(v.nonce).unwrap());
+        let aad = hex::decode(v.aad).unwrap();
+        let plaintext = hex::decode(v.plaintext).unwrap();
+
+        let payload = Payload {
+            msg: &plaintext,
+            aad: &aad,
+        };
+        let sealed = gcm.encrypt(&nonce, payload).unwrap();
+        assert_eq!(hex::encode(&sealed[sealed.len() - 16..]), v.tag);
+
+        let mut buffer = plaintext.clone();
+        let tag = gcm
+            .encrypt_in_place_detached(&nonce, &aad, &mut buffer)
+            .unwrap();
+        assert_eq!(&tag[..], &sealed[sealed.len() - 16..]);
+        gcm.decrypt_in_place_detached(&nonce, &aad, &mut buffer, &tag)
+            .unwrap();
+        assert_eq!(buffer, plaintext);
+
+        let payload = Payload {
+            msg: &sealed,
+            aad: b"",
+        };
+        assert!(gcm.decrypt(&nonce, payload).is_err());
+    }
+}
@@ -12,8 +12,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod ccm;
 pub mod cipher;
 pub mod cipher_mode;
+pub mod error;
+pub mod gcm;
 
 pub type Mode = self::cipher_mode::CipherMode;
 pub type Cipher = self::cipher_mode::Sm4CipherMode;
+
+pub use self::ccm::Sm4Ccm;
+pub use self::error::Sm4Error;
+pub use self::gcm::Sm4Gcm;
@@ -0,0 +1,459 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::string::*;
+use alloc::vec::*;
+use num_bigint::BigUint;
+use yasna;
+use yasna::models::ObjectIdentifier;
+use yasna::{ASN1Result, BERReader, Tag};
+
+use super::der::*;
+use super::error::X509Error;
+use super::name::Name;
+use sm2::keys::{pem_decode, pem_encode};
+use sm2::signature::{Pubkey, SigCtx, Signature, DEFAULT_ID};
+
+pub const PEM_CERTIFICATE: &str = "CERTIFICATE";
+
+// 2.5.29.19
+const OID_BASIC_CONSTRAINTS: &[u64] = &[2, 5, 29, 19];
+// 2.5.29.15
+const OID_KEY_USAGE: &[u64] = &[2, 5, 29, 15];
+// extensions that don't restrict the use of a certificate, they are accepted
+// when critical: subject key identifier, subject alternative name, authority
+// key identifier and extended key usage
+const OID_IGNORED: &[&[u64]] = &[
+    &[2, 5, 29, 14],
+    &[2, 5, 29, 17],
+    &[2, 5, 29, 35],
+    &[2, 5, 29, 37],
+];
+
+/// Bits of the key usage extension.
+pub const KU_DIGITAL_SIGNATURE: u16 = 1;
+pub const KU_NON_REPUDIATION: u16 = 1 << 1;
+pub const KU_KEY_ENCIPHERMENT: u16 = 1 << 2;
+pub const KU_DATA_ENCIPHERMENT: u16 = 1 << 3;
+pub const KU_KEY_AGREEMENT: u16 = 1 << 4;
+pub const KU_KEY_CERT_SIGN: u16 = 1 << 5;
+pub const KU_CRL_SIGN: u16 = 1 << 6;
+pub const KU_ENCIPHER_ONLY: u16 = 1 << 7;
+pub const KU_DECIPHER_ONLY: u16 = 1 << 8;
+
+/// Seconds since the Unix epoch, both ends included.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Validity {
+    pub not_before: i64,
+    pub not_after: i64,
+}
+
+impl Validity {
+    pub fn check(&self, time: i64) -> Result<(), X509Error> {
+        if time < self.not_before {
+            Err(X509Error::NotYetValid)
+        } else if time > self.not_after {
+            Err(X509Error::Expired)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BasicConstraints {
+    pub ca: bool,
+    pub path_len: Option<u32>,
+}
+
+/// An X.509 certificate with an SM2 key, signed with SM2-with-SM3.
+#[derive(Clone)]
+pub struct Certificate {
+    der: Vec<u8>,
+    tbs: Vec<u8>,
+    version: u8,
+    serial: BigUint,
+    issuer: Name,
+    subject: Name,
+    validity: Validity,
+    public_key: Pubkey,
+    basic_constraints: Option<BasicConstraints>,
+    key_usage: Option<u16>,
+    unknown_critical: bool,
+    signature: Signature,
+}
+
+// the parsed extensions
+#[derive(Default)]
+struct Extensions {
+    basic_constraints: Option<BasicConstraints>,
+    key_usage: Option<u16>,
+    unknown_critical: bool,
+}
+
+impl Certificate {
+    pub fn from_der(der: &[u8]) -> Result<Certificate, X509Error> {
+        let (tbs, algorithm, (signature, bits)) = yasna::parse_der(der, |reader| {
+            reader.read_sequence(|reader| {
+                let tbs = reader.next().read_der()?;
+                let algorithm = Algorithm::read(reader.next())?;
+                let signature = reader.next().read_bitvec_bytes()?;
+                Ok((tbs, algorithm, signature))
+            })
+        })
+        .map_err(|_| X509Error::InvalidDer)?;
+
+        let (version, serial, tbs_algorithm, issuer, validity, subject, spki, extensions) =
+            yasna::parse_der(&tbs, |reader| {
+                reader.read_sequence(|reader| {
+                    let version = reader
+                        .read_optional(|reader| reader.read_tagged(Tag::context(0), |reader| reader.read_u8()))?
+                        .unwrap_or(0);
+                    if version > 2 {
+                        return Err(invalid());
+                    }
+                    let serial = reader.next().read_biguint()?;
+                    let algorithm = Algorithm::read(reader.next())?;
+                    let issuer = Name::from_der(reader.next().read_der()?)?;
+                    let validity = reader.next().read_sequence(|reader| {
+                        let not_before = read_time(reader.next())?;
+                        let not_after = read_time(reader.next())?;
+                        Ok(Validity { not_before, not_after })
+                    })?;
+                    let subject = Name::from_der(reader.next().read_der()?)?;
+                    let spki = read_spki(reader.next())?;
+                    // unique identifiers of v2, unused
+                    for i in 1..3 {
+                        reader.read_optional(|reader| {
+                            reader.read_tagged_implicit(Tag::context(i), |reader| reader.read_bitvec_bytes())
+                        })?;
+                    }
+                    let extensions = reader.read_optional(|reader| {
+                        reader.read_tagged(Tag::context(3), read_extensions)
+                    })?;
+                    if extensions.is_some() && version != 2 {
+                        return Err(invalid());
+                    }
+                    Ok((
+                        version + 1,
+                        serial,
+                        algorithm,
+                        issuer,
+                        validity,
+                        subject,
+                        spki,
+                        extensions.unwrap_or_default(),
+                    ))
+                })
+            })
+            .map_err(|_| X509Error::InvalidDer)?;
+
+        if tbs_algorithm != algorithm {
+            return Err(X509Error::InvalidDer);
+        }
+        check_sm2_with_sm3(&algorithm)?;
+        let public_key = sm2_public_key(&spki.0, &spki.1)?;
+        let signature = sm2_signature(&signature, bits)?;
+
+        Ok(Certificate {
+            der: der.to_vec(),
+            tbs,
+            version,
+            serial,
+            issuer,
+            subject,
+            validity,
+            public_key,
+            basic_constraints: extensions.basic_constraints,
+            key_usage: extensions.key_usage,
+            unknown_critical: extensions.unknown_critical,
+            signature,
+        })
+    }
+
+    /// The first certificate of a PEM.
+    pub fn from_pem(pem: &str) -> Result<Certificate, X509Error> {
+        match pem_decode(pem).map_err(|_| X509Error::InvalidPem)? {
+            (ref label, ref der) if label == PEM_CERTIFICATE => Certificate::from_der(der),
+            _ => Err(X509Error::InvalidPem),
+        }
+    }
+
+    /// All the certificates of a PEM bundle, in order.
+    pub fn from_pem_bundle(pem: &str) -> Result<Vec<Certificate>, X509Error> {
+        let mut certs = Vec::new();
+        let mut rest = pem;
+        while let Some(begin) = rest.find("-----BEGIN ") {
+            rest = &rest[begin..];
+            certs.push(Certificate::from_pem(rest)?);
+            let end = rest.find("-----END ").ok_or(X509Error::InvalidPem)?;
+            rest = &rest[end + 9..];
+        }
+        Ok(certs)
+    }
+
+    #[inline]
+    pub fn der(&self) -> &[u8] {
+        &self.der
+    }
+
+    pub fn to_pem(&self) -> String {
+        pem_encode(PEM_CERTIFICATE, &self.der)
+    }
+
+    /// The signed part, TBSCertificate.
+    #[inline]
+    pub fn tbs(&self) -> &[u8] {
+        &self.tbs
+    }
+
+    /// 1, 2 or 3.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    #[inline]
+    pub fn serial(&self) -> &BigUint {
+        &self.serial
+    }
+
+    #[inline]
+    pub fn issuer(&self) -> &Name {
+        &self.issuer
+    }
+
+    #[inline]
+    pub fn subject(&self) -> &Name {
+        &self.subject
+    }
+
+    #[inline]
+    pub fn validity(&self) -> &Validity {
+        &self.validity
+    }
+
+    #[inline]
+    pub fn public_key(&self) -> &Pubkey {
+        &self.public_key
+    }
+
+    #[inline]
+    pub fn basic_constraints(&self) -> Option<&BasicConstraints> {
+        self.basic_constraints.as_ref()
+    }
+
+    /// The `KU_*` bits of the key usage extension.
+    #[inline]
+    pub fn key_usage(&self) -> Option<u16> {
+        self.key_usage
+    }
+
+    #[inline]
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    /// Whether the certificate may issue certificates: the basic constraints
+    /// mark it as a CA and the key usage, if present, allows keyCertSign.
+    pub fn is_ca(&self) -> bool {
+        let ca = self.basic_constraints.is_some_and(|bc| bc.ca);
+        ca && self.key_usage.is_none_or(|ku| ku & KU_KEY_CERT_SIGN != 0)
+    }
+
+    /// Whether the subject and the issuer are the same name.
+    pub fn is_self_issued(&self) -> bool {
+        self.subject == self.issuer
+    }
+
+    /// Verifies the signature with the key of the issuer and the default ID,
+    /// as GM/T 0015 specifies.
+    pub fn verify_signature(&self, issuer_key: &Pubkey) -> Result<(), X509Error> {
+        self.verify_signature_with_id(DEFAULT_ID, issuer_key)
+    }
+
+    /// Verifies the signature with another ID. OpenSSL signs certificates
+    /// with an empty ID unless `-sigopt distid:1234567812345678` is given.
+    pub fn verify_signature_with_id(&self, id: &str, issuer_key: &Pubkey) -> Result<(), X509Error> {
+        if SigCtx::new().verify_with_id(id, &self.tbs, issuer_key, &self.signature) {
+            Ok(())
+        } else {
+            Err(X509Error::InvalidSignature)
+        }
+    }
+
+    /// The checks that don't depend on the issuer: the validity period at
+    /// `time` and the absence of unknown critical extensions.
+    pub fn check(&self, time: i64) -> Result<(), X509Error> {
+        if self.unknown_critical {
+            return Err(X509Error::UnsupportedExtension);
+        }
+        self.validity.check(time)
+    }
+}
+
+impl PartialEq for Certificate {
+    fn eq(&self, other: &Certificate) -> bool {
+        self.der == other.der
+    }
+}
+
+impl Eq for Certificate {}
+
+fn read_extensions(reader: BERReader) -> ASN1Result<Extensions> {
+    let mut extensions = Extensions::default();
+    let mut seen: Vec<ObjectIdentifier> = Vec::new();
+    reader.read_sequence_of(|reader| {
+        let (oid, critical, value) = reader.read_sequence(|reader| {
+            let oid = reader.next().read_oid()?;
+            let critical = reader.read_default(false, |reader| reader.read_bool())?;
+            let value = reader.next().read_bytes()?;
+            Ok((oid, critical, value))
+        })?;
+        // RFC 5280 4.2, at most one instance of an extension
+        if seen.contains(&oid) {
+            return Err(invalid());
+        }
+
+        let components = oid.components().as_slice();
+        if components == OID_BASIC_CONSTRAINTS {
+            extensions.basic_constraints = Some(yasna::parse_der(&value, |reader| {
+                reader.read_sequence(|reader| {
+                    let ca = reader.read_default(false, |reader| reader.read_bool())?;
+                    let path_len = reader.read_optional(|reader| reader.read_u32())?;
+                    Ok(BasicConstraints { ca, path_len })
+                })
+            })?);
+        } else if components == OID_KEY_USAGE {
+            let (bytes, bits) = yasna::parse_der(&value, |reader| reader.read_bitvec_bytes())?;
+            let mut usage = 0u16;
+            for i in 0..bits.min(9) {
+                if bytes[i / 8] & (0x80 >> (i % 8)) != 0 {
+                    usage |= 1 << i;
+                }
+            }
+            extensions.key_usage = Some(usage);
+        } else if critical && !OID_IGNORED.contains(&components) {
+            extensions.unknown_critical = true;
+        }
+        seen.push(oid);
+        Ok(())
+    })?;
+    Ok(extensions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use x509::name;
+    use x509::testdata::*;
+
+    #[test]
+    fn test_parse() {
+        let cert = Certificate::from_pem(LEAF).unwrap();
+        assert_eq!(cert.version(), 3);
+        assert_eq!(*cert.serial(), BigUint::from(3u32));
+        assert_eq!(cert.subject().common_name(), Some("node1.example.org"));
+        assert_eq!(cert.subject().get(name::COUNTRY), Some("CN"));
+        assert_eq!(cert.issuer().common_name(), Some("Example Issuing CA"));
+        // 2025-01-01 and 2026-01-01
+        assert_eq!(cert.validity().not_before, 1735689600);
+        assert_eq!(cert.validity().not_after, 1767225600);
+        assert_eq!(
+            cert.basic_constraints(),
+            Some(&BasicConstraints { ca: false, path_len: None })
+        );
+        assert_eq!(cert.key_usage(), Some(KU_DIGITAL_SIGNATURE));
+        assert!(!cert.is_ca());
+        assert!(!cert.is_self_issued());
+
+        let inter = Certificate::from_pem(INTER).unwrap();
+        assert_eq!(
+            inter.basic_constraints(),
+            Some(&BasicConstraints { ca: true, path_len: Some(0) })
+        );
+        assert_eq!(inter.key_usage(), Some(KU_KEY_CERT_SIGN | KU_CRL_SIGN));
+        assert!(inter.is_ca());
+        assert_eq!(inter.subject(), cert.issuer());
+
+        let root = Certificate::from_pem(ROOT).unwrap();
+        assert!(root.is_self_issued());
+        assert!(root.verify_signature(root.public_key()).is_ok());
+        assert!(inter.verify_signature(root.public_key()).is_ok());
+        assert!(cert.verify_signature(inter.public_key()).is_ok());
+        assert_eq!(
+            cert.verify_signature(root.public_key()),
+            Err(X509Error::InvalidSignature)
+        );
+        assert!(cert.verify_signature_with_id("", inter.public_key()).is_err());
+
+        let again = Certificate::from_der(cert.der()).unwrap();
+        assert!(again == cert);
+        assert_eq!(again.to_pem(), LEAF);
+    }
+
+    #[test]
+    fn test_pem_bundle() {
+        let bundle = [LEAF, INTER, ROOT].concat();
+        let certs = Certificate::from_pem_bundle(&bundle).unwrap();
+        assert_eq!(certs.len(), 3);
+        assert_eq!(certs[2].subject().common_name(), Some("Example Root CA"));
+        assert_eq!(Certificate::from_pem_bundle("").unwrap().len(), 0);
+        assert_eq!(
+            Certificate::from_pem(REQUEST).err(),
+            Some(X509Error::InvalidPem)
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        let cert = Certificate::from_pem(LEAF).unwrap();
+        let der = cert.der();
+        assert_eq!(
+            Certificate::from_der(&der[..der.len() - 1]).err(),
+            Some(X509Error::InvalidDer)
+        );
+
+        // the serial number changed from 3 to 7
+        let mut bad = der.to_vec();
+        assert_eq!(bad[15], 3);
+        bad[15] = 7;
+        let bad = Certificate::from_der(&bad).unwrap();
+        let inter = Certificate::from_pem(INTER).unwrap();
+        assert_eq!(
+            bad.verify_signature(inter.public_key()),
+            Err(X509Error::InvalidSignature)
+        );
+
+        // the signature algorithm renamed to 1.2.156.10197.1.502
+        let alg = [0x06, 0x08, 0x2a, 0x81, 0x1c, 0xcf, 0x55, 0x01, 0x83, 0x75];
+        let mut other = der.to_vec();
+        let n = other.len();
+        for i in 0..n - alg.len() {
+            if other[i..i + alg.len()] == alg {
+                other[i + alg.len() - 1] = 0x76;
+            }
+        }
+        assert_eq!(
+            Certificate::from_der(&other).err(),
+            Some(X509Error::UnsupportedAlgorithm)
+        );
+
+        let validity = cert.validity();
+        assert!(cert.check(validity.not_before).is_ok());
+        assert!(cert.check(validity.not_after).is_ok());
+        assert_eq!(cert.check(validity.not_before - 1), Err(X509Error::NotYetValid));
+        assert_eq!(cert.check(validity.not_after + 1), Err(X509Error::Expired));
+    }
+}
@@ -0,0 +1,146 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::certificate::Certificate;
+use super::error::X509Error;
+
+/// Verifies a certificate chain at `time`, in seconds since the Unix epoch.
+///
+/// `chain` starts with the end entity, each certificate is followed by its
+/// issuer. The chain may stop below a trust anchor or include it. Every
+/// certificate must be valid at `time` without unknown critical extensions,
+/// every issuer must be a CA allowed to sign certificates and its path
+/// length constraint must hold. Trust anchors are only compared by their
+/// DER, their own signatures are not checked.
+pub fn verify_chain(chain: &[Certificate], anchors: &[Certificate], time: i64) -> Result<(), X509Error> {
+    let mut cert = chain.first().ok_or(X509Error::EmptyChain)?;
+    cert.check(time)?;
+
+    // intermediate certificates below the current issuer, RFC 5280 6.1.4 (l)
+    let mut intermediates = 0;
+    let mut i = 0;
+    loop {
+        if anchors.contains(cert) {
+            return Ok(());
+        }
+        let (issuer, is_anchor) = match chain.get(i + 1) {
+            Some(issuer) => (issuer, false),
+            None => {
+                let issuer = anchors
+                    .iter()
+                    .find(|a| a.subject() == cert.issuer() && cert.verify_signature(a.public_key()).is_ok())
+                    .ok_or(X509Error::UntrustedIssuer)?;
+                (issuer, true)
+            }
+        };
+
+        if issuer.subject() != cert.issuer() {
+            return Err(X509Error::IssuerMismatch);
+        }
+        cert.verify_signature(issuer.public_key())?;
+        issuer.check(time)?;
+        if !issuer.is_ca() {
+            return Err(X509Error::NotCa);
+        }
+        if i > 0 && !cert.is_self_issued() {
+            intermediates += 1;
+        }
+        if let Some(path_len) = issuer.basic_constraints().and_then(|bc| bc.path_len) {
+            if intermediates > path_len {
+                return Err(X509Error::PathLenExceeded);
+            }
+        }
+
+        if is_anchor {
+            return Ok(());
+        }
+        cert = issuer;
+        i += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::*;
+    use x509::testdata::*;
+
+    fn load(pems: &[&str]) -> Vec<Certificate> {
+        pems.iter().map(|pem| Certificate::from_pem(pem).unwrap()).collect()
+    }
+
+    // 2025-10-09
+    const NOW: i64 = 1760000000;
+
+    #[test]
+    fn test_verify_chain() {
+        let anchors = load(&[ROOT]);
+        let chain = load(&[LEAF, INTER]);
+        assert!(verify_chain(&chain, &anchors, NOW).is_ok());
+        // with the anchor at the end
+        assert!(verify_chain(&load(&[LEAF, INTER, ROOT]), &anchors, NOW).is_ok());
+        // the intermediate as an anchor
+        assert!(verify_chain(&load(&[LEAF]), &load(&[INTER]), NOW).is_ok());
+        assert!(verify_chain(&anchors, &anchors, NOW).is_ok());
+
+        assert_eq!(verify_chain(&[], &anchors, NOW), Err(X509Error::EmptyChain));
+        assert_eq!(verify_chain(&chain, &[], NOW), Err(X509Error::UntrustedIssuer));
+        assert_eq!(
+            verify_chain(&load(&[LEAF]), &anchors, NOW),
+            Err(X509Error::UntrustedIssuer)
+        );
+        assert_eq!(
+            verify_chain(&load(&[LEAF, ROOT]), &anchors, NOW),
+            Err(X509Error::IssuerMismatch)
+        );
+    }
+
+    #[test]
+    fn test_validity() {
+        let anchors = load(&[ROOT]);
+        let chain = load(&[LEAF, INTER]);
+        // the leaf covers 2025
+        assert_eq!(
+            verify_chain(&chain, &anchors, 1735689599),
+            Err(X509Error::NotYetValid)
+        );
+        assert_eq!(verify_chain(&chain, &anchors, 1767225601), Err(X509Error::Expired));
+        // the intermediate expires in 2034, before the root
+        let inter = load(&[INTER]);
+        assert!(verify_chain(&inter, &anchors, 2019686400).is_ok());
+        assert_eq!(
+            verify_chain(&inter, &anchors, 2019686401),
+            Err(X509Error::Expired)
+        );
+    }
+
+    #[test]
+    fn test_constraints() {
+        let anchors = load(&[ROOT]);
+        // a CA below the intermediate, whose path length is 0
+        assert!(verify_chain(&load(&[SUB, INTER]), &anchors, NOW).is_ok());
+        assert_eq!(
+            verify_chain(&load(&[DEEP, SUB, INTER]), &anchors, NOW),
+            Err(X509Error::PathLenExceeded)
+        );
+        // the sub CA trusted directly
+        assert!(verify_chain(&load(&[DEEP]), &load(&[SUB]), NOW).is_ok());
+
+        // a certificate issued by an end entity
+        assert_eq!(
+            verify_chain(&load(&[ROGUE, LEAF, INTER]), &anchors, NOW),
+            Err(X509Error::NotCa)
+        );
+    }
+}
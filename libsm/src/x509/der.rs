@@ -0,0 +1,156 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! ASN.1 pieces shared by certificates and requests, on top of the SPKI
+//! parsing shared with the SM2 key files.
+
+use yasna::tags::{TAG_GENERALIZEDTIME, TAG_UTCTIME};
+use yasna::{ASN1Result, BERReader};
+
+use super::error::X509Error;
+pub(crate) use asn1::{invalid, read_spki, Algorithm};
+use asn1::oid;
+use sm2::ecc::EccCtx;
+use sm2::signature::{Pubkey, Signature};
+
+// 1.2.156.10197.1.501
+const OID_SM2_WITH_SM3: &[u64] = &[1, 2, 156, 10197, 1, 501];
+
+const NULL: &[u8] = &[0x05, 0x00];
+
+/// SM2-with-SM3, the parameters are absent or NULL.
+pub(crate) fn check_sm2_with_sm3(algorithm: &Algorithm) -> Result<(), X509Error> {
+    let no_params = match algorithm.params {
+        None => true,
+        Some(ref params) => params.as_slice() == NULL,
+    };
+    if algorithm.oid == oid(OID_SM2_WITH_SM3) && no_params {
+        Ok(())
+    } else {
+        Err(X509Error::UnsupportedAlgorithm)
+    }
+}
+
+/// The SM2 public key of a SubjectPublicKeyInfo, id-ecPublicKey with the SM2
+/// curve as parameters.
+pub(crate) fn sm2_public_key(algorithm: &Algorithm, point: &[u8]) -> Result<Pubkey, X509Error> {
+    if !algorithm.is_sm2_public_key() {
+        return Err(X509Error::UnsupportedAlgorithm);
+    }
+    EccCtx::new()
+        .bytes_to_point(point)
+        .map_err(|_| X509Error::InvalidPublic)
+}
+
+/// The signature of a BIT STRING, whose content is the DER of (r, s).
+pub(crate) fn sm2_signature(value: &[u8], bits: usize) -> Result<Signature, X509Error> {
+    if bits != value.len() * 8 {
+        return Err(X509Error::InvalidSignature);
+    }
+    Signature::der_decode(value).map_err(|_| X509Error::InvalidSignature)
+}
+
+/// UTCTime or GeneralizedTime as seconds since the Unix epoch. Only the
+/// forms allowed by RFC 5280 are accepted: YYMMDDHHMMSSZ and
+/// YYYYMMDDHHMMSSZ.
+pub(crate) fn read_time(reader: BERReader) -> ASN1Result<i64> {
+    let time = reader.read_tagged_der()?;
+    let value = time.value();
+    let (year, rest) = if time.tag() == TAG_UTCTIME && value.len() == 13 {
+        let year = digits(&value[..2])?;
+        // RFC 5280 4.1.2.5.1
+        (if year >= 50 { 1900 + year } else { 2000 + year }, &value[2..])
+    } else if time.tag() == TAG_GENERALIZEDTIME && value.len() == 15 {
+        (digits(&value[..4])?, &value[4..])
+    } else {
+        return Err(invalid());
+    };
+    if rest[10] != b'Z' {
+        return Err(invalid());
+    }
+
+    let month = digits(&rest[0..2])?;
+    let day = digits(&rest[2..4])?;
+    let hour = digits(&rest[4..6])?;
+    let minute = digits(&rest[6..8])?;
+    let second = digits(&rest[8..10])?;
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return Err(invalid());
+    }
+    if hour > 23 || minute > 59 || second > 59 {
+        return Err(invalid());
+    }
+    Ok(days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+fn digits(s: &[u8]) -> ASN1Result<i64> {
+    s.iter().try_fold(0, |acc, c| {
+        if c.is_ascii_digit() {
+            Ok(acc * 10 + i64::from(c - b'0'))
+        } else {
+            Err(invalid())
+        }
+    })
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// days since 1970-01-01 of a date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yasna::models::TaggedDerValue;
+    use yasna::Tag;
+
+    fn time(tag: Tag, s: &str) -> ASN1Result<i64> {
+        let der = yasna::construct_der(|writer| {
+            writer.write_tagged_der(&TaggedDerValue::from_tag_and_bytes(tag, s.as_bytes().to_vec()))
+        });
+        yasna::parse_der(&der, |reader| read_time(reader))
+    }
+
+    #[test]
+    fn test_time() {
+        assert_eq!(time(TAG_UTCTIME, "700101000000Z").unwrap(), 0);
+        assert_eq!(time(TAG_UTCTIME, "491231235959Z").unwrap(), 2524607999);
+        assert_eq!(time(TAG_UTCTIME, "500101000000Z").unwrap(), -631152000);
+        assert_eq!(time(TAG_UTCTIME, "240229120000Z").unwrap(), 1709208000);
+        assert_eq!(time(TAG_GENERALIZEDTIME, "20500101000000Z").unwrap(), 2524608000);
+        assert_eq!(time(TAG_GENERALIZEDTIME, "19691231235959Z").unwrap(), -1);
+
+        assert!(time(TAG_UTCTIME, "230229120000Z").is_err());
+        assert!(time(TAG_UTCTIME, "240101000000+0800").is_err());
+        assert!(time(TAG_UTCTIME, "2401010000Z").is_err());
+        assert!(time(TAG_UTCTIME, "20240101000000Z").is_err());
+        assert!(time(TAG_GENERALIZEDTIME, "20240101000000.5Z").is_err());
+        assert!(time(TAG_GENERALIZEDTIME, "20241301000000Z").is_err());
+    }
+}
@@ -0,0 +1,59 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum X509Error {
+    InvalidDer,
+    InvalidPem,
+    UnsupportedAlgorithm,
+    InvalidPublic,
+    InvalidSignature,
+    UnsupportedExtension,
+    NotYetValid,
+    Expired,
+    IssuerMismatch,
+    NotCa,
+    PathLenExceeded,
+    UntrustedIssuer,
+    EmptyChain,
+}
+
+impl From<X509Error> for &str {
+    fn from(e: X509Error) -> Self {
+        match e {
+            X509Error::InvalidDer => "invalid der",
+            X509Error::InvalidPem => "invalid pem",
+            X509Error::UnsupportedAlgorithm => "unsupported algorithm",
+            X509Error::InvalidPublic => "invalid public key",
+            X509Error::InvalidSignature => "invalid signature",
+            X509Error::UnsupportedExtension => "unsupported critical extension",
+            X509Error::NotYetValid => "certificate not yet valid",
+            X509Error::Expired => "certificate expired",
+            X509Error::IssuerMismatch => "issuer does not match",
+            X509Error::NotCa => "issuer is not a ca",
+            X509Error::PathLenExceeded => "path length constraint exceeded",
+            X509Error::UntrustedIssuer => "no trusted issuer",
+            X509Error::EmptyChain => "empty certificate chain",
+        }
+    }
+}
+
+impl core::fmt::Display for X509Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str((*self).into())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for X509Error {}
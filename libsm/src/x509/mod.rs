@@ -0,0 +1,31 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! X.509 v3 certificates and PKCS#10 requests with SM2 keys and SM2-with-SM3
+//! signatures (GM/T 0015), and the verification of certificate chains.
+
+pub mod certificate;
+pub mod chain;
+mod der;
+pub mod error;
+pub mod name;
+pub mod request;
+#[cfg(test)]
+mod testdata;
+
+pub use self::certificate::{BasicConstraints, Certificate, Validity};
+pub use self::chain::verify_chain;
+pub use self::error::X509Error;
+pub use self::name::Name;
+pub use self::request::CertificateRequest;
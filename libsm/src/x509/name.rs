@@ -0,0 +1,100 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::string::*;
+use alloc::vec::*;
+use yasna;
+use yasna::models::ObjectIdentifier;
+use yasna::tags::*;
+use yasna::ASN1Result;
+
+pub const COUNTRY: &[u64] = &[2, 5, 4, 6];
+pub const ORGANIZATION: &[u64] = &[2, 5, 4, 10];
+pub const ORGANIZATIONAL_UNIT: &[u64] = &[2, 5, 4, 11];
+pub const COMMON_NAME: &[u64] = &[2, 5, 4, 3];
+
+/// A distinguished name. Names are compared by their DER, the string
+/// attributes are decoded for display.
+#[derive(Clone, Debug)]
+pub struct Name {
+    der: Vec<u8>,
+    attributes: Vec<(ObjectIdentifier, String)>,
+}
+
+impl Name {
+    pub(crate) fn from_der(der: Vec<u8>) -> ASN1Result<Name> {
+        let mut attributes = Vec::new();
+        yasna::parse_der(&der, |reader| {
+            reader.read_sequence_of(|reader| {
+                reader.read_set_of(|reader| {
+                    reader.read_sequence(|reader| {
+                        let oid = reader.next().read_oid()?;
+                        let value = reader.next().read_tagged_der()?;
+                        if let Some(value) = decode_string(value.tag(), value.value()) {
+                            attributes.push((oid, value));
+                        }
+                        Ok(())
+                    })
+                })
+            })
+        })?;
+        Ok(Name { der, attributes })
+    }
+
+    #[inline]
+    pub fn der(&self) -> &[u8] {
+        &self.der
+    }
+
+    /// The attributes with a string value, in order.
+    #[inline]
+    pub fn attributes(&self) -> &[(ObjectIdentifier, String)] {
+        &self.attributes
+    }
+
+    /// The first attribute of type `oid`, e.g. `name::COMMON_NAME`.
+    pub fn get(&self, oid: &[u64]) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(o, _)| o.components().as_slice() == oid)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn common_name(&self) -> Option<&str> {
+        self.get(COMMON_NAME)
+    }
+}
+
+impl PartialEq for Name {
+    fn eq(&self, other: &Name) -> bool {
+        self.der == other.der
+    }
+}
+
+impl Eq for Name {}
+
+fn decode_string(tag: yasna::Tag, value: &[u8]) -> Option<String> {
+    if tag == TAG_UTF8STRING
+        || tag == TAG_PRINTABLESTRING
+        || tag == TAG_IA5STRING
+        || tag == TAG_TELETEXSTRING
+    {
+        String::from_utf8(value.to_vec()).ok()
+    } else if tag == TAG_BMPSTRING && value.len().is_multiple_of(2) {
+        let units = value.chunks(2).map(|c| u16::from_be_bytes([c[0], c[1]]));
+        core::char::decode_utf16(units).collect::<Result<String, _>>().ok()
+    } else {
+        None
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::string::*;
+use alloc::vec::*;
+use yasna;
+use yasna::Tag;
+
+use super::der::*;
+use super::error::X509Error;
+use super::name::Name;
+use sm2::keys::{pem_decode, pem_encode};
+use sm2::signature::{Pubkey, SigCtx, Signature};
+
+pub const PEM_CERTIFICATE_REQUEST: &str = "CERTIFICATE REQUEST";
+// written by some older tools
+const PEM_NEW_CERTIFICATE_REQUEST: &str = "NEW CERTIFICATE REQUEST";
+
+/// A PKCS#10 certification request with an SM2 key, signed with
+/// SM2-with-SM3. The attributes are not parsed.
+#[derive(Clone)]
+pub struct CertificateRequest {
+    der: Vec<u8>,
+    info: Vec<u8>,
+    subject: Name,
+    public_key: Pubkey,
+    signature: Signature,
+}
+
+impl CertificateRequest {
+    pub fn from_der(der: &[u8]) -> Result<CertificateRequest, X509Error> {
+        let (info, algorithm, (signature, bits)) = yasna::parse_der(der, |reader| {
+            reader.read_sequence(|reader| {
+                let info = reader.next().read_der()?;
+                let algorithm = Algorithm::read(reader.next())?;
+                let signature = reader.next().read_bitvec_bytes()?;
+                Ok((info, algorithm, signature))
+            })
+        })
+        .map_err(|_| X509Error::InvalidDer)?;
+
+        let (subject, spki) = yasna::parse_der(&info, |reader| {
+            reader.read_sequence(|reader| {
+                if reader.next().read_u8()? != 0 {
+                    return Err(invalid());
+                }
+                let subject = Name::from_der(reader.next().read_der()?)?;
+                let spki = read_spki(reader.next())?;
+                let attributes = reader.next().read_tagged_der()?;
+                if attributes.tag() != Tag::context(0) {
+                    return Err(invalid());
+                }
+                Ok((subject, spki))
+            })
+        })
+        .map_err(|_| X509Error::InvalidDer)?;
+
+        check_sm2_with_sm3(&algorithm)?;
+        let public_key = sm2_public_key(&spki.0, &spki.1)?;
+        let signature = sm2_signature(&signature, bits)?;
+
+        Ok(CertificateRequest {
+            der: der.to_vec(),
+            info,
+            subject,
+            public_key,
+            signature,
+        })
+    }
+
+    pub fn from_pem(pem: &str) -> Result<CertificateRequest, X509Error> {
+        match pem_decode(pem).map_err(|_| X509Error::InvalidPem)? {
+            (ref label, ref der)
+                if label == PEM_CERTIFICATE_REQUEST || label == PEM_NEW_CERTIFICATE_REQUEST =>
+            {
+                CertificateRequest::from_der(der)
+            }
+            _ => Err(X509Error::InvalidPem),
+        }
+    }
+
+    #[inline]
+    pub fn der(&self) -> &[u8] {
+        &self.der
+    }
+
+    pub fn to_pem(&self) -> String {
+        pem_encode(PEM_CERTIFICATE_REQUEST, &self.der)
+    }
+
+    #[inline]
+    pub fn subject(&self) -> &Name {
+        &self.subject
+    }
+
+    #[inline]
+    pub fn public_key(&self) -> &Pubkey {
+        &self.public_key
+    }
+
+    #[inline]
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    /// Verifies that the request is signed by the key it carries, with the
+    /// default ID.
+    pub fn verify(&self) -> Result<(), X509Error> {
+        if SigCtx::new().verify(&self.info, &self.public_key, &self.signature) {
+            Ok(())
+        } else {
+            Err(X509Error::InvalidSignature)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sm2::ecc::EccCtx;
+    use x509::certificate::Certificate;
+    use x509::testdata::*;
+
+    #[test]
+    fn test_request() {
+        let req = CertificateRequest::from_pem(REQUEST).unwrap();
+        assert!(req.verify().is_ok());
+        assert_eq!(req.to_pem(), REQUEST);
+
+        // the leaf was issued for this request
+        let cert = Certificate::from_pem(LEAF).unwrap();
+        assert_eq!(req.subject(), cert.subject());
+        assert!(EccCtx::new().eq(req.public_key(), cert.public_key()));
+
+        // the last byte of the subject
+        let mut der = req.der().to_vec();
+        der[69] ^= 1;
+        let bad = CertificateRequest::from_der(&der).unwrap();
+        assert_eq!(bad.verify(), Err(X509Error::InvalidSignature));
+
+        assert_eq!(
+            CertificateRequest::from_pem(LEAF).err(),
+            Some(X509Error::InvalidPem)
+        );
+        assert_eq!(
+            CertificateRequest::from_der(cert.der()).err(),
+            Some(X509Error::InvalidDer)
+        );
+    }
+}
@@ -0,0 +1,108 @@
+// Copyright 2018 Cryptape Technology LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Made with OpenSSL 3.5 and `-sigopt distid:1234567812345678`: a root CA, an
+//! issuing CA limited to a path length of 0 and a leaf with its request,
+//! plus a CA below the issuing CA, a leaf of that CA and a certificate
+//! issued by the first leaf.
+
+pub const ROOT: &str = "\
+-----BEGIN CERTIFICATE-----\n\
+MIIBojCCAUmgAwIBAgIBATAKBggqgRzPVQGDdTA5MQswCQYDVQQGEwJDTjEQMA4G\n\
+A1UECgwHRXhhbXBsZTEYMBYGA1UEAwwPRXhhbXBsZSBSb290IENBMB4XDTI0MDEw\n\
+MTAwMDAwMFoXDTQ0MDEwMTAwMDAwMFowOTELMAkGA1UEBhMCQ04xEDAOBgNVBAoM\n\
+B0V4YW1wbGUxGDAWBgNVBAMMD0V4YW1wbGUgUm9vdCBDQTBZMBMGByqGSM49AgEG\n\
+CCqBHM9VAYItA0IABKKcAcfPzwl/TLEo02rKL2/ua6QSp2Sjm3LKQ3xH2u6nIJkT\n\
+Tcj2qhu9ZzA8HBORTICvCqrnmeymG3GaF5nJb4SjQjBAMA8GA1UdEwEB/wQFMAMB\n\
+Af8wDgYDVR0PAQH/BAQDAgEGMB0GA1UdDgQWBBTZZLInHu0EB8k4EVZIVgcs6mo/\n\
+YzAKBggqgRzPVQGDdQNHADBEAiByXLeaYN0YMmb3SDvjzZ+ooDfjIBlduuRvU55y\n\
+YmXnEAIgI/0Ti6x+2WFffz0KJ7BNtQjiuuN0FKYiSZTpuR0e22M=\n\
+-----END CERTIFICATE-----\n";
+
+pub const INTER: &str = "\
+-----BEGIN CERTIFICATE-----\n\
+MIIByjCCAXCgAwIBAgIBAjAKBggqgRzPVQGDdTA5MQswCQYDVQQGEwJDTjEQMA4G\n\
+A1UECgwHRXhhbXBsZTEYMBYGA1UEAwwPRXhhbXBsZSBSb290IENBMB4XDTI0MDEw\n\
+MTAwMDAwMFoXDTM0MDEwMTAwMDAwMFowPDELMAkGA1UEBhMCQ04xEDAOBgNVBAoM\n\
+B0V4YW1wbGUxGzAZBgNVBAMMEkV4YW1wbGUgSXNzdWluZyBDQTBZMBMGByqGSM49\n\
+AgEGCCqBHM9VAYItA0IABDxV4sHpl5DDQ4zyf2Zio8j4fhcT57oSgh5pBRyM9WjI\n\
+NSdaRVjqlfcg52b/AnXWV+fCu7Hi9emCb8BN9KtjNxKjZjBkMBIGA1UdEwEB/wQI\n\
+MAYBAf8CAQAwDgYDVR0PAQH/BAQDAgEGMB0GA1UdDgQWBBRviEk3HMDSzP68ca6V\n\
+sEK+V8qYSjAfBgNVHSMEGDAWgBTZZLInHu0EB8k4EVZIVgcs6mo/YzAKBggqgRzP\n\
+VQGDdQNIADBFAiANpyrFbVFfEvZZn8dDi1af5tJL7g7bRTBiZUhyMR0eiwIhAJQH\n\
+05igoWsj/C0JCBcTaX8A1uNc7pUYuQGznd2FFGsa\n\
+-----END CERTIFICATE-----\n";
+
+pub const LEAF: &str = "\
+-----BEGIN CERTIFICATE-----\n\
+MIIB5TCCAYqgAwIBAgIBAzAKBggqgRzPVQGDdTA8MQswCQYDVQQGEwJDTjEQMA4G\n\
+A1UECgwHRXhhbXBsZTEbMBkGA1UEAwwSRXhhbXBsZSBJc3N1aW5nIENBMB4XDTI1\n\
+MDEwMTAwMDAwMFoXDTI2MDEwMTAwMDAwMFowOzELMAkGA1UEBhMCQ04xEDAOBgNV\n\
+BAoMB0V4YW1wbGUxGjAYBgNVBAMMEW5vZGUxLmV4YW1wbGUub3JnMFkwEwYHKoZI\n\
+zj0CAQYIKoEcz1UBgi0DQgAETpJ5TSmZH5EmmHtCCCy/Z1nFsf00Vis2ypT2mZa9\n\
+kra+HZNK4Um4aarUR44J0GzfTJjTDnWgNzLG+hg+IJmubKN+MHwwDAYDVR0TAQH/\n\
+BAIwADAOBgNVHQ8BAf8EBAMCB4AwHAYDVR0RBBUwE4IRbm9kZTEuZXhhbXBsZS5v\n\
+cmcwHwYDVR0jBBgwFoAUb4hJNxzA0sz+vHGulbBCvlfKmEowHQYDVR0OBBYEFHVH\n\
+i5HdOSBG1UTfMysH98cub3LcMAoGCCqBHM9VAYN1A0kAMEYCIQCjvW8AJ0Ufx2RT\n\
+vhS0Xed9qG7wX8fGybrE/XBRXy1RZgIhAKaTIqGHpO/VH50zromDBn2gYtKS0MTM\n\
+lni6eqYcxM4o\n\
+-----END CERTIFICATE-----\n";
+
+pub const SUB: &str = "\
+-----BEGIN CERTIFICATE-----\n\
+MIIBnjCCAUWgAwIBAgIBBDAKBggqgRzPVQGDdTA8MQswCQYDVQQGEwJDTjEQMA4G\n\
+A1UECgwHRXhhbXBsZTEbMBkGA1UEAwwSRXhhbXBsZSBJc3N1aW5nIENBMB4XDTI1\n\
+MDEwMTAwMDAwMFoXDTI2MDEwMTAwMDAwMFowETEPMA0GA1UEAwwGU3ViIENBMFkw\n\
+EwYHKoZIzj0CAQYIKoEcz1UBgi0DQgAEQ4zc9bXtbn/DS8QHMBYNQv86wNV1Ito+\n\
+8FtGousq1sMjLaBr2M9RnUUAOJf/afqTYuXr3fCMv80ZKP8Um/JCW6NjMGEwDwYD\n\
+VR0TAQH/BAUwAwEB/zAOBgNVHQ8BAf8EBAMCAgQwHQYDVR0OBBYEFL9Bwv5OGkwS\n\
+2svdImEq/bcjDKIPMB8GA1UdIwQYMBaAFG+ISTccwNLM/rxxrpWwQr5XyphKMAoG\n\
+CCqBHM9VAYN1A0cAMEQCIAHNxS/tJ/62VZzOZLQwXMNmOeH4S/a0l/c1HWkABz7x\n\
+AiAyEWzWnEJA7JIA2d5cbshmIbEtHOEHyg/6cTcDrFnu7Q==\n\
+-----END CERTIFICATE-----\n";
+
+pub const DEEP: &str = "\
+-----BEGIN CERTIFICATE-----\n\
+MIIBXjCCAQWgAwIBAgIBBTAKBggqgRzPVQGDdTARMQ8wDQYDVQQDDAZTdWIgQ0Ew\n\
+HhcNMjUwMTAxMDAwMDAwWhcNMjYwMTAxMDAwMDAwWjAPMQ0wCwYDVQQDDARkZWVw\n\
+MFkwEwYHKoZIzj0CAQYIKoEcz1UBgi0DQgAEF9dGnnH0T0og4mVVhFPRtMCoDOQ/\n\
++zNfZZj76q2V/eHkv35c1f/dBqQ8WTRvbfLdlF6+WjX3fMHw8RKjmQBRMqNQME4w\n\
+DAYDVR0TAQH/BAIwADAdBgNVHQ4EFgQUuWJ/D9assEJ19Dgj/tCKZC34jL0wHwYD\n\
+VR0jBBgwFoAUv0HC/k4aTBLay90iYSr9tyMMog8wCgYIKoEcz1UBg3UDRwAwRAIg\n\
+KBliPqliYAZ64VyroQDFPBUmd6SA+EI6Wj5QSBliTYwCICtALJk60VEPaZQZqCCx\n\
+UhKyjDhsZ19KmiMXBBkNfgwR\n\
+-----END CERTIFICATE-----\n";
+
+pub const ROGUE: &str = "\
+-----BEGIN CERTIFICATE-----\n\
+MIIBizCCATCgAwIBAgIBBjAKBggqgRzPVQGDdTA7MQswCQYDVQQGEwJDTjEQMA4G\n\
+A1UECgwHRXhhbXBsZTEaMBgGA1UEAwwRbm9kZTEuZXhhbXBsZS5vcmcwHhcNMjUw\n\
+MTAxMDAwMDAwWhcNMjYwMTAxMDAwMDAwWjAQMQ4wDAYDVQQDDAVyb2d1ZTBZMBMG\n\
+ByqGSM49AgEGCCqBHM9VAYItA0IABLXNsJIa8bqZ+yjUC3O+dxdFL6ZDEVEs9E6R\n\
+qAkFzIFfh/twvNDKDiUtvUTvnDFnYCQw44zJeocEA8iN384LZP+jUDBOMAwGA1Ud\n\
+EwEB/wQCMAAwHQYDVR0OBBYEFEQ9K00yFdvIq3t44AvX3OmWLbMJMB8GA1UdIwQY\n\
+MBaAFHVHi5HdOSBG1UTfMysH98cub3LcMAoGCCqBHM9VAYN1A0kAMEYCIQCFdWtJ\n\
+To7tf4YeXR3UBKOwMxr1z+jOChA3W03pnw8fmgIhALxksc9HEtS7BlCOHggCmtop\n\
+lSKKoUgofwxcNupbk4QL\n\
+-----END CERTIFICATE-----\n";
+
+pub const REQUEST: &str = "\
+-----BEGIN CERTIFICATE REQUEST-----\n\
+MIH2MIGdAgEAMDsxCzAJBgNVBAYTAkNOMRAwDgYDVQQKDAdFeGFtcGxlMRowGAYD\n\
+VQQDDBFub2RlMS5leGFtcGxlLm9yZzBZMBMGByqGSM49AgEGCCqBHM9VAYItA0IA\n\
+BE6SeU0pmR+RJph7Qggsv2dZxbH9NFYrNsqU9pmWvZK2vh2TSuFJuGmq1EeOCdBs\n\
+30yY0w51oDcyxvoYPiCZrmygADAKBggqgRzPVQGDdQNIADBFAiEA/BdotnIqET76\n\
+vufs36LMI4m1CP0udtztVSTR0CEDNWECIBbUBC3YLDN80E5WQ8NSp931RLPvKmiZ\n\
+0HPUHBoqzAoH\n\
+-----END CERTIFICATE REQUEST-----\n";
@@ -12,6 +12,7 @@ use std::ptr::null;
 use libsm::sm2;
 use libsm::sm2::signature::Signature;
 use libsm::sm3::hash;
+use libsm::sm3::kdf;
 use num_bigint::BigUint;
 
 mod utils;
@@ -98,21 +99,16 @@ impl FromHex for Vec<u8> {
 
 impl AsHex for Signature {
     fn to_hex(&self) -> String {
-        let mut ret: Vec<u8> = vec![0; 64];
-        let r = self.r_bytes();
-        let s = self.s_bytes();
-        &ret[(32 - r.len())..32].copy_from_slice(&r);
-        &ret[(64 - s.len())..].copy_from_slice(&s);
-        to_hex(&ret)
+        to_hex(&self.to_bytes())
     }
 }
 
 impl FromHex for Signature {
     fn from_hex(hex: &str) -> Self {
-        let rs = decode_hex(hex);
-        let r = &rs[..32];
-        let s = &rs[32..];
-        Signature::new(r, s)
+        match Signature::from_bytes(&decode_hex(hex)) {
+            Ok(s) => s,
+            Err(e) => panic!("{}", <&str>::from(e)),
+        }
     }
 }
 
@@ -171,6 +167,42 @@ pub fn sm3(x: String) -> String {
     to_hex(&h)
 }
 
+// hmac-sm3 of data under key
+#[wasm_bindgen]
+pub fn hmac_sm3(key: String, data: String) -> String {
+    // @pure
+    to_hex(&libsm::sm3::hmac::hmac_sm3(&decode_hex(&key), &decode_hex(&data)))
+}
+
+// hkdf-sm3, len is at most 255 * 32 bytes
+#[wasm_bindgen]
+pub fn hkdf_sm3(salt: String, ikm: String, info: String, len: usize) -> String {
+    // @pure
+    let mut okm = vec![0u8; len];
+    match kdf::hkdf(&decode_hex(&salt), &decode_hex(&ikm), &decode_hex(&info), &mut okm) {
+        Ok(()) => to_hex(&okm),
+        Err(e) => panic!("{}", <&str>::from(e)),
+    }
+}
+
+// pbkdf2 with hmac-sm3, iterations is in [1, PBKDF2_MAX_ITERATIONS]
+#[wasm_bindgen]
+pub fn pbkdf2_hmac_sm3(password: String, salt: String, iterations: u32, len: usize) -> String {
+    // @pure
+    let mut key = vec![0u8; len];
+    match kdf::pbkdf2_hmac_sm3_checked(&decode_hex(&password), &decode_hex(&salt), iterations, &mut key) {
+        Ok(()) => to_hex(&key),
+        Err(e) => panic!("{}", <&str>::from(e)),
+    }
+}
+
+// kdf of sm2 encryption and key exchange
+#[wasm_bindgen]
+pub fn sm2_kdf(z: String, len: usize) -> String {
+    // @pure
+    to_hex(&kdf::kdf(&decode_hex(&z), len))
+}
+
 // convert private key to public key
 #[wasm_bindgen]
 pub fn sm2_pk_from_sk(private_key: String, compress: bool) -> String {
@@ -183,28 +215,96 @@ pub fn sm2_pk_from_sk(private_key: String, compress: bool) -> String {
 }
 
 // sm2 sign algorithm
+// deprecated: signatures are deterministic, `_seed` is ignored and only kept
+// so existing callers don't break
 #[wasm_bindgen]
-pub fn sm2_sign(seed: u64, private_key: String, message: String) -> String {
+pub fn sm2_sign(_seed: u64, private_key: String, message: String) -> String {
     // @pure
-    libsm::seed(seed);
     let c = sm2::signature::SigCtx::new();
     let sk = BigUint::from_bytes_be(&decode_hex(&private_key));
     c.sign(&decode_hex(&message), &sk, &c.pk_from_sk(&sk)).to_hex()
 }
 
 // sm2 verify
+// deprecated: `_seed` is ignored and only kept so existing callers don't break
 #[wasm_bindgen]
-pub fn sm2_verify(seed: u64, message: String, public_key: String, sig: String) -> bool {
+pub fn sm2_verify(_seed: u64, message: String, public_key: String, sig: String) -> bool {
     // @pure
-    libsm::seed(seed);
     let sig_ctx = sm2::signature::SigCtx::new();
-    let s: Signature = Signature::from_hex(&sig);
+    let s = match Signature::from_bytes(&decode_hex(&sig)) {
+        Ok(s) => s,
+        Err(_) => return false,
+    };
     let ecc_ctx = sm2::ecc::EccCtx::new();
     let decoded = decode_hex(&public_key);
     let pk = ecc_ctx.bytes_to_point(&decoded).unwrap();
     sig_ctx.verify(&decode_hex(&message), &pk, &s)
 }
 
+// sm2 sign with a distinguishing identifier instead of the default one
+#[wasm_bindgen]
+pub fn sm2_sign_with_id(id: String, private_key: String, message: String) -> String {
+    // @pure
+    let c = sm2::signature::SigCtx::new();
+    let sk = BigUint::from_bytes_be(&decode_hex(&private_key));
+    let pk = match c.pk_from_sk_checked(&sk) {
+        Ok(p) => p,
+        Err(e) => panic!("{}", <&str>::from(e)),
+    };
+    c.sign_with_id(&id, &decode_hex(&message), &sk, &pk).to_hex()
+}
+
+// sm2 verify with a distinguishing identifier
+#[wasm_bindgen]
+pub fn sm2_verify_with_id(id: String, message: String, public_key: String, sig: String) -> bool {
+    // @pure
+    let sig_ctx = sm2::signature::SigCtx::new();
+    let s = match Signature::from_bytes(&decode_hex(&sig)) {
+        Ok(s) => s,
+        Err(_) => return false,
+    };
+    let ecc_ctx = sm2::ecc::EccCtx::new();
+    let decoded = decode_hex(&public_key);
+    let pk = match ecc_ctx.bytes_to_point(&decoded) {
+        Ok(p) => p,
+        Err(_) => return false,
+    };
+    sig_ctx.verify_with_id(&id, &decode_hex(&message), &pk, &s)
+}
+
+// sm2 sign a digest, returns r || s || recovery id
+#[wasm_bindgen]
+pub fn sm2_sign_recoverable(private_key: String, digest: String) -> String {
+    // @pure
+    let c = sm2::signature::SigCtx::new();
+    let sk = BigUint::from_bytes_be(&decode_hex(&private_key));
+    let (sig, recid) = match c.sign_recoverable_checked(&decode_hex(&digest), &sk) {
+        Ok(r) => r,
+        Err(e) => panic!("{}", <&str>::from(e)),
+    };
+    let mut ret = decode_hex(&sig.to_hex());
+    ret.push(recid);
+    to_hex(&ret)
+}
+
+// recover public key from digest and r || s || recovery id
+#[wasm_bindgen]
+pub fn sm2_recover_public_key(digest: String, sig: String, compress: bool) -> String {
+    // @pure
+    let sig_ctx = sm2::signature::SigCtx::new();
+    let ecc_ctx = sm2::ecc::EccCtx::new();
+    let rsv = decode_hex(&sig);
+    assert!(rsv.len() == 65, "sig.len != 65");
+    let s = match Signature::from_bytes(&rsv[..64]) {
+        Ok(s) => s,
+        Err(e) => panic!("{}", <&str>::from(e)),
+    };
+    match sig_ctx.recover_public_key(&decode_hex(&digest), &s, rsv[64]) {
+        Ok(p) => to_hex(&ecc_ctx.point_to_bytes(&p, compress)),
+        Err(e) => panic!("{}", <&str>::from(e)),
+    }
+}
+
 #[wasm_bindgen]
 pub fn mlsag_generate_decoys(seed: u64, count: i32) -> Vec<JsValue> {
     assert!(count >= 0, "count should >= 0");
@@ -278,4 +378,47 @@ mod tests {
         println!("sm2 verfy");
         super::sm2_verify(128, "0xff".into(), pk, sig);
     }
+
+    #[test]
+    fn test_with_id() {
+        let sk = "0xd2ea6fe0a7b0f8e520c418dca23b01f9b2f451c58fa3ed2bea55237fa451f7fd";
+        let id = String::from("ALICE123@YAHOO.COM");
+        let sig = super::sm2_sign_with_id(id.clone(), sk.into(), "0xff".into());
+        let pk = super::sm2_pk_from_sk(sk.into(), true);
+        assert!(super::sm2_verify_with_id(id.clone(), "0xff".into(), pk.clone(), sig.clone()));
+        assert!(!super::sm2_verify_with_id(id, "0xff".into(), "0x02ff".into(), sig.clone()));
+        assert!(!super::sm2_verify(0, "0xff".into(), pk, sig));
+    }
+
+    #[test]
+    fn test_recover() {
+        let sk = "0xd2ea6fe0a7b0f8e520c418dca23b01f9b2f451c58fa3ed2bea55237fa451f7fd";
+        let digest = super::sm3("0xff".into());
+        let sig = super::sm2_sign_recoverable(sk.into(), digest.clone());
+        let pk = super::sm2_pk_from_sk(sk.into(), true);
+        assert_eq!(super::sm2_recover_public_key(digest, sig, true), pk);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid private key")]
+    fn test_sign_recoverable_zero_key() {
+        super::sm2_sign_recoverable("0x00".into(), super::sm3("0xff".into()));
+    }
+
+    #[test]
+    fn test_kdf() {
+        let mac = super::hmac_sm3(super::to_hex(b"Jefe"), super::to_hex(b"what do ya want for nothing?"));
+        assert_eq!(mac, "0x2e87f1d16862e6d964b50a5200bf2b10b764faa9680a296a2405f24bec39f882");
+        let okm = super::hkdf_sm3("0x".into(), "0x0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b".into(), "0x".into(), 42);
+        assert_eq!(okm, "0xc8c91a38ae2fb3b023a7c38ce9f0748f28230d59b6b950ba3ba949bf0d713a5774815778801741cb2034");
+        let key = super::pbkdf2_hmac_sm3(super::to_hex(b"password"), super::to_hex(b"salt"), 2, 32);
+        assert_eq!(key, "0xfee723a2bc966e11dffb66133f4e8df577383c78ade30e3298edbd3e54ed85b7");
+        assert_eq!(super::sm2_kdf("0x616263".into(), 40).len(), 2 + 80);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid iteration count")]
+    fn test_pbkdf2_iterations() {
+        super::pbkdf2_hmac_sm3("0x".into(), "0x".into(), 10_000_001, 32);
+    }
 }
\ No newline at end of file
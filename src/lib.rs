@@ -37,14 +37,17 @@ pub fn sm2_pk_from_sk(private_key: Bytes32, compress: bool) -> &'static Vec<u8>
 }
 
 // sm2 verify
+// deprecated: `_seed` is ignored and only kept so existing callers don't break
 #[no_mangle]
-pub fn sm2_verify(seed: u64, message: Vec<u8>, public_key: Vec<u8>, sig: Vec<u8>) -> bool {
+pub fn sm2_verify(_seed: u64, message: Vec<u8>, public_key: Vec<u8>, sig: Vec<u8>) -> bool {
     // @pure
-    libsm::seed(seed);
     log("1");
     let sig_ctx = sm2::signature::SigCtx::new();
     log("2");
-    let s: Signature = Signature::new(&sig[..32], &sig[32..]);
+    let s = match Signature::from_bytes(&sig) {
+        Ok(s) => s,
+        Err(_) => return false,
+    };
     log("3");
     let ecc_ctx = sm2::ecc::EccCtx::new();
     log("4");
@@ -0,0 +1,670 @@
+#![cfg_attr(target_arch = "wasm32", no_std)]
+#[macro_use]
+extern crate alloc;
+pub extern crate rust_wbi;
+
+use alloc::string::*;
+use alloc::vec::Vec;
+use rust_wbi::{address::Address, context, db, event, revert, u256::U256};
+
+const NAME_KEY: &[u8] = b"__nft_name";
+const SYMBOL_KEY: &[u8] = b"__nft_symbol";
+const BASE_URI_KEY: &[u8] = b"__nft_base_uri";
+const URI_PREFIX: &[u8] = b"__nft_uri";
+const OWNER_PREFIX: &[u8] = b"__nft_owner";
+const BALANCE_PREFIX: &[u8] = b"__nft_balance";
+const APPROVED_PREFIX: &[u8] = b"__nft_approved";
+const OPERATOR_PREFIX: &[u8] = b"__nft_operator";
+const ALL_LEN_KEY: &[u8] = b"__nft_all_len";
+const ALL_PREFIX: &[u8] = b"__nft_all";
+const ALL_INDEX_PREFIX: &[u8] = b"__nft_all_index";
+const OWNED_PREFIX: &[u8] = b"__nft_owned";
+const OWNED_INDEX_PREFIX: &[u8] = b"__nft_owned_index";
+
+pub const TRANSFER_EVENT: &str = "Transfer";
+pub const APPROVAL_EVENT: &str = "Approval";
+pub const APPROVAL_FOR_ALL_EVENT: &str = "ApprovalForAll";
+
+pub trait Hooks {
+    /// called before every ownership change, `from` is zero address when minting, `to` is zero address when burning
+    fn before_token_transfer(&self, _from: &Address, _to: &Address, _id: &U256) {}
+
+    /// ask `to` whether it accepts a safe transfer, e.g. by calling `onERC721Received` of a contract,
+    /// the transfer is reverted when false is returned
+    fn on_received(&self, _operator: &Address, _from: &Address, _to: &Address, _id: &U256, _data: &[u8]) -> bool {
+        true
+    }
+}
+
+pub struct NoHooks;
+
+impl Hooks for NoHooks {}
+
+/// erc-721 style non-fungible token with enumerable and metadata extensions, state is stored in `wbi::db`
+pub struct Nft<H: Hooks = NoHooks> {
+    hooks: H,
+}
+
+impl Nft<NoHooks> {
+    pub const fn new() -> Nft<NoHooks> {
+        Nft { hooks: NoHooks }
+    }
+}
+
+impl Default for Nft<NoHooks> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn get_u256(k: &[u8]) -> U256 {
+    match db::get(k) {
+        Some(v) => U256::from_bytes(&v),
+        None => U256::zero(),
+    }
+}
+
+fn set_u256(k: &[u8], u: &U256) {
+    db::insert(k, &u.bytes32())
+}
+
+fn get_string(k: &[u8]) -> String {
+    String::from_utf8(db::get(k).unwrap_or_default()).unwrap()
+}
+
+fn is_zero_address(a: &Address) -> bool {
+    *a == Address::default()
+}
+
+impl<H: Hooks> Nft<H> {
+    pub const fn with_hooks(hooks: H) -> Nft<H> {
+        Nft { hooks }
+    }
+
+    /// set collection metadata, usually called in `init`, can only be called once
+    pub fn init(&self, name: &str, symbol: &str, base_uri: &str) {
+        if db::contains_key(NAME_KEY) {
+            revert("nft: already initialized");
+        }
+        db::insert(NAME_KEY, name.as_bytes());
+        db::insert(SYMBOL_KEY, symbol.as_bytes());
+        db::insert(BASE_URI_KEY, base_uri.as_bytes());
+    }
+
+    pub fn name(&self) -> String {
+        get_string(NAME_KEY)
+    }
+
+    pub fn symbol(&self) -> String {
+        get_string(SYMBOL_KEY)
+    }
+
+    /// uri set by `set_token_uri`, otherwise base uri followed by decimal token id
+    pub fn token_uri(&self, id: &U256) -> String {
+        self.require_minted(id);
+        let k = db::key(URI_PREFIX, &[&id.bytes32()]);
+        if db::contains_key(&k) {
+            return get_string(&k);
+        }
+        let base = get_string(BASE_URI_KEY);
+        if base.is_empty() {
+            return base;
+        }
+        format!("{}{}", base, id)
+    }
+
+    pub fn set_token_uri(&self, id: &U256, uri: &str) {
+        self.require_minted(id);
+        db::insert(&db::key(URI_PREFIX, &[&id.bytes32()]), uri.as_bytes());
+    }
+
+    pub fn set_base_uri(&self, base_uri: &str) {
+        db::insert(BASE_URI_KEY, base_uri.as_bytes());
+    }
+
+    pub fn balance_of(&self, owner: &Address) -> U256 {
+        if is_zero_address(owner) {
+            revert("nft: balance query for the zero address");
+        }
+        get_u256(&db::key(BALANCE_PREFIX, &[owner.as_slice()]))
+    }
+
+    pub fn owner_of(&self, id: &U256) -> Address {
+        match db::get(&db::key(OWNER_PREFIX, &[&id.bytes32()])) {
+            Some(v) => Address::new(v),
+            None => revert("nft: owner query for nonexistent token"),
+        }
+    }
+
+    pub fn exists(&self, id: &U256) -> bool {
+        db::contains_key(&db::key(OWNER_PREFIX, &[&id.bytes32()]))
+    }
+
+    /// approve `to` to transfer token `id`, zero address clears approval
+    pub fn approve(&self, to: &Address, id: &U256) {
+        let owner = self.owner_of(id);
+        if *to == owner {
+            revert("nft: approval to current owner");
+        }
+        let sender = context::msg().sender;
+        if sender != owner && !self.is_approved_for_all(&owner, &sender) {
+            revert("nft: approve caller is not owner nor approved for all");
+        }
+        self.set_approved(&owner, to, id);
+    }
+
+    pub fn get_approved(&self, id: &U256) -> Address {
+        self.require_minted(id);
+        match db::get(&db::key(APPROVED_PREFIX, &[&id.bytes32()])) {
+            Some(v) => Address::new(v),
+            None => Address::default(),
+        }
+    }
+
+    /// allow or disallow `operator` to manage all tokens of sender
+    pub fn set_approval_for_all(&self, operator: &Address, approved: bool) {
+        let owner = context::msg().sender;
+        if owner == *operator {
+            revert("nft: approve to caller");
+        }
+        let k = db::key(OPERATOR_PREFIX, &[owner.as_slice(), operator.as_slice()]);
+        if approved {
+            db::insert(&k, &[1]);
+        } else {
+            db::remove(&k);
+        }
+        event::emit(APPROVAL_FOR_ALL_EVENT, &[owner.as_slice(), operator.as_slice(), &[approved as u8]]);
+    }
+
+    pub fn is_approved_for_all(&self, owner: &Address, operator: &Address) -> bool {
+        db::contains_key(&db::key(OPERATOR_PREFIX, &[owner.as_slice(), operator.as_slice()]))
+    }
+
+    /// move token `id` from `from` to `to`, sender must be owner, approved or operator
+    pub fn transfer_from(&self, from: &Address, to: &Address, id: &U256) {
+        let sender = context::msg().sender;
+        if !self.is_approved_or_owner(&sender, id) {
+            revert("nft: transfer caller is not owner nor approved");
+        }
+        self.move_token(from, to, id);
+    }
+
+    /// `transfer_from` then check `to` accepts the token by `Hooks::on_received`
+    pub fn safe_transfer_from(&self, from: &Address, to: &Address, id: &U256, data: &[u8]) {
+        self.transfer_from(from, to, id);
+        self.check_on_received(from, to, id, data);
+    }
+
+    pub fn total_supply(&self) -> U256 {
+        get_u256(ALL_LEN_KEY)
+    }
+
+    pub fn token_by_index(&self, index: &U256) -> U256 {
+        if *index >= self.total_supply() {
+            revert("nft: global index out of bounds");
+        }
+        get_u256(&db::key(ALL_PREFIX, &[&index.bytes32()]))
+    }
+
+    pub fn token_of_owner_by_index(&self, owner: &Address, index: &U256) -> U256 {
+        if *index >= self.balance_of(owner) {
+            revert("nft: owner index out of bounds");
+        }
+        get_u256(&db::key(OWNED_PREFIX, &[owner.as_slice(), &index.bytes32()]))
+    }
+
+    /// create token `id` for `to`, access control is left to the contract
+    pub fn mint(&self, to: &Address, id: &U256) {
+        if is_zero_address(to) {
+            revert("nft: mint to the zero address");
+        }
+        if self.exists(id) {
+            revert("nft: token already minted");
+        }
+        let zero = Address::default();
+        self.hooks.before_token_transfer(&zero, to, id);
+        let len = self.total_supply();
+        set_u256(&db::key(ALL_PREFIX, &[&len.bytes32()]), id);
+        set_u256(&db::key(ALL_INDEX_PREFIX, &[&id.bytes32()]), &len);
+        set_u256(ALL_LEN_KEY, &(len + U256::one()));
+        self.add_to_owner(to, id);
+        event::emit(TRANSFER_EVENT, &[zero.as_slice(), to.as_slice(), &id.bytes32()]);
+    }
+
+    /// `mint` then check `to` accepts the token by `Hooks::on_received`
+    pub fn safe_mint(&self, to: &Address, id: &U256, data: &[u8]) {
+        self.mint(to, id);
+        self.check_on_received(&Address::default(), to, id, data);
+    }
+
+    /// destroy token `id`, access control is left to the contract
+    pub fn burn(&self, id: &U256) {
+        let owner = self.owner_of(id);
+        let zero = Address::default();
+        self.hooks.before_token_transfer(&owner, &zero, id);
+        db::remove(&db::key(APPROVED_PREFIX, &[&id.bytes32()]));
+        db::remove(&db::key(URI_PREFIX, &[&id.bytes32()]));
+        self.remove_from_owner(&owner, id);
+
+        // move last token into the removed slot
+        let index = get_u256(&db::key(ALL_INDEX_PREFIX, &[&id.bytes32()]));
+        let last_index = self.total_supply() - U256::one();
+        if index != last_index {
+            let last = get_u256(&db::key(ALL_PREFIX, &[&last_index.bytes32()]));
+            set_u256(&db::key(ALL_PREFIX, &[&index.bytes32()]), &last);
+            set_u256(&db::key(ALL_INDEX_PREFIX, &[&last.bytes32()]), &index);
+        }
+        db::remove(&db::key(ALL_PREFIX, &[&last_index.bytes32()]));
+        db::remove(&db::key(ALL_INDEX_PREFIX, &[&id.bytes32()]));
+        set_u256(ALL_LEN_KEY, &last_index);
+        event::emit(TRANSFER_EVENT, &[owner.as_slice(), zero.as_slice(), &id.bytes32()]);
+    }
+
+    pub fn is_approved_or_owner(&self, spender: &Address, id: &U256) -> bool {
+        let owner = self.owner_of(id);
+        *spender == owner || self.get_approved(id) == *spender || self.is_approved_for_all(&owner, spender)
+    }
+
+    fn require_minted(&self, id: &U256) {
+        if !self.exists(id) {
+            revert("nft: invalid token id");
+        }
+    }
+
+    fn set_approved(&self, owner: &Address, to: &Address, id: &U256) {
+        let k = db::key(APPROVED_PREFIX, &[&id.bytes32()]);
+        if is_zero_address(to) {
+            db::remove(&k);
+        } else {
+            db::insert(&k, to.as_slice());
+        }
+        event::emit(APPROVAL_EVENT, &[owner.as_slice(), to.as_slice(), &id.bytes32()]);
+    }
+
+    fn move_token(&self, from: &Address, to: &Address, id: &U256) {
+        if self.owner_of(id) != *from {
+            revert("nft: transfer from incorrect owner");
+        }
+        if is_zero_address(to) {
+            revert("nft: transfer to the zero address");
+        }
+        self.hooks.before_token_transfer(from, to, id);
+        db::remove(&db::key(APPROVED_PREFIX, &[&id.bytes32()]));
+        self.remove_from_owner(from, id);
+        self.add_to_owner(to, id);
+        event::emit(TRANSFER_EVENT, &[from.as_slice(), to.as_slice(), &id.bytes32()]);
+    }
+
+    fn check_on_received(&self, from: &Address, to: &Address, id: &U256, data: &[u8]) {
+        let operator = context::msg().sender;
+        if !self.hooks.on_received(&operator, from, to, id, data) {
+            revert("nft: transfer to non receiver implementer");
+        }
+    }
+
+    fn add_to_owner(&self, to: &Address, id: &U256) {
+        let balance_key = db::key(BALANCE_PREFIX, &[to.as_slice()]);
+        let len = get_u256(&balance_key);
+        set_u256(&db::key(OWNED_PREFIX, &[to.as_slice(), &len.bytes32()]), id);
+        set_u256(&db::key(OWNED_INDEX_PREFIX, &[&id.bytes32()]), &len);
+        set_u256(&balance_key, &(len + U256::one()));
+        db::insert(&db::key(OWNER_PREFIX, &[&id.bytes32()]), to.as_slice());
+    }
+
+    // swap token with the last one of owner then pop
+    fn remove_from_owner(&self, from: &Address, id: &U256) {
+        let balance_key = db::key(BALANCE_PREFIX, &[from.as_slice()]);
+        let last_index = get_u256(&balance_key) - U256::one();
+        let index = get_u256(&db::key(OWNED_INDEX_PREFIX, &[&id.bytes32()]));
+        if index != last_index {
+            let last = get_u256(&db::key(OWNED_PREFIX, &[from.as_slice(), &last_index.bytes32()]));
+            set_u256(&db::key(OWNED_PREFIX, &[from.as_slice(), &index.bytes32()]), &last);
+            set_u256(&db::key(OWNED_INDEX_PREFIX, &[&last.bytes32()]), &index);
+        }
+        db::remove(&db::key(OWNED_PREFIX, &[from.as_slice(), &last_index.bytes32()]));
+        db::remove(&db::key(OWNED_INDEX_PREFIX, &[&id.bytes32()]));
+        set_u256(&balance_key, &last_index);
+        db::remove(&db::key(OWNER_PREFIX, &[&id.bytes32()]));
+    }
+}
+
+/// export erc-721 abi methods (`name`, `symbol`, `tokenURI`, `balanceOf`, `ownerOf`, `approve`, `getApproved`,
+/// `setApprovalForAll`, `isApprovedForAll`, `transferFrom`, `safeTransferFrom`, `totalSupply`, `tokenByIndex`,
+/// `tokenOfOwnerByIndex`) of a `static` nft
+#[macro_export]
+macro_rules! export_nft {
+    ($nft: expr) => {
+        #[no_mangle]
+        pub fn name() -> &'static alloc::string::String {
+            $crate::rust_wbi::ret($nft.name())
+        }
+
+        #[no_mangle]
+        pub fn symbol() -> &'static alloc::string::String {
+            $crate::rust_wbi::ret($nft.symbol())
+        }
+
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub fn tokenURI(id: $crate::rust_wbi::u256::U256) -> &'static alloc::string::String {
+            $crate::rust_wbi::ret($nft.token_uri(&id))
+        }
+
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub fn balanceOf(owner: $crate::rust_wbi::address::Address) -> &'static $crate::rust_wbi::u256::U256 {
+            $crate::rust_wbi::ret($nft.balance_of(&owner))
+        }
+
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub fn ownerOf(id: $crate::rust_wbi::u256::U256) -> &'static $crate::rust_wbi::address::Address {
+            $crate::rust_wbi::ret($nft.owner_of(&id))
+        }
+
+        #[no_mangle]
+        pub fn approve(to: $crate::rust_wbi::address::Address, id: $crate::rust_wbi::u256::U256) {
+            $nft.approve(&to, &id)
+        }
+
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub fn getApproved(id: $crate::rust_wbi::u256::U256) -> &'static $crate::rust_wbi::address::Address {
+            $crate::rust_wbi::ret($nft.get_approved(&id))
+        }
+
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub fn setApprovalForAll(operator: $crate::rust_wbi::address::Address, approved: bool) {
+            $nft.set_approval_for_all(&operator, approved)
+        }
+
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub fn isApprovedForAll(
+            owner: $crate::rust_wbi::address::Address,
+            operator: $crate::rust_wbi::address::Address,
+        ) -> bool {
+            $nft.is_approved_for_all(&owner, &operator)
+        }
+
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub fn transferFrom(
+            from: $crate::rust_wbi::address::Address,
+            to: $crate::rust_wbi::address::Address,
+            id: $crate::rust_wbi::u256::U256,
+        ) {
+            $nft.transfer_from(&from, &to, &id)
+        }
+
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub fn safeTransferFrom(
+            from: $crate::rust_wbi::address::Address,
+            to: $crate::rust_wbi::address::Address,
+            id: $crate::rust_wbi::u256::U256,
+            data: alloc::vec::Vec<u8>,
+        ) {
+            $nft.safe_transfer_from(&from, &to, &id, &data)
+        }
+
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub fn totalSupply() -> &'static $crate::rust_wbi::u256::U256 {
+            $crate::rust_wbi::ret($nft.total_supply())
+        }
+
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub fn tokenByIndex(index: $crate::rust_wbi::u256::U256) -> &'static $crate::rust_wbi::u256::U256 {
+            $crate::rust_wbi::ret($nft.token_by_index(&index))
+        }
+
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub fn tokenOfOwnerByIndex(
+            owner: $crate::rust_wbi::address::Address,
+            index: $crate::rust_wbi::u256::U256,
+        ) -> &'static $crate::rust_wbi::u256::U256 {
+            $crate::rust_wbi::ret($nft.token_of_owner_by_index(&owner, &index))
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_wbi::event::{take_events, Event};
+    use rust_wbi::testing::{self, addr, reverts, u};
+
+    mod abi {
+        static NFT: crate::Nft = crate::Nft::new();
+        export_nft!(NFT);
+    }
+
+    fn transfer_event(from: &Address, to: &Address, id: u64) -> Event {
+        Event {
+            name: TRANSFER_EVENT.to_string(),
+            fields: vec![from.as_slice().to_vec(), to.as_slice().to_vec(), u(id).bytes32()],
+        }
+    }
+
+    // addr(1) owns 1, 2, 3
+    fn setup() -> Nft {
+        let n = Nft::new();
+        n.init("Test NFT", "TN", "https://example.com/");
+        for id in 1..4 {
+            n.mint(&addr(1), &u(id));
+        }
+        testing::begin(&addr(1));
+        n
+    }
+
+    fn owned(n: &Nft, owner: &Address) -> Vec<u64> {
+        let len = n.balance_of(owner).u64();
+        let mut v: Vec<u64> = (0..len).map(|i| n.token_of_owner_by_index(owner, &u(i)).u64()).collect();
+        v.sort();
+        v
+    }
+
+    fn all(n: &Nft) -> Vec<u64> {
+        let len = n.total_supply().u64();
+        let mut v: Vec<u64> = (0..len).map(|i| n.token_by_index(&u(i)).u64()).collect();
+        v.sort();
+        v
+    }
+
+    #[test]
+    fn mint() {
+        let _host = testing::lock();
+        let n = setup();
+        assert_eq!(n.owner_of(&u(1)), addr(1));
+        assert_eq!(n.balance_of(&addr(1)), u(3));
+        n.mint(&addr(2), &u(10));
+        assert_eq!(take_events(), vec![transfer_event(&Address::default(), &addr(2), 10)]);
+        reverts(|| n.mint(&addr(2), &u(10)), "nft: token already minted");
+        reverts(|| n.mint(&Address::default(), &u(11)), "nft: mint to the zero address");
+        reverts(|| n.owner_of(&u(11)), "nft: owner query for nonexistent token");
+    }
+
+    #[test]
+    fn metadata() {
+        let _host = testing::lock();
+        let n = setup();
+        assert_eq!(n.name(), "Test NFT");
+        assert_eq!(n.symbol(), "TN");
+        assert_eq!(n.token_uri(&u(2)), "https://example.com/2");
+        n.set_token_uri(&u(2), "ipfs://two");
+        assert_eq!(n.token_uri(&u(2)), "ipfs://two");
+        n.set_base_uri("");
+        assert_eq!(n.token_uri(&u(1)), "");
+        reverts(|| n.token_uri(&u(4)), "nft: invalid token id");
+    }
+
+    #[test]
+    fn transfer() {
+        let _host = testing::lock();
+        let n = setup();
+        n.transfer_from(&addr(1), &addr(2), &u(2));
+        assert_eq!(n.owner_of(&u(2)), addr(2));
+        assert_eq!(owned(&n, &addr(1)), vec![1, 3]);
+        assert_eq!(owned(&n, &addr(2)), vec![2]);
+        assert_eq!(take_events(), vec![transfer_event(&addr(1), &addr(2), 2)]);
+
+        reverts(|| n.transfer_from(&addr(1), &addr(3), &u(2)), "nft: transfer caller is not owner nor approved");
+        context::set_sender(addr(2));
+        reverts(|| n.transfer_from(&addr(1), &addr(3), &u(2)), "nft: transfer from incorrect owner");
+        reverts(|| n.transfer_from(&addr(2), &Address::default(), &u(2)), "nft: transfer to the zero address");
+    }
+
+    #[test]
+    fn approve() {
+        let _host = testing::lock();
+        let n = setup();
+        n.approve(&addr(2), &u(1));
+        assert_eq!(n.get_approved(&u(1)), addr(2));
+        assert_eq!(
+            take_events(),
+            vec![Event {
+                name: APPROVAL_EVENT.to_string(),
+                fields: vec![addr(1).as_slice().to_vec(), addr(2).as_slice().to_vec(), u(1).bytes32()],
+            }]
+        );
+        reverts(|| n.approve(&addr(1), &u(1)), "nft: approval to current owner");
+
+        context::set_sender(addr(2));
+        reverts(|| n.approve(&addr(2), &u(3)), "nft: approve caller is not owner nor approved for all");
+        n.transfer_from(&addr(1), &addr(3), &u(1));
+        // approval is cleared by transfer
+        assert_eq!(n.get_approved(&u(1)), Address::default());
+        reverts(|| n.transfer_from(&addr(3), &addr(2), &u(1)), "nft: transfer caller is not owner nor approved");
+    }
+
+    #[test]
+    fn operator() {
+        let _host = testing::lock();
+        let n = setup();
+        n.set_approval_for_all(&addr(2), true);
+        assert!(n.is_approved_for_all(&addr(1), &addr(2)));
+        assert_eq!(take_events()[0].fields[2], vec![1]);
+
+        context::set_sender(addr(2));
+        n.approve(&addr(3), &u(1));
+        n.transfer_from(&addr(1), &addr(2), &u(2));
+        assert_eq!(owned(&n, &addr(2)), vec![2]);
+
+        context::set_sender(addr(1));
+        n.set_approval_for_all(&addr(2), false);
+        context::set_sender(addr(2));
+        reverts(|| n.transfer_from(&addr(1), &addr(2), &u(3)), "nft: transfer caller is not owner nor approved");
+        reverts(|| n.set_approval_for_all(&addr(2), true), "nft: approve to caller");
+    }
+
+    #[test]
+    fn burn() {
+        let _host = testing::lock();
+        let n = setup();
+        n.mint(&addr(2), &u(4));
+        n.approve(&addr(3), &u(1));
+        take_events();
+        n.burn(&u(1));
+        assert!(!n.exists(&u(1)));
+        assert_eq!(all(&n), vec![2, 3, 4]);
+        assert_eq!(owned(&n, &addr(1)), vec![2, 3]);
+        assert_eq!(take_events(), vec![transfer_event(&addr(1), &Address::default(), 1)]);
+        reverts(|| n.get_approved(&u(1)), "nft: invalid token id");
+
+        n.burn(&u(4));
+        assert_eq!(all(&n), vec![2, 3]);
+        assert_eq!(n.balance_of(&addr(2)), U256::zero());
+        n.mint(&addr(2), &u(1));
+        assert_eq!(all(&n), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn enumerable() {
+        let _host = testing::lock();
+        let n = setup();
+        n.mint(&addr(2), &u(7));
+        n.transfer_from(&addr(1), &addr(2), &u(1));
+        n.transfer_from(&addr(1), &addr(2), &u(3));
+        assert_eq!(all(&n), vec![1, 2, 3, 7]);
+        assert_eq!(owned(&n, &addr(1)), vec![2]);
+        assert_eq!(owned(&n, &addr(2)), vec![1, 3, 7]);
+        reverts(|| n.token_by_index(&u(4)), "nft: global index out of bounds");
+        reverts(|| n.token_of_owner_by_index(&addr(1), &u(1)), "nft: owner index out of bounds");
+    }
+
+    // only addresses starting with 0x0a accept tokens, called back as receiver
+    struct Receivers;
+
+    impl Hooks for Receivers {
+        fn on_received(&self, operator: &Address, _from: &Address, to: &Address, _id: &U256, data: &[u8]) -> bool {
+            let receiver = to.clone();
+            context::mock_call(operator.clone(), || {
+                assert_eq!(context::call_depth(), 1);
+                receiver.as_slice()[0] == 0x0a && data != b"reject"
+            })
+        }
+    }
+
+    // mock host doesn't roll back state of reverted calls, so every revert is checked in its own test
+    fn safe_setup() -> Nft<Receivers> {
+        let n = Nft::with_hooks(Receivers);
+        n.safe_mint(&addr(0x0a), &u(1), b"");
+        context::set_sender(addr(0x0a));
+        n
+    }
+
+    #[test]
+    fn safe_transfer() {
+        let _host = testing::lock();
+        let n = safe_setup();
+        n.safe_transfer_from(&addr(0x0a), &addr(0x0a), &u(1), b"");
+        // plain transfer doesn't ask receiver
+        n.transfer_from(&addr(0x0a), &addr(0x0b), &u(1));
+        assert_eq!(n.owner_of(&u(1)), addr(0x0b));
+    }
+
+    #[test]
+    fn safe_transfer_to_non_receiver() {
+        let _host = testing::lock();
+        let n = std::panic::AssertUnwindSafe(safe_setup());
+        reverts(
+            || n.safe_transfer_from(&addr(0x0a), &addr(0x0b), &u(1), b""),
+            "nft: transfer to non receiver implementer",
+        );
+    }
+
+    #[test]
+    fn safe_transfer_rejected() {
+        let _host = testing::lock();
+        let n = std::panic::AssertUnwindSafe(safe_setup());
+        reverts(
+            || n.safe_transfer_from(&addr(0x0a), &addr(0x0a), &u(1), b"reject"),
+            "nft: transfer to non receiver implementer",
+        );
+    }
+
+    #[test]
+    fn safe_mint_to_non_receiver() {
+        let _host = testing::lock();
+        let n = std::panic::AssertUnwindSafe(Nft::with_hooks(Receivers));
+        reverts(|| n.safe_mint(&addr(0x0b), &u(1), b""), "nft: transfer to non receiver implementer");
+    }
+
+    #[test]
+    fn abi_methods() {
+        let _host = testing::lock();
+        setup();
+        abi::transferFrom(addr(1), addr(2), u(1));
+        abi::setApprovalForAll(addr(3), true);
+        assert_eq!(*abi::ownerOf(u(1)), addr(2));
+        assert_eq!(*abi::balanceOf(addr(2)), u(1));
+        assert_eq!(*abi::tokenOfOwnerByIndex(addr(2), u(0)), u(1));
+        assert_eq!(*abi::totalSupply(), u(3));
+        assert_eq!(*abi::tokenURI(u(1)), "https://example.com/1");
+        assert!(abi::isApprovedForAll(addr(1), addr(3)));
+    }
+}
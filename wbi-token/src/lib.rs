@@ -0,0 +1,509 @@
+#![cfg_attr(target_arch = "wasm32", no_std)]
+#[macro_use]
+extern crate alloc;
+pub extern crate rust_wbi;
+
+use alloc::string::*;
+use alloc::vec::Vec;
+use rust_wbi::{address::Address, context, db, event, revert, u256::U256};
+
+const NAME_KEY: &[u8] = b"__token_name";
+const SYMBOL_KEY: &[u8] = b"__token_symbol";
+const DECIMALS_KEY: &[u8] = b"__token_decimals";
+const SUPPLY_KEY: &[u8] = b"__token_supply";
+const BALANCE_PREFIX: &[u8] = b"__token_balance";
+const ALLOWANCE_PREFIX: &[u8] = b"__token_allowance";
+
+pub const TRANSFER_EVENT: &str = "Transfer";
+pub const APPROVAL_EVENT: &str = "Approval";
+
+/// called around every balance change, `from` is zero address when minting, `to` is zero address when burning
+pub trait Hooks {
+    fn before_token_transfer(&self, _from: &Address, _to: &Address, _amount: &U256) {}
+    fn after_token_transfer(&self, _from: &Address, _to: &Address, _amount: &U256) {}
+}
+
+pub struct NoHooks;
+
+impl Hooks for NoHooks {}
+
+/// erc-20 compatible fungible token, state is stored in `wbi::db`
+pub struct Token<H: Hooks = NoHooks> {
+    hooks: H,
+}
+
+impl Token<NoHooks> {
+    pub const fn new() -> Token<NoHooks> {
+        Token { hooks: NoHooks }
+    }
+}
+
+impl Default for Token<NoHooks> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn balance_key(owner: &Address) -> Vec<u8> {
+    db::key(BALANCE_PREFIX, &[owner.as_slice()])
+}
+
+fn allowance_key(owner: &Address, spender: &Address) -> Vec<u8> {
+    db::key(ALLOWANCE_PREFIX, &[owner.as_slice(), spender.as_slice()])
+}
+
+fn get_u256(k: &[u8]) -> U256 {
+    match db::get(k) {
+        Some(v) => U256::from_bytes(&v),
+        None => U256::zero(),
+    }
+}
+
+fn set_u256(k: &[u8], u: &U256) {
+    if u.is_zero() {
+        db::remove(k)
+    } else {
+        db::insert(k, &u.bytes32())
+    }
+}
+
+fn is_zero_address(a: &Address) -> bool {
+    *a == Address::default()
+}
+
+impl<H: Hooks> Token<H> {
+    pub const fn with_hooks(hooks: H) -> Token<H> {
+        Token { hooks }
+    }
+
+    /// set token metadata, usually called in `init`, can only be called once
+    pub fn init(&self, name: &str, symbol: &str, decimals: u8) {
+        if db::contains_key(DECIMALS_KEY) {
+            revert("token: already initialized");
+        }
+        db::insert(NAME_KEY, name.as_bytes());
+        db::insert(SYMBOL_KEY, symbol.as_bytes());
+        db::insert(DECIMALS_KEY, &[decimals]);
+    }
+
+    pub fn name(&self) -> String {
+        String::from_utf8(db::get(NAME_KEY).unwrap_or_default()).unwrap()
+    }
+
+    pub fn symbol(&self) -> String {
+        String::from_utf8(db::get(SYMBOL_KEY).unwrap_or_default()).unwrap()
+    }
+
+    pub fn decimals(&self) -> u8 {
+        db::get(DECIMALS_KEY).map(|v| v[0]).unwrap_or(18)
+    }
+
+    pub fn total_supply(&self) -> U256 {
+        get_u256(SUPPLY_KEY)
+    }
+
+    pub fn balance_of(&self, owner: &Address) -> U256 {
+        get_u256(&balance_key(owner))
+    }
+
+    pub fn allowance(&self, owner: &Address, spender: &Address) -> U256 {
+        get_u256(&allowance_key(owner, spender))
+    }
+
+    /// move `amount` from sender to `to`
+    pub fn transfer(&self, to: &Address, amount: &U256) -> bool {
+        let sender = context::msg().sender;
+        self.move_balance(&sender, to, amount);
+        true
+    }
+
+    /// allow `spender` to spend `amount` of sender's tokens, replaces previous allowance
+    pub fn approve(&self, spender: &Address, amount: &U256) -> bool {
+        let sender = context::msg().sender;
+        self.set_allowance(&sender, spender, amount);
+        true
+    }
+
+    /// move `amount` from `from` to `to` on behalf of sender, deducted from allowance
+    pub fn transfer_from(&self, from: &Address, to: &Address, amount: &U256) -> bool {
+        let sender = context::msg().sender;
+        self.spend_allowance(from, &sender, amount);
+        self.move_balance(from, to, amount);
+        true
+    }
+
+    pub fn increase_allowance(&self, spender: &Address, added: &U256) -> bool {
+        let sender = context::msg().sender;
+        let a = self.allowance(&sender, spender) + added;
+        self.set_allowance(&sender, spender, &a);
+        true
+    }
+
+    pub fn decrease_allowance(&self, spender: &Address, subtracted: &U256) -> bool {
+        let sender = context::msg().sender;
+        let a = self.allowance(&sender, spender);
+        if *subtracted > a {
+            revert("token: decreased allowance below zero");
+        }
+        self.set_allowance(&sender, spender, &(a - subtracted));
+        true
+    }
+
+    /// create `amount` tokens for `to`, access control is left to the contract
+    pub fn mint(&self, to: &Address, amount: &U256) {
+        if is_zero_address(to) {
+            revert("token: mint to the zero address");
+        }
+        let zero = Address::default();
+        self.hooks.before_token_transfer(&zero, to, amount);
+        set_u256(SUPPLY_KEY, &(self.total_supply() + amount));
+        set_u256(&balance_key(to), &(self.balance_of(to) + amount));
+        emit_transfer(&zero, to, amount);
+        self.hooks.after_token_transfer(&zero, to, amount);
+    }
+
+    /// destroy `amount` tokens of `from`, access control is left to the contract
+    pub fn burn(&self, from: &Address, amount: &U256) {
+        if is_zero_address(from) {
+            revert("token: burn from the zero address");
+        }
+        let zero = Address::default();
+        self.hooks.before_token_transfer(from, &zero, amount);
+        let balance = self.balance_of(from);
+        if *amount > balance {
+            revert("token: burn amount exceeds balance");
+        }
+        set_u256(&balance_key(from), &(balance - amount));
+        set_u256(SUPPLY_KEY, &(self.total_supply() - amount));
+        emit_transfer(from, &zero, amount);
+        self.hooks.after_token_transfer(from, &zero, amount);
+    }
+
+    /// destroy `amount` tokens of `from` on behalf of sender, deducted from allowance
+    pub fn burn_from(&self, from: &Address, amount: &U256) {
+        let sender = context::msg().sender;
+        self.spend_allowance(from, &sender, amount);
+        self.burn(from, amount);
+    }
+
+    fn move_balance(&self, from: &Address, to: &Address, amount: &U256) {
+        if is_zero_address(from) {
+            revert("token: transfer from the zero address");
+        }
+        if is_zero_address(to) {
+            revert("token: transfer to the zero address");
+        }
+        self.hooks.before_token_transfer(from, to, amount);
+        let from_balance = self.balance_of(from);
+        if *amount > from_balance {
+            revert("token: transfer amount exceeds balance");
+        }
+        set_u256(&balance_key(from), &(from_balance - amount));
+        set_u256(&balance_key(to), &(self.balance_of(to) + amount));
+        emit_transfer(from, to, amount);
+        self.hooks.after_token_transfer(from, to, amount);
+    }
+
+    fn set_allowance(&self, owner: &Address, spender: &Address, amount: &U256) {
+        if is_zero_address(owner) {
+            revert("token: approve from the zero address");
+        }
+        if is_zero_address(spender) {
+            revert("token: approve to the zero address");
+        }
+        set_u256(&allowance_key(owner, spender), amount);
+        event::emit(APPROVAL_EVENT, &[owner.as_slice(), spender.as_slice(), &amount.bytes32()]);
+    }
+
+    // max allowance is treated as infinite and never decreased
+    fn spend_allowance(&self, owner: &Address, spender: &Address, amount: &U256) {
+        let a = self.allowance(owner, spender);
+        if a == U256::max() {
+            return;
+        }
+        if *amount > a {
+            revert("token: insufficient allowance");
+        }
+        self.set_allowance(owner, spender, &(a - amount));
+    }
+}
+
+fn emit_transfer(from: &Address, to: &Address, amount: &U256) {
+    event::emit(TRANSFER_EVENT, &[from.as_slice(), to.as_slice(), &amount.bytes32()]);
+}
+
+/// export erc-20 abi methods (`name`, `symbol`, `decimals`, `totalSupply`, `balanceOf`, `transfer`,
+/// `allowance`, `approve`, `transferFrom`) of a `static` token
+#[macro_export]
+macro_rules! export_token {
+    ($token: expr) => {
+        #[no_mangle]
+        pub fn name() -> &'static alloc::string::String {
+            $crate::rust_wbi::ret($token.name())
+        }
+
+        #[no_mangle]
+        pub fn symbol() -> &'static alloc::string::String {
+            $crate::rust_wbi::ret($token.symbol())
+        }
+
+        #[no_mangle]
+        pub fn decimals() -> u64 {
+            $token.decimals() as u64
+        }
+
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub fn totalSupply() -> &'static $crate::rust_wbi::u256::U256 {
+            $crate::rust_wbi::ret($token.total_supply())
+        }
+
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub fn balanceOf(owner: $crate::rust_wbi::address::Address) -> &'static $crate::rust_wbi::u256::U256 {
+            $crate::rust_wbi::ret($token.balance_of(&owner))
+        }
+
+        #[no_mangle]
+        pub fn transfer(to: $crate::rust_wbi::address::Address, amount: $crate::rust_wbi::u256::U256) -> bool {
+            $token.transfer(&to, &amount)
+        }
+
+        #[no_mangle]
+        pub fn allowance(
+            owner: $crate::rust_wbi::address::Address,
+            spender: $crate::rust_wbi::address::Address,
+        ) -> &'static $crate::rust_wbi::u256::U256 {
+            $crate::rust_wbi::ret($token.allowance(&owner, &spender))
+        }
+
+        #[no_mangle]
+        pub fn approve(spender: $crate::rust_wbi::address::Address, amount: $crate::rust_wbi::u256::U256) -> bool {
+            $token.approve(&spender, &amount)
+        }
+
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub fn transferFrom(
+            from: $crate::rust_wbi::address::Address,
+            to: $crate::rust_wbi::address::Address,
+            amount: $crate::rust_wbi::u256::U256,
+        ) -> bool {
+            $token.transfer_from(&from, &to, &amount)
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::RefCell;
+    use rust_wbi::event::{take_events, Event};
+    use rust_wbi::testing::{self, addr, reverts, u};
+
+    mod abi {
+        static TOKEN: crate::Token = crate::Token::new();
+        export_token!(TOKEN);
+    }
+
+    #[test]
+    fn abi_methods() {
+        let _host = testing::lock();
+        let t = setup();
+        context::set_sender(addr(1));
+        assert!(abi::transfer(addr(2), u(5)));
+        assert!(abi::approve(addr(3), u(7)));
+        assert_eq!(*abi::balanceOf(addr(2)), u(5));
+        assert_eq!(*abi::allowance(addr(1), addr(3)), u(7));
+        assert_eq!(*abi::totalSupply(), t.total_supply());
+        assert_eq!(*abi::symbol(), "TT");
+        assert_eq!(abi::decimals(), 8);
+    }
+
+    fn transfer_event(from: &Address, to: &Address, n: u64) -> Event {
+        Event {
+            name: TRANSFER_EVENT.to_string(),
+            fields: vec![from.as_slice().to_vec(), to.as_slice().to_vec(), u(n).bytes32()],
+        }
+    }
+
+    fn approval_event(owner: &Address, spender: &Address, n: u64) -> Event {
+        Event {
+            name: APPROVAL_EVENT.to_string(),
+            fields: vec![owner.as_slice().to_vec(), spender.as_slice().to_vec(), u(n).bytes32()],
+        }
+    }
+
+    fn setup() -> Token {
+        let t = Token::new();
+        t.init("Test Token", "TT", 8);
+        t.mint(&addr(1), &u(1000));
+        testing::begin(&addr(1));
+        t
+    }
+
+    #[test]
+    fn metadata() {
+        let _host = testing::lock();
+        let t = Token::new();
+        t.init("Test Token", "TT", 8);
+        assert_eq!(t.name(), "Test Token");
+        assert_eq!(t.symbol(), "TT");
+        assert_eq!(t.decimals(), 8);
+        assert_eq!(t.total_supply(), U256::zero());
+        reverts(|| t.init("Other", "O", 18), "token: already initialized");
+    }
+
+    #[test]
+    fn mint() {
+        let _host = testing::lock();
+        let t = setup();
+        t.mint(&addr(2), &u(50));
+        assert_eq!(t.total_supply(), u(1050));
+        assert_eq!(t.balance_of(&addr(2)), u(50));
+        assert_eq!(take_events(), vec![transfer_event(&Address::default(), &addr(2), 50)]);
+        reverts(|| t.mint(&Address::default(), &u(1)), "token: mint to the zero address");
+    }
+
+    #[test]
+    fn burn() {
+        let _host = testing::lock();
+        let t = setup();
+        t.burn(&addr(1), &u(400));
+        assert_eq!(t.total_supply(), u(600));
+        assert_eq!(t.balance_of(&addr(1)), u(600));
+        assert_eq!(take_events(), vec![transfer_event(&addr(1), &Address::default(), 400)]);
+        reverts(|| t.burn(&addr(1), &u(601)), "token: burn amount exceeds balance");
+    }
+
+    #[test]
+    fn transfer() {
+        let _host = testing::lock();
+        let t = setup();
+        assert!(t.transfer(&addr(2), &u(300)));
+        assert_eq!(t.balance_of(&addr(1)), u(700));
+        assert_eq!(t.balance_of(&addr(2)), u(300));
+        assert_eq!(t.total_supply(), u(1000));
+        assert_eq!(take_events(), vec![transfer_event(&addr(1), &addr(2), 300)]);
+
+        // whole balance and zero amount
+        assert!(t.transfer(&addr(3), &u(700)));
+        assert!(t.transfer(&addr(3), &u(0)));
+        assert_eq!(t.balance_of(&addr(1)), U256::zero());
+        assert_eq!(t.balance_of(&addr(3)), u(700));
+    }
+
+    #[test]
+    fn transfer_exceeds_balance() {
+        let _host = testing::lock();
+        let t = setup();
+        reverts(|| t.transfer(&addr(2), &u(1001)), "token: transfer amount exceeds balance");
+        assert_eq!(t.balance_of(&addr(1)), u(1000));
+    }
+
+    #[test]
+    fn transfer_to_zero_address() {
+        let _host = testing::lock();
+        let t = setup();
+        reverts(|| t.transfer(&Address::default(), &u(1)), "token: transfer to the zero address");
+    }
+
+    #[test]
+    fn approve_and_transfer_from() {
+        let _host = testing::lock();
+        let t = setup();
+        assert!(t.approve(&addr(2), &u(500)));
+        assert_eq!(t.allowance(&addr(1), &addr(2)), u(500));
+        assert_eq!(take_events(), vec![approval_event(&addr(1), &addr(2), 500)]);
+
+        context::set_sender(addr(2));
+        assert!(t.transfer_from(&addr(1), &addr(3), &u(200)));
+        assert_eq!(t.allowance(&addr(1), &addr(2)), u(300));
+        assert_eq!(t.balance_of(&addr(1)), u(800));
+        assert_eq!(t.balance_of(&addr(3)), u(200));
+        assert_eq!(
+            take_events(),
+            vec![approval_event(&addr(1), &addr(2), 300), transfer_event(&addr(1), &addr(3), 200)]
+        );
+
+        reverts(|| t.transfer_from(&addr(1), &addr(3), &u(301)), "token: insufficient allowance");
+        context::set_sender(addr(4));
+        reverts(|| t.transfer_from(&addr(1), &addr(4), &u(1)), "token: insufficient allowance");
+    }
+
+    #[test]
+    fn infinite_allowance() {
+        let _host = testing::lock();
+        let t = setup();
+        t.approve(&addr(2), &U256::max());
+        context::set_sender(addr(2));
+        t.transfer_from(&addr(1), &addr(2), &u(1000));
+        assert_eq!(t.allowance(&addr(1), &addr(2)), U256::max());
+    }
+
+    #[test]
+    fn increase_decrease_allowance() {
+        let _host = testing::lock();
+        let t = setup();
+        t.increase_allowance(&addr(2), &u(10));
+        t.increase_allowance(&addr(2), &u(5));
+        assert_eq!(t.allowance(&addr(1), &addr(2)), u(15));
+        t.decrease_allowance(&addr(2), &u(15));
+        assert_eq!(t.allowance(&addr(1), &addr(2)), U256::zero());
+        reverts(|| t.decrease_allowance(&addr(2), &u(1)), "token: decreased allowance below zero");
+    }
+
+    #[test]
+    fn burn_from() {
+        let _host = testing::lock();
+        let t = setup();
+        t.approve(&addr(2), &u(100));
+        context::set_sender(addr(2));
+        t.burn_from(&addr(1), &u(60));
+        assert_eq!(t.total_supply(), u(940));
+        assert_eq!(t.allowance(&addr(1), &addr(2)), u(40));
+    }
+
+    // caps supply and records every call
+    struct Capped {
+        cap: U256,
+        calls: RefCell<Vec<(Address, Address, U256)>>,
+    }
+
+    impl Hooks for Capped {
+        fn before_token_transfer(&self, from: &Address, _to: &Address, amount: &U256) {
+            if *from == Address::default() && get_u256(SUPPLY_KEY) + amount > self.cap {
+                revert("token: cap exceeded");
+            }
+        }
+
+        fn after_token_transfer(&self, from: &Address, to: &Address, amount: &U256) {
+            self.calls.borrow_mut().push((from.clone(), to.clone(), amount.clone()));
+        }
+    }
+
+    #[test]
+    fn hooks() {
+        let _host = testing::lock();
+        let t = Token::with_hooks(Capped { cap: u(100), calls: RefCell::new(Vec::new()) });
+        t.mint(&addr(1), &u(100));
+        context::set_sender(addr(1));
+        t.transfer(&addr(2), &u(30));
+        t.burn(&addr(2), &u(10));
+        let zero = Address::default();
+        assert_eq!(
+            *t.hooks.calls.borrow(),
+            vec![(zero.clone(), addr(1), u(100)), (addr(1), addr(2), u(30)), (addr(2), zero, u(10))]
+        );
+        let t = std::panic::AssertUnwindSafe(t);
+        reverts(|| t.mint(&addr(1), &u(11)), "token: cap exceeded");
+    }
+
+    #[test]
+    fn allowance_keys_dont_collide() {
+        // addresses have no fixed length, owner || spender alone doesn't mark where the owner ends
+        let a = allowance_key(&Address::new(vec![1, 2]), &Address::new(vec![3]));
+        let b = allowance_key(&Address::new(vec![1]), &Address::new(vec![2, 3]));
+        assert_ne!(a, b);
+    }
+}
@@ -0,0 +1,345 @@
+use alloc::vec::Vec;
+use alloc::string::*;
+use crate::{address::Address, context, db, revert, to_hex};
+
+const OWNER_KEY: &[u8] = b"__access_owner";
+const ROLE_PREFIX: &[u8] = b"__access_role";
+const ROLE_ADMIN_PREFIX: &[u8] = b"__access_role_admin";
+const SIGNERS_KEY: &[u8] = b"__access_signers";
+const THRESHOLD_KEY: &[u8] = b"__access_threshold";
+const CONFIRM_PREFIX: &[u8] = b"__access_confirm";
+const CONFIRMS_PREFIX: &[u8] = b"__access_confirms";
+
+/// admin of every role unless `set_role_admin` is called
+pub const DEFAULT_ADMIN_ROLE: &[u8] = b"DEFAULT_ADMIN";
+
+/// revert unless sender is the owner
+#[macro_export]
+macro_rules! only_owner {
+    () => {
+        $crate::access::Ownable::only_owner()
+    };
+}
+
+/// revert unless sender has the role
+#[macro_export]
+macro_rules! only_role {
+    ($role: expr) => {
+        $crate::access::AccessControl::only_role($role)
+    };
+}
+
+/// confirm operation as sender, return from function until enough signers confirmed
+#[macro_export]
+macro_rules! only_multisig {
+    ($op: expr) => {
+        if !$crate::access::Multisig::confirm($op) {
+            return;
+        }
+    };
+    ($op: expr, $ret: expr) => {
+        if !$crate::access::Multisig::confirm($op) {
+            return $ret;
+        }
+    };
+}
+
+fn get_u32(k: &[u8]) -> u32 {
+    match db::get(k) {
+        Some(v) => {
+            let mut buf = [0u8; 4];
+            buf.copy_from_slice(&v);
+            u32::from_be_bytes(buf)
+        }
+        None => 0,
+    }
+}
+
+fn set_u32(k: &[u8], n: u32) {
+    db::insert(k, &n.to_be_bytes())
+}
+
+/// single owner stored in `wbi::db`
+pub struct Ownable;
+
+impl Ownable {
+    /// set first owner, usually called in `init`, can only be called once
+    pub fn init(owner: &Address) {
+        if db::contains_key(OWNER_KEY) {
+            revert("access: owner already initialized");
+        }
+        db::insert(OWNER_KEY, owner.as_slice());
+    }
+
+    /// current owner, zero address after ownership renounced
+    pub fn owner() -> Address {
+        match db::get(OWNER_KEY) {
+            Some(v) => Address::new(v),
+            None => Address::default(),
+        }
+    }
+
+    pub fn is_owner(a: &Address) -> bool {
+        *a != Address::default() && Self::owner() == *a
+    }
+
+    pub fn only_owner() {
+        if !Self::is_owner(&context::msg().sender) {
+            revert("access: caller is not the owner");
+        }
+    }
+
+    pub fn transfer_ownership(new_owner: &Address) {
+        Self::only_owner();
+        if *new_owner == Address::default() {
+            revert("access: new owner is the zero address");
+        }
+        db::insert(OWNER_KEY, new_owner.as_slice());
+    }
+
+    /// leave contract without owner, `only_owner` always reverts afterwards
+    pub fn renounce_ownership() {
+        Self::only_owner();
+        db::insert(OWNER_KEY, Address::default().as_slice());
+    }
+}
+
+/// role based access control, every role is administrated by an admin role
+pub struct AccessControl;
+
+impl AccessControl {
+    pub fn has_role(role: &[u8], account: &Address) -> bool {
+        db::contains_key(&db::key(ROLE_PREFIX, &[role, account.as_slice()]))
+    }
+
+    pub fn role_admin(role: &[u8]) -> Vec<u8> {
+        db::get(&db::key(ROLE_ADMIN_PREFIX, &[role])).unwrap_or_else(|| Vec::from(DEFAULT_ADMIN_ROLE))
+    }
+
+    pub fn only_role(role: &[u8]) {
+        let sender = context::msg().sender;
+        if !Self::has_role(role, &sender) {
+            let reason = format!("access: account {} is missing role {}", sender.to_string(), to_hex(role));
+            revert(&reason);
+        }
+    }
+
+    /// grant role without check, used in `init` to set up the first admin
+    pub fn setup_role(role: &[u8], account: &Address) {
+        db::insert(&db::key(ROLE_PREFIX, &[role, account.as_slice()]), &[1]);
+    }
+
+    pub fn grant_role(role: &[u8], account: &Address) {
+        Self::only_role(&Self::role_admin(role));
+        Self::setup_role(role, account);
+    }
+
+    pub fn revoke_role(role: &[u8], account: &Address) {
+        Self::only_role(&Self::role_admin(role));
+        db::remove(&db::key(ROLE_PREFIX, &[role, account.as_slice()]));
+    }
+
+    /// give up a role, account must be the sender
+    pub fn renounce_role(role: &[u8], account: &Address) {
+        if *account != context::msg().sender {
+            revert("access: can only renounce roles for self");
+        }
+        db::remove(&db::key(ROLE_PREFIX, &[role, account.as_slice()]));
+    }
+
+    /// change admin role of role, sender must have current admin role
+    pub fn set_role_admin(role: &[u8], admin: &[u8]) {
+        Self::only_role(&Self::role_admin(role));
+        db::insert(&db::key(ROLE_ADMIN_PREFIX, &[role]), admin);
+    }
+}
+
+/// m-of-n confirmation of operations by a fixed set of signers
+pub struct Multisig;
+
+impl Multisig {
+    /// set signers and threshold, usually called in `init`, can only be called once
+    pub fn setup(signers: &[Address], threshold: u32) {
+        if db::contains_key(THRESHOLD_KEY) {
+            revert("access: multisig already initialized");
+        }
+        if threshold == 0 || threshold as usize > signers.len() {
+            revert("access: invalid multisig threshold");
+        }
+        // len || address || ...
+        let mut encoded: Vec<u8> = Vec::new();
+        for s in signers {
+            encoded.extend_from_slice(&(s.as_slice().len() as u16).to_be_bytes());
+            encoded.extend_from_slice(s.as_slice());
+        }
+        db::insert(SIGNERS_KEY, &encoded);
+        set_u32(THRESHOLD_KEY, threshold);
+    }
+
+    pub fn signers() -> Vec<Address> {
+        let encoded = db::get(SIGNERS_KEY).unwrap_or_default();
+        let mut signers = Vec::new();
+        let mut i = 0;
+        while i < encoded.len() {
+            let len = u16::from_be_bytes([encoded[i], encoded[i + 1]]) as usize;
+            signers.push(Address::new(Vec::from(&encoded[i + 2..i + 2 + len])));
+            i += 2 + len;
+        }
+        signers
+    }
+
+    pub fn is_signer(a: &Address) -> bool {
+        Self::signers().iter().any(|s| s == a)
+    }
+
+    pub fn threshold() -> u32 {
+        get_u32(THRESHOLD_KEY)
+    }
+
+    pub fn confirmations(op: &[u8]) -> u32 {
+        get_u32(&db::key(CONFIRMS_PREFIX, &[op]))
+    }
+
+    pub fn is_confirmed_by(op: &[u8], signer: &Address) -> bool {
+        db::contains_key(&db::key(CONFIRM_PREFIX, &[op, signer.as_slice()]))
+    }
+
+    /// record confirmation of sender, return true and reset confirmations once threshold reached
+    pub fn confirm(op: &[u8]) -> bool {
+        let sender = context::msg().sender;
+        if !Self::is_signer(&sender) {
+            revert("access: caller is not a signer");
+        }
+        if !Self::is_confirmed_by(op, &sender) {
+            db::insert(&db::key(CONFIRM_PREFIX, &[op, sender.as_slice()]), &[1]);
+            set_u32(&db::key(CONFIRMS_PREFIX, &[op]), Self::confirmations(op) + 1);
+        }
+        if Self::confirmations(op) < Self::threshold() {
+            return false;
+        }
+        Self::clear(op);
+        true
+    }
+
+    /// withdraw confirmation of sender
+    pub fn revoke(op: &[u8]) {
+        let sender = context::msg().sender;
+        if !Self::is_confirmed_by(op, &sender) {
+            revert("access: operation not confirmed by caller");
+        }
+        db::remove(&db::key(CONFIRM_PREFIX, &[op, sender.as_slice()]));
+        set_u32(&db::key(CONFIRMS_PREFIX, &[op]), Self::confirmations(op) - 1);
+    }
+
+    fn clear(op: &[u8]) {
+        for s in Self::signers() {
+            db::remove(&db::key(CONFIRM_PREFIX, &[op, s.as_slice()]));
+        }
+        db::remove(&db::key(CONFIRMS_PREFIX, &[op]));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::{self, addr};
+
+    #[test]
+    fn ownable() {
+        let _host = testing::lock();
+        Ownable::init(&addr(1));
+        context::set_sender(addr(1));
+        only_owner!();
+        Ownable::transfer_ownership(&addr(2));
+        assert_eq!(Ownable::owner(), addr(2));
+        assert!(!Ownable::is_owner(&addr(1)));
+
+        context::set_sender(addr(2));
+        Ownable::renounce_ownership();
+        assert_eq!(Ownable::owner(), Address::default());
+        assert!(std::panic::catch_unwind(|| Ownable::only_owner()).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "caller is not the owner")]
+    fn not_owner() {
+        let _host = testing::lock();
+        Ownable::init(&addr(1));
+        context::set_sender(addr(2));
+        only_owner!();
+    }
+
+    #[test]
+    fn roles() {
+        let _host = testing::lock();
+        let minter: &[u8] = b"MINTER";
+        AccessControl::setup_role(DEFAULT_ADMIN_ROLE, &addr(1));
+        context::set_sender(addr(1));
+        AccessControl::grant_role(minter, &addr(2));
+        assert!(AccessControl::has_role(minter, &addr(2)));
+
+        context::set_sender(addr(2));
+        only_role!(minter);
+        assert!(std::panic::catch_unwind(|| AccessControl::grant_role(minter, &addr(3))).is_err());
+        AccessControl::renounce_role(minter, &addr(2));
+        assert!(!AccessControl::has_role(minter, &addr(2)));
+
+        // delegate administration of minters
+        context::set_sender(addr(1));
+        AccessControl::set_role_admin(minter, b"MINTER_ADMIN");
+        AccessControl::grant_role(b"MINTER_ADMIN", &addr(3));
+        context::set_sender(addr(3));
+        AccessControl::grant_role(minter, &addr(4));
+        AccessControl::revoke_role(minter, &addr(4));
+        assert!(!AccessControl::has_role(minter, &addr(4)));
+    }
+
+    #[test]
+    #[should_panic(expected = "is missing role")]
+    fn missing_role() {
+        let _host = testing::lock();
+        context::set_sender(addr(5));
+        only_role!(b"MINTER");
+    }
+
+    #[test]
+    fn keys_dont_collide() {
+        // "__access_role" is a prefix of "__access_role_admin", the length prefix keeps them apart
+        let admin_of: &[u8] = b"R";
+        let mut role = b"_admin".to_vec();
+        role.extend_from_slice(&[0, 0, 0, 1]);
+        role.extend_from_slice(admin_of);
+        assert_ne!(
+            db::key(ROLE_PREFIX, &[&role, &[]]),
+            db::key(ROLE_ADMIN_PREFIX, &[admin_of])
+        );
+        assert_ne!(db::key(CONFIRM_PREFIX, &[b"s"]), db::key(CONFIRMS_PREFIX, &[]));
+    }
+
+    fn withdraw() -> bool {
+        only_multisig!(b"withdraw", false);
+        true
+    }
+
+    #[test]
+    fn multisig() {
+        let _host = testing::lock();
+        Multisig::setup(&[addr(1), addr(2), addr(3)], 2);
+        context::set_sender(addr(1));
+        assert!(!withdraw());
+        // repeated confirmation doesn't count
+        assert!(!withdraw());
+        assert_eq!(Multisig::confirmations(b"withdraw"), 1);
+
+        context::set_sender(addr(3));
+        assert!(withdraw());
+        assert_eq!(Multisig::confirmations(b"withdraw"), 0);
+
+        assert!(!withdraw());
+        Multisig::revoke(b"withdraw");
+        assert_eq!(Multisig::confirmations(b"withdraw"), 0);
+
+        context::set_sender(addr(4));
+        assert!(std::panic::catch_unwind(withdraw).is_err());
+    }
+}
@@ -19,6 +19,8 @@ mod context_type {
     pub(crate) const THIS_ADDRESS: u32 = 0x644836c2; // keccak('this')
     pub(crate) const MSG_SENDER: u32 = 0xb2f2618c; // keccak('msg.sender')
     pub(crate) const MSG_VALUE: u32 = 0x6db8129b; // keccak('msg.value')
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) const CALL_DEPTH: u32 = 0x7ca271ec; // keccak('call.depth')
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -28,10 +30,21 @@ pub fn this() -> Address {
 
 #[cfg(not(target_arch = "wasm32"))]
 pub fn this() -> Address {
-    Address::default()
+    MOCK.with(|m| m.borrow().this.clone())
 }
 
 
+/// number of contract calls below current one, 0 when called by a transaction directly
+#[cfg(target_arch = "wasm32")]
+pub fn call_depth() -> u64 {
+    unsafe { _context(context_type::CALL_DEPTH as u64, 0) }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn call_depth() -> u64 {
+    MOCK.with(|m| m.borrow().depth)
+}
+
 pub fn msg() -> Msg  {
     Msg::new()
 }
@@ -46,7 +59,13 @@ pub struct Msg {
 impl Msg {
     #[cfg(not(target_arch = "wasm32"))]    
     fn new() -> Msg {
-        Msg::default()
+        MOCK.with(|m| {
+            let m = m.borrow();
+            Msg {
+                sender: m.sender.clone(),
+                value: m.value.clone(),
+            }
+        })
     }
 
     #[cfg(target_arch = "wasm32")]    
@@ -59,3 +78,62 @@ impl Msg {
         }
     }    
 }
+
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+struct Mock {
+    this: Address,
+    sender: Address,
+    value: U256,
+    depth: u64,
+}
+
+// context of native mock host, per thread so that parallel tests don't interfere
+#[cfg(not(target_arch = "wasm32"))]
+std::thread_local! {
+    static MOCK: core::cell::RefCell<Mock> = core::cell::RefCell::new(Mock::default());
+}
+
+/// set address returned by `this()` in native tests
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set_this(a: Address) {
+    MOCK.with(|m| m.borrow_mut().this = a)
+}
+
+/// set `msg().sender` in native tests
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set_sender(a: Address) {
+    MOCK.with(|m| m.borrow_mut().sender = a)
+}
+
+/// set `msg().value` in native tests
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set_value(v: U256) {
+    MOCK.with(|m| m.borrow_mut().value = v)
+}
+
+/// simulate a call from `sender` into current contract, e.g. a callback of an external contract,
+/// `msg()` and `call_depth()` are restored when `f` returns or panics
+#[cfg(not(target_arch = "wasm32"))]
+pub fn mock_call<R, F: FnOnce() -> R>(sender: Address, f: F) -> R {
+    struct Frame(Address, U256);
+
+    impl Drop for Frame {
+        fn drop(&mut self) {
+            MOCK.with(|m| {
+                let mut m = m.borrow_mut();
+                m.sender = core::mem::take(&mut self.0);
+                m.value = core::mem::take(&mut self.1);
+                m.depth -= 1;
+            })
+        }
+    }
+
+    let _frame = MOCK.with(|m| {
+        let mut m = m.borrow_mut();
+        m.depth += 1;
+        Frame(core::mem::replace(&mut m.sender, sender), core::mem::take(&mut m.value))
+    });
+    f()
+}
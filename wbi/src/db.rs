@@ -26,6 +26,18 @@ pub fn get(key: &[u8]) -> Option<Vec<u8>>{
     }
 }
 
+/// storage key of `parts` under `prefix`, prefix and every part are written as u32 big endian length || bytes
+/// so that keys of different prefixes or parts never collide
+pub fn key(prefix: &[u8], parts: &[&[u8]]) -> Vec<u8> {
+    let mut k = Vec::new();
+    for p in core::iter::once(&prefix).chain(parts.iter()) {
+        assert!(p.len() <= u32::MAX as usize, "db: key part too long");
+        k.extend_from_slice(&(p.len() as u32).to_be_bytes());
+        k.extend_from_slice(p);
+    }
+    k
+}
+
 pub fn remove(key: &[u8]) {
     let k = to_vec!(key);
     __db(Op::REMOVE as u64, forget!(k), 0);
@@ -54,8 +66,21 @@ lazy_static! {
 }
 
 
+/// remove everything from storage of native mock host
+#[cfg(all(not(target_arch = "wasm32"), any(test, feature = "testing")))]
+pub(crate) fn clear() {
+    _mem_db.write().unwrap().clear()
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub fn _db(op: u64, left: u64, right: u64) -> u64 {
+    crate::gas::charge(|c| match op {
+        0 => c.db_insert,
+        1 => c.db_get,
+        2 => c.db_remove,
+        3 => c.db_contains,
+        _ => 0
+    });
     let k: Vec<u8> = remember!(left);
     
     let ret = match op {
@@ -0,0 +1,115 @@
+use alloc::vec::Vec;
+use alloc::string::*;
+
+// `_event` is not part of the host abi of every chain, only hosts that provide it
+// should enable the `host-event` feature
+extern "C" {
+    #[cfg(all(target_arch = "wasm32", feature = "host-event"))]
+    pub fn _event(name: u64, fields: u64);
+}
+
+/// emitted event, every field is abi encoded by caller, e.g. `U256::bytes32()` or `Address::as_slice()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub name: String,
+    pub fields: Vec<Vec<u8>>,
+}
+
+// fields are passed to host as u32 big endian length prefixed bytes
+#[cfg(any(not(target_arch = "wasm32"), feature = "host-event"))]
+fn encode_fields(fields: &[&[u8]]) -> Vec<u8> {
+    let mut v = Vec::new();
+    for f in fields {
+        v.extend_from_slice(&(f.len() as u32).to_be_bytes());
+        v.extend_from_slice(f);
+    }
+    v
+}
+
+/// emit an event, written to the host log as `event Name(0x.., 0x..)`
+#[cfg(all(target_arch = "wasm32", not(feature = "host-event")))]
+pub fn emit(name: &str, fields: &[&[u8]]) {
+    crate::log(&log_line(name, fields))
+}
+
+/// emit an event through the `_event` host function, or the mock host in native tests
+#[cfg(any(not(target_arch = "wasm32"), feature = "host-event"))]
+pub fn emit(name: &str, fields: &[&[u8]]) {
+    let n = String::from(name);
+    let f = encode_fields(fields);
+    __event(forget!(n), forget!(f))
+}
+
+#[cfg(any(all(target_arch = "wasm32", not(feature = "host-event")), test))]
+fn log_line(name: &str, fields: &[&[u8]]) -> String {
+    let mut s = format!("event {}(", name);
+    for (i, f) in fields.iter().enumerate() {
+        if i > 0 {
+            s.push_str(", ");
+        }
+        s.push_str("0x");
+        s.push_str(&crate::to_hex(f));
+    }
+    s.push(')');
+    s
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "host-event"))]
+#[inline]
+fn __event(name: u64, fields: u64) {
+    unsafe { _event(name, fields) }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[inline]
+fn __event(name: u64, fields: u64) {
+    _event(name, fields)
+}
+
+// events emitted to native mock host, per thread so that parallel tests don't interfere
+#[cfg(not(target_arch = "wasm32"))]
+std::thread_local! {
+    static EVENTS: core::cell::RefCell<Vec<Event>> = const { core::cell::RefCell::new(Vec::new()) };
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn _event(name: u64, fields: u64) {
+    let name: String = remember!(name);
+    let encoded: Vec<u8> = remember!(fields);
+    let mut fields = Vec::new();
+    let mut i = 0;
+    while i < encoded.len() {
+        let mut len = [0u8; 4];
+        len.copy_from_slice(&encoded[i..i + 4]);
+        let len = u32::from_be_bytes(len) as usize;
+        fields.push(encoded[i + 4..i + 4 + len].to_vec());
+        i += 4 + len;
+    }
+    EVENTS.with(|e| e.borrow_mut().push(Event { name, fields }))
+}
+
+/// take events emitted in native tests since last call
+#[cfg(not(target_arch = "wasm32"))]
+pub fn take_events() -> Vec<Event> {
+    EVENTS.with(|e| core::mem::take(&mut *e.borrow_mut()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn emit_and_take() {
+        emit("Transfer", &[b"from", b"", b"\x01"]);
+        let events = take_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].name, "Transfer");
+        assert_eq!(events[0].fields, vec![b"from".to_vec(), vec![], vec![1]]);
+        assert!(take_events().is_empty());
+    }
+
+    #[test]
+    fn log_line_of_event() {
+        assert_eq!(log_line("Transfer", &[b"\x01\x02", b""]), "event Transfer(0x0102, 0x)");
+    }
+}
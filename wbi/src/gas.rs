@@ -0,0 +1,171 @@
+extern "C" {
+    #[cfg(target_arch = "wasm32")]
+    pub fn _gas(op: u64, amount: u64) -> u64;
+}
+
+enum Op {
+    Left = 0, Consume = 1
+}
+
+/// remaining gas of current call
+pub fn gas_left() -> u64 {
+    __gas(Op::Left as u64, 0)
+}
+
+/// charge extra gas, e.g. for every round of a loop, aborts with `OutOfGas` when limit exceeded
+pub fn consume(amount: u64) {
+    __gas(Op::Consume as u64, amount);
+}
+
+#[cfg(target_arch = "wasm32")]
+#[inline]
+fn __gas(op: u64, amount: u64) -> u64 {
+    unsafe { _gas(op, amount) }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[inline]
+fn __gas(op: u64, amount: u64) -> u64 {
+    _gas(op, amount)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutOfGas {
+    pub limit: u64,
+    pub required: u64,
+}
+
+/// cost of host operations charged by the native mock host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CostTable {
+    pub db_get: u64,
+    pub db_insert: u64,
+    pub db_remove: u64,
+    pub db_contains: u64,
+    pub u256: u64,
+    pub log: u64,
+}
+
+impl Default for CostTable {
+    fn default() -> CostTable {
+        CostTable {
+            db_get: 50,
+            db_insert: 100,
+            db_remove: 50,
+            db_contains: 20,
+            u256: 5,
+            log: 10,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+struct Meter {
+    limit: Option<u64>,
+    used: u64,
+    costs: CostTable,
+}
+
+// meter is per thread so that parallel tests don't drain each other
+#[cfg(not(target_arch = "wasm32"))]
+std::thread_local! {
+    static METER: core::cell::RefCell<Meter> = core::cell::RefCell::new(Meter::default());
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn _gas(op: u64, amount: u64) -> u64 {
+    METER.with(|m| {
+        let mut m = m.borrow_mut();
+        match op {
+            0 => match m.limit {
+                Some(limit) => limit - m.used,
+                None => u64::MAX,
+            },
+            1 => {
+                let required = m.used.saturating_add(amount);
+                if let Some(limit) = m.limit {
+                    if required > limit {
+                        // reset so later code in the same thread is not affected
+                        m.used = 0;
+                        m.limit = None;
+                        panic!("{:?}", OutOfGas { limit, required });
+                    }
+                }
+                m.used = required;
+                0
+            }
+            _ => 0,
+        }
+    })
+}
+
+/// limit gas of the mock host, `None` for unlimited, resets used gas
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set_limit(limit: Option<u64>) {
+    METER.with(|m| {
+        let mut m = m.borrow_mut();
+        m.limit = limit;
+        m.used = 0;
+    })
+}
+
+/// gas used since last `set_limit`
+#[cfg(not(target_arch = "wasm32"))]
+pub fn gas_used() -> u64 {
+    METER.with(|m| m.borrow().used)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set_costs(costs: CostTable) {
+    METER.with(|m| m.borrow_mut().costs = costs)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn costs() -> CostTable {
+    METER.with(|m| m.borrow().costs)
+}
+
+/// charge the cost of a host operation selected from cost table
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn charge<F: FnOnce(&CostTable) -> u64>(f: F) {
+    consume(f(&costs()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn consume_and_left() {
+        set_limit(Some(1000));
+        consume(300);
+        assert_eq!(gas_left(), 700);
+        assert_eq!(gas_used(), 300);
+        set_limit(None);
+        assert_eq!(gas_left(), u64::MAX);
+    }
+
+    #[test]
+    fn host_ops_are_charged() {
+        let _host = testing::lock();
+        set_costs(CostTable { db_insert: 7, db_contains: 3, ..CostTable::default() });
+        set_limit(Some(100));
+        crate::db::insert(b"gas-test", b"v");
+        assert!(crate::db::contains_key(b"gas-test"));
+        assert_eq!(gas_used(), 10);
+        set_costs(CostTable::default());
+        set_limit(None);
+    }
+
+    #[test]
+    #[should_panic(expected = "OutOfGas")]
+    fn out_of_gas() {
+        let _host = testing::lock();
+        set_limit(Some(1000));
+        loop {
+            crate::db::insert(b"gas-loop", b"v");
+        }
+    }
+}
@@ -92,6 +92,12 @@ pub mod u256;
 pub mod db;
 pub mod address;
 pub mod context;
+pub mod gas;
+pub mod access;
+pub mod reentrancy;
+pub mod event;
+#[cfg(all(not(target_arch = "wasm32"), any(test, feature = "testing")))]
+pub mod testing;
 
 use alloc::{vec::Vec};
 use alloc::string::*;
@@ -139,11 +145,17 @@ extern "C" {
 
 #[cfg(not(target_arch = "wasm32"))]
 pub fn _log(a: u64) {
+    gas::charge(|c| c.log);
     let p: String = remember!(a);    
     println!("{}", p);
     mem::forget(p);
 }
 
+/// abort current call with a reason, all state changes are reverted by host
+pub fn revert(reason: &str) -> ! {
+    panic!("{}", reason)
+}
+
 pub fn log(s: &str) {
     unsafe {
         let raw_cloned = String::from_raw_parts(
@@ -0,0 +1,84 @@
+use crate::{db, revert};
+
+// reserved storage slot, set while a guarded function is running
+const GUARD_KEY: &[u8] = b"__reentrancy_guard";
+
+/// revert when current function is entered again before it returns, e.g. by a callback
+#[macro_export]
+macro_rules! nonreentrant {
+    () => {
+        let _nonreentrant = $crate::reentrancy::NonReentrant::enter();
+    };
+}
+
+/// guard of a non-reentrant function, the slot is released on drop
+pub struct NonReentrant {
+    _private: (),
+}
+
+impl NonReentrant {
+    pub fn enter() -> NonReentrant {
+        if db::contains_key(GUARD_KEY) {
+            revert("reentrancy: reentrant call");
+        }
+        db::insert(GUARD_KEY, &[1]);
+        NonReentrant { _private: () }
+    }
+
+    pub fn entered() -> bool {
+        db::contains_key(GUARD_KEY)
+    }
+}
+
+impl Drop for NonReentrant {
+    fn drop(&mut self) {
+        db::remove(GUARD_KEY);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{address::Address, context, testing};
+
+    // calls back into itself through `attacker` if given
+    fn withdraw(attacker: Option<Address>) -> u64 {
+        nonreentrant!();
+        match attacker {
+            Some(a) => context::mock_call(a, || withdraw(None)),
+            None => context::call_depth(),
+        }
+    }
+
+    #[test]
+    fn call_depth() {
+        assert_eq!(context::call_depth(), 0);
+        let a = Address::new(vec![1; 20]);
+        let d = context::mock_call(a.clone(), || {
+            assert_eq!(context::msg().sender, a);
+            context::mock_call(Address::default(), context::call_depth)
+        });
+        assert_eq!(d, 2);
+        assert_eq!(context::call_depth(), 0);
+        assert_eq!(context::msg().sender, Address::default());
+    }
+
+    #[test]
+    fn sequential_calls() {
+        let _host = testing::lock();
+        assert_eq!(withdraw(None), 0);
+        assert_eq!(withdraw(None), 0);
+        assert!(!super::NonReentrant::entered());
+    }
+
+    #[test]
+    fn reentrant_callback() {
+        let _host = testing::lock();
+        let r = std::panic::catch_unwind(|| withdraw(Some(Address::new(vec![2; 20]))));
+        let err = r.unwrap_err();
+        assert_eq!(err.downcast_ref::<String>().unwrap(), "reentrancy: reentrant call");
+        // guard and context are released after the call is aborted
+        assert!(!super::NonReentrant::entered());
+        assert_eq!(context::call_depth(), 0);
+        assert_eq!(withdraw(None), 0);
+    }
+}
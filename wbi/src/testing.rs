@@ -0,0 +1,38 @@
+//! helpers for native tests of contracts, enabled by the `testing` feature
+
+use std::sync::{Mutex, MutexGuard};
+
+use crate::{address::Address, context, db, event, u256::U256};
+use alloc::string::*;
+
+static HOST: Mutex<()> = Mutex::new(());
+
+/// take the mock host for a test, storage of native mock host is shared by all threads,
+/// tests holding the guard run one at a time on empty storage
+pub fn lock() -> MutexGuard<'static, ()> {
+    // a test failing with the guard held poisons the mutex, the storage is cleared anyway
+    let guard = HOST.lock().unwrap_or_else(|e| e.into_inner());
+    db::clear();
+    guard
+}
+
+/// end the setup of a test, drop events emitted so far and call following functions as `sender`
+pub fn begin(sender: &Address) {
+    event::take_events();
+    context::set_sender(sender.clone());
+}
+
+/// address of 20 bytes `b`
+pub fn addr(b: u8) -> Address {
+    Address::new(vec![b; 20])
+}
+
+pub fn u(n: u64) -> U256 {
+    U256::from(n)
+}
+
+/// assert that `f` reverts with `reason`
+pub fn reverts<F: FnOnce() -> R + std::panic::UnwindSafe, R>(f: F, reason: &str) {
+    let err = std::panic::catch_unwind(f).err().expect("expected revert");
+    assert_eq!(err.downcast_ref::<String>().unwrap(), reason);
+}
@@ -93,6 +93,7 @@ fn _u256(op: u64, l0: u64, l1: u64, l2: u64, l3: u64, r0: u64, r1: u64, r2: u64,
         }};
     }
 
+    crate::gas::charge(|c| c.u256);
     let l_buf = as_u256!(l0, l1, l2, l3);
     let r_buf = as_u256!(r0, r1, r2, r3);
 
@@ -353,6 +354,18 @@ impl U256 {
         U256(d)
     }
 
+    /// decode big endian bytes, at most 32 bytes
+    pub fn from_bytes(b: &[u8]) -> U256 {
+        assert!(b.len() <= 32, "u256 overflow");
+        let mut buf = [0u8; 32];
+        buf[32 - b.len()..].copy_from_slice(b);
+        let mut d = ZEROS;
+        for i in 0..U256_MAGS {
+            d[i] = u32::from_be_bytes([buf[i * 4], buf[i * 4 + 1], buf[i * 4 + 2], buf[i * 4 + 3]]);
+        }
+        U256(d)
+    }
+
     pub fn new(data: [u32; U256_MAGS]) -> U256 {
         U256(data)
     }
@@ -602,3 +615,17 @@ mod primitive {
         return 0;
     }
 }
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_bytes() {
+        let u: U256 = "1234567890123456789012345".parse().unwrap();
+        assert_eq!(U256::from_bytes(&u.bytes32()), u);
+        assert_eq!(U256::from_bytes(&u.to_vec()), u);
+        assert_eq!(U256::from_bytes(&[]), U256::zero());
+    }
+}